@@ -0,0 +1,500 @@
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::fs;
+use std::sync::atomic::AtomicBool;
+use std::time::Duration;
+
+use crate::ai_context;
+use crate::ai_provider::{self, AiMessage, AiProvider, CancelToken, GenerateRequest};
+use crate::file_io::{self, FileVersion};
+use crate::local_history::{self, HistoryDiff};
+use crate::project_config::ProjectLayout;
+use crate::refactor;
+use crate::sandbox::{FsError, Sandbox};
+use crate::search::{self, SearchOptions};
+use crate::usage::UsageTotals;
+use crate::workspace_edit::{EditOperation, TextEdit};
+
+const MAX_STEPS: usize = 15;
+// Lo que ve el modelo de cada resultado; el resto se recorta
+const MAX_RESULT_CHARS: usize = 12_000;
+const MAX_READ_LINES: usize = 400;
+const MAX_SEARCH_RESULTS: usize = 40;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AgentStep {
+    pub step: usize,
+    pub tool: String,
+    pub args: Value,
+    // Archivo o búsqueda sobre la que actuó la herramienta (para el registro de actividad)
+    pub target: String,
+    // Resumen del resultado, no el resultado completo que recibió el modelo
+    pub result: String,
+    pub error: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProposedEdit {
+    // Relativa a la raíz del proyecto
+    pub path: String,
+    pub description: String,
+    pub is_new: bool,
+    // Versión en disco cuando el agente la leyó: si cambia antes de aplicar, hay conflicto
+    pub expected_version: Option<FileVersion>,
+    pub diff: HistoryDiff,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AgentRun {
+    pub id: String,
+    pub task: String,
+    pub summary: String,
+    pub steps: Vec<AgentStep>,
+    pub edits: Vec<ProposedEdit>,
+    // "finished", "maxSteps" o "cancelled"
+    pub status: String,
+    pub usage: UsageTotals,
+}
+
+impl AgentRun {
+    /// Operaciones para aplicar las ediciones de `paths` (todas si es `None`) en un solo lote.
+    pub fn operations(&self, paths: Option<&[String]>) -> Vec<EditOperation> {
+        self.edits.iter()
+            .filter(|edit| paths.map(|p| p.contains(&edit.path)).unwrap_or(true))
+            .map(|edit| {
+                if edit.is_new {
+                    return EditOperation::CreateFile {
+                        path: edit.path.clone(),
+                        content: edit.diff.new_content.clone(),
+                        overwrite: false,
+                    };
+                }
+
+                // Reemplazo del archivo completo: del inicio al final de la última línea
+                let old = &edit.diff.old_content;
+                let last_line = old.split('\n').count();
+                let last_column = old.rsplit('\n').next().unwrap_or("").encode_utf16().count() + 1;
+                EditOperation::Edit {
+                    path: edit.path.clone(),
+                    edits: vec![TextEdit {
+                        start_line: 1,
+                        start_column: 1,
+                        end_line: last_line,
+                        end_column: last_column,
+                        new_text: edit.diff.new_content.clone(),
+                    }],
+                    expected_version: edit.expected_version.clone(),
+                }
+            })
+            .collect()
+    }
+}
+
+struct Proposal {
+    description: String,
+    original: Option<String>,
+    expected_version: Option<FileVersion>,
+    content: String,
+}
+
+/// Las herramientas que puede usar el agente. Solo leen el proyecto: las ediciones
+/// quedan como propuestas en memoria hasta que el usuario las aplica.
+pub struct AgentTools {
+    sandbox: Sandbox,
+    layout: ProjectLayout,
+    proposals: BTreeMap<String, Proposal>,
+}
+
+impl AgentTools {
+    pub fn new(sandbox: Sandbox) -> Result<Self, String> {
+        let layout = ProjectLayout::load(sandbox.root())?;
+        Ok(AgentTools {
+            sandbox,
+            layout,
+            proposals: BTreeMap::new(),
+        })
+    }
+
+    fn relative(&self, path: &str) -> Result<(std::path::PathBuf, String), String> {
+        let resolved = self.sandbox.resolve(path)?;
+        if self.sandbox.is_external(&resolved) {
+            return Err(FsError::OutsideProject(path.to_string()).into());
+        }
+        let relative = resolved.strip_prefix(self.sandbox.root())
+            .unwrap_or(&resolved)
+            .to_string_lossy()
+            .replace('\\', "/");
+        if relative.is_empty() || relative.split('/').any(|part| part == "node_modules" || part.starts_with('.')) {
+            return Err(format!("Ruta no permitida para el agente: {}", path));
+        }
+        Ok((resolved, relative))
+    }
+
+    /// Ejecuta una herramienta. Devuelve (destino, resultado para el modelo).
+    fn call(&mut self, tool: &str, args: &Value) -> Result<(String, String), String> {
+        let arg = |name: &str| args[name].as_str().unwrap_or("").to_string();
+
+        match tool {
+            "read_file" => {
+                let path = arg("path");
+                let (_, relative) = self.relative(&path)?;
+                let content = self.current_content(&relative)?
+                    .ok_or_else(|| format!("El archivo no existe: {}", relative))?;
+
+                let lines: Vec<&str> = content.lines().collect();
+                let start = args["start_line"].as_u64().unwrap_or(1).max(1);
+                if start > lines.len().max(1) as u64 {
+                    return Err(format!(
+                        "start_line {} fuera de rango: {} tiene {} líneas",
+                        start, relative, lines.len()
+                    ));
+                }
+                let start = start as usize;
+                let end = args["end_line"].as_u64()
+                    .map(|e| e as usize)
+                    .unwrap_or(lines.len())
+                    .min(start.saturating_add(MAX_READ_LINES - 1))
+                    .min(lines.len());
+                let mut numbered: Vec<String> = (start..=end)
+                    .filter_map(|n| lines.get(n - 1).map(|line| format!("{:>4} | {}", n, line)))
+                    .collect();
+                if end < lines.len() {
+                    numbered.push(format!("… ({} líneas en total; usa start_line/end_line)", lines.len()));
+                }
+                Ok((relative, numbered.join("\n")))
+            }
+            "search_project" => {
+                let query = arg("query");
+                if query.is_empty() {
+                    return Err("Falta `query`".to_string());
+                }
+                let options = SearchOptions {
+                    regex: args["regex"].as_bool().unwrap_or(false),
+                    context_lines: 0,
+                    max_results: MAX_SEARCH_RESULTS,
+                    ..Default::default()
+                };
+                let matcher = search::build_matcher(&query, &options)?;
+                let mut lines = Vec::new();
+                let summary = search::search_content(
+                    self.sandbox.root(), "agent", &matcher, &options, &AtomicBool::new(false),
+                    |file| {
+                        for m in file.matches {
+                            // Varias coincidencias en la misma línea se muestran una vez
+                            let line = format!("{}:{}: {}", file.relative_path, m.line, m.text.trim());
+                            if lines.last() != Some(&line) {
+                                lines.push(line);
+                            }
+                        }
+                    },
+                )?;
+                if lines.is_empty() {
+                    lines.push("Sin resultados".to_string());
+                } else if summary.truncated {
+                    lines.push(format!("… (más de {} resultados; afina la búsqueda)", MAX_SEARCH_RESULTS));
+                }
+                Ok((query, lines.join("\n")))
+            }
+            "list_commands" => {
+                let commands_dir = self.layout.commands_dir.trim_end_matches('/').to_string();
+                let type_re = Regex::new(r#"\btype\s*:\s*["'`](\w+)["'`]"#).unwrap();
+                let description_re = Regex::new(r#"\bdescription\s*:\s*["'`]([^"'`]*)["'`]"#).unwrap();
+
+                let mut commands = Vec::new();
+                for (path, relative) in search::project_files(self.sandbox.root())? {
+                    if !relative.starts_with(&format!("{}/", commands_dir)) {
+                        continue;
+                    }
+                    let Ok(content) = fs::read_to_string(&path) else { continue };
+                    let lines: Vec<String> = content.lines().map(|l| l.to_string()).collect();
+                    let Some((_, _, name)) = refactor::find_command_name(&lines) else { continue };
+
+                    let kind = type_re.captures(&content).map(|c| c[1].to_string()).unwrap_or_default();
+                    let description = description_re.captures(&content).map(|c| c[1].to_string()).unwrap_or_default();
+                    commands.push(format!("{} [{}] {} — {}", name, kind, relative, description));
+                }
+                commands.sort();
+                Ok((commands_dir, commands.join("\n")))
+            }
+            "read_prisma_schema" => {
+                let schema_path = self.layout.prisma_schema_path(self.sandbox.root());
+                let schema = fs::read_to_string(&schema_path)
+                    .map_err(|_| format!("No se encontró el schema de Prisma: {}", self.layout.prisma_schema))?;
+                let definitions = ai_context::prisma_definitions(&schema);
+
+                let model = arg("model");
+                if !model.is_empty() {
+                    let found = definitions.iter()
+                        .find(|(name, _)| name.eq_ignore_ascii_case(&model))
+                        .map(|(_, body)| body.clone())
+                        .ok_or_else(|| format!("No existe el modelo {}", model))?;
+                    return Ok((self.layout.prisma_schema.clone(), found));
+                }
+
+                // El schema completo si cabe; si no, la lista para pedir modelos concretos
+                let result = if schema.len() <= MAX_RESULT_CHARS {
+                    schema
+                } else {
+                    let names: Vec<&str> = definitions.iter().map(|(name, _)| name.as_str()).collect();
+                    format!("El schema es grande; pide un modelo con `model`. Modelos y enums: {}", names.join(", "))
+                };
+                Ok((self.layout.prisma_schema.clone(), result))
+            }
+            "propose_edit" => {
+                let path = arg("path");
+                let (_, relative) = self.relative(&path)?;
+                let current = self.current_content(&relative)?;
+
+                let content = if let Some(content) = args["content"].as_str() {
+                    content.replace("\r\n", "\n")
+                } else if let Some(replacements) = args["replacements"].as_array() {
+                    let mut content = current.clone()
+                        .ok_or_else(|| format!("El archivo no existe: {}; usa `content`", relative))?;
+                    for replacement in replacements {
+                        let find = replacement["find"].as_str().unwrap_or("").replace("\r\n", "\n");
+                        let replace = replacement["replace"].as_str().unwrap_or("").replace("\r\n", "\n");
+                        match content.matches(&find).count() {
+                            1 if !find.is_empty() => content = content.replacen(&find, &replace, 1),
+                            0 => return Err(format!("No se encontró el texto a reemplazar en {}: {:?}", relative, find)),
+                            _ => return Err(format!("El texto a reemplazar aparece varias veces en {}; incluye más contexto", relative)),
+                        }
+                    }
+                    content
+                } else {
+                    return Err("Falta `content` o `replacements`".to_string());
+                };
+
+                if current.as_deref() == Some(content.as_str()) {
+                    return Err(format!("La edición no cambia {}", relative));
+                }
+
+                let diff = local_history::diff_versions(current.as_deref().unwrap_or("").as_bytes(), content.as_bytes());
+                let description = arg("description");
+                match self.proposals.get_mut(&relative) {
+                    // Una segunda edición del mismo archivo se suma a la primera
+                    Some(proposal) => {
+                        proposal.content = content;
+                        if !description.is_empty() {
+                            proposal.description = format!("{}; {}", proposal.description, description);
+                        }
+                    }
+                    None => {
+                        let disk = self.sandbox.root().join(&relative);
+                        let expected_version = file_io::read_versioned(&disk).ok().map(|f| f.version);
+                        self.proposals.insert(relative.clone(), Proposal {
+                            description,
+                            original: current,
+                            expected_version,
+                            content,
+                        });
+                    }
+                }
+                Ok((relative.clone(), format!("Edición registrada para {} (+{} -{} líneas)", relative, diff.added, diff.removed)))
+            }
+            other => Err(format!("Herramienta desconocida: {}", other)),
+        }
+    }
+
+    // Contenido que ve el agente: su propia propuesta si ya editó el archivo, si no el disco
+    fn current_content(&self, relative: &str) -> Result<Option<String>, String> {
+        if let Some(proposal) = self.proposals.get(relative) {
+            return Ok(Some(proposal.content.clone()));
+        }
+        let path = self.sandbox.root().join(relative);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let file = file_io::read_versioned(&path)?;
+        if file.binary {
+            return Err(format!("{} es un archivo binario", relative));
+        }
+        Ok(Some(file.content))
+    }
+
+    fn edits(&self) -> Vec<ProposedEdit> {
+        self.proposals.iter()
+            .map(|(path, proposal)| ProposedEdit {
+                path: path.clone(),
+                description: proposal.description.clone(),
+                is_new: proposal.original.is_none(),
+                expected_version: proposal.expected_version.clone(),
+                diff: local_history::diff_versions(
+                    proposal.original.as_deref().unwrap_or("").as_bytes(),
+                    proposal.content.as_bytes(),
+                ),
+            })
+            .collect()
+    }
+}
+
+fn system_prompt(layout: &ProjectLayout) -> String {
+    format!(
+        r#"You are a coding agent working on a Discord bot written in TypeScript (discord.js, Prisma).
+Project layout: sources in `{src}`, commands in `{commands}`, events in `{events}`, Prisma schema in `{prisma}`.
+Commands export an object typed `CommandSlash` or `CommandMessage` from the project's core types.
+
+You can use these tools:
+- read_file {{"path": string, "start_line"?: number, "end_line"?: number}}: file content with line numbers.
+- search_project {{"query": string, "regex"?: boolean}}: text search across the project.
+- list_commands {{}}: every command with its name, type, file and description.
+- read_prisma_schema {{"model"?: string}}: the Prisma schema, or a single model/enum.
+- propose_edit {{"path": string, "description": string, "content"?: string, "replacements"?: [{{"find": string, "replace": string}}]}}:
+  propose the full new content of a file (or exact find/replace pairs). Nothing is written: the user reviews every edit.
+- finish {{"summary": string}}: stop and summarize the proposed changes for the user.
+
+Read the relevant files before proposing edits and follow the existing code style.
+Answer every message with exactly one JSON object and nothing else:
+{{"thought": "brief reasoning", "tool": "<tool name>", "args": {{ ... }}}}"#,
+        src = layout.src_dir,
+        commands = layout.commands_dir,
+        events = layout.events_dir,
+        prisma = layout.prisma_schema,
+    )
+}
+
+// El primer objeto JSON de la respuesta (los modelos a veces lo envuelven en ```json)
+fn parse_action(text: &str) -> Option<(String, Value)> {
+    let start = text.find('{')?;
+    let end = text.rfind('}')?;
+    let value: Value = serde_json::from_str(text.get(start..=end)?).ok()?;
+    let tool = value["tool"].as_str()?.to_string();
+    let args = if value["args"].is_object() { value["args"].clone() } else { json!({}) };
+    Some((tool, args))
+}
+
+fn truncate(text: &str, max: usize) -> String {
+    if text.chars().count() <= max {
+        return text.to_string();
+    }
+    let cut: String = text.chars().take(max).collect();
+    format!("{}\n… (recortado)", cut)
+}
+
+/// Ejecuta el bucle del agente: el modelo pide herramientas hasta llamar a `finish`
+/// (o agotar los pasos). `on_step` se llama después de cada herramienta y `record`
+/// con cada petición al mandarla y con sus tokens al llegar la respuesta.
+#[allow(clippy::too_many_arguments)]
+pub async fn run(
+    provider: &dyn AiProvider,
+    tools: &mut AgentTools,
+    run_id: &str,
+    task: &str,
+    current_file: Option<&str>,
+    thinking: bool,
+    cancel: &CancelToken,
+    record: &(dyn Fn(&UsageTotals) + Sync),
+    mut on_step: impl FnMut(&AgentStep),
+) -> Result<AgentRun, String> {
+    let mut first = format!("Task: {}", task);
+    if let Some(file) = current_file {
+        first.push_str(&format!("\nThe user has this file open: {}", file));
+    }
+
+    let mut messages = vec![
+        AiMessage { role: "system".to_string(), text: system_prompt(&tools.layout) },
+        AiMessage { role: "user".to_string(), text: first },
+    ];
+    let mut steps = Vec::new();
+    let mut summary = String::new();
+    let mut status = "maxSteps";
+    let mut usage = UsageTotals::default();
+
+    for step in 1..=MAX_STEPS {
+        // Cancelado antes de empezar o durante una herramienta: no mandar otra petición
+        if cancel.is_cancelled() {
+            status = "cancelled";
+            break;
+        }
+        let request = GenerateRequest {
+            messages: messages.clone(),
+            temperature: 0.2,
+            max_output_tokens: 8192,
+            thinking,
+            timeout: Duration::from_secs(90),
+        };
+        record(&UsageTotals::request(None));
+        let response = tokio::select! {
+            response = ai_provider::generate(provider, &request) => response?,
+            _ = cancel.cancelled() => {
+                status = "cancelled";
+                break;
+            }
+        };
+        usage.add_request(response.usage.as_ref());
+        record(&UsageTotals::tokens(response.usage.as_ref()));
+        messages.push(AiMessage { role: "assistant".to_string(), text: response.text.clone() });
+
+        let Some((tool, args)) = parse_action(&response.text) else {
+            let agent_step = AgentStep {
+                step,
+                tool: "invalid".to_string(),
+                args: json!({}),
+                target: String::new(),
+                result: "La respuesta no era un objeto JSON con `tool`".to_string(),
+                error: true,
+            };
+            on_step(&agent_step);
+            steps.push(agent_step);
+            messages.push(AiMessage {
+                role: "user".to_string(),
+                text: "Invalid reply. Answer with exactly one JSON object: {\"thought\": ..., \"tool\": ..., \"args\": {...}}".to_string(),
+            });
+            continue;
+        };
+
+        if tool == "finish" {
+            summary = args["summary"].as_str().unwrap_or("").to_string();
+            status = "finished";
+            let agent_step = AgentStep {
+                step,
+                tool,
+                args,
+                target: String::new(),
+                result: format!("{} ediciones propuestas", tools.proposals.len()),
+                error: false,
+            };
+            on_step(&agent_step);
+            steps.push(agent_step);
+            break;
+        }
+
+        let (target, result, error) = match tools.call(&tool, &args) {
+            Ok((target, result)) => (target, result, false),
+            Err(e) => (args["path"].as_str().unwrap_or("").to_string(), format!("Error: {}", e), true),
+        };
+        messages.push(AiMessage {
+            role: "user".to_string(),
+            text: format!("Result of {}:\n{}", tool, truncate(&result, MAX_RESULT_CHARS)),
+        });
+
+        let agent_step = AgentStep {
+            step,
+            tool,
+            args,
+            target,
+            result: truncate(&result, 300),
+            error,
+        };
+        on_step(&agent_step);
+        steps.push(agent_step);
+    }
+    // Cancelado durante la última herramienta
+    if status == "maxSteps" && cancel.is_cancelled() {
+        status = "cancelled";
+    }
+
+    Ok(AgentRun {
+        id: run_id.to_string(),
+        task: task.to_string(),
+        summary,
+        steps,
+        edits: tools.edits(),
+        status: status.to_string(),
+        usage,
+    })
+}
@@ -0,0 +1,459 @@
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::sync::{Mutex, OnceLock};
+
+use crate::import_graph;
+use crate::project_index::ProjectIndex;
+
+// Presupuesto por defecto del contexto de proyecto que acompaña a cada sugerencia
+pub const DEFAULT_TOKEN_BUDGET: usize = 1200;
+// Parte del presupuesto que pueden ocupar las firmas y los modelos; los ejemplos usan lo que sobre
+const SIGNATURES_SHARE: usize = 40;
+const PRISMA_SHARE: usize = 30;
+const MAX_EXAMPLES: usize = 2;
+const MAX_EXAMPLE_LINES: usize = 60;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ContextSection {
+    // "imports", "signatures", "prisma" o "example"
+    pub kind: String,
+    // Archivo o modelo de donde sale el fragmento
+    pub source: String,
+    pub content: String,
+    pub tokens: usize,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProjectContext {
+    pub sections: Vec<ContextSection>,
+    pub tokens: usize,
+    pub budget: usize,
+    // Algo se recortó o quedó fuera por el presupuesto
+    pub truncated: bool,
+}
+
+impl ProjectContext {
+    fn remaining(&self) -> usize {
+        self.budget.saturating_sub(self.tokens)
+    }
+
+    // Agrega la sección recortándola a `limit` tokens (por líneas completas)
+    fn push(&mut self, kind: &str, source: &str, content: &str, limit: usize) {
+        let limit = limit.min(self.remaining());
+        let mut text = String::new();
+        for line in content.lines() {
+            if estimate_tokens(&text) + estimate_tokens(line) + 1 > limit {
+                self.truncated = true;
+                break;
+            }
+            text.push_str(line);
+            text.push('\n');
+        }
+
+        if text.trim().is_empty() {
+            return;
+        }
+        let tokens = estimate_tokens(&text);
+        self.tokens += tokens;
+        self.sections.push(ContextSection {
+            kind: kind.to_string(),
+            source: source.to_string(),
+            content: text,
+            tokens,
+        });
+    }
+
+    /// Texto para el prompt, con un encabezado por sección.
+    pub fn render(&self) -> String {
+        self.sections.iter()
+            .map(|section| {
+                let label = match section.kind.as_str() {
+                    "imports" => "Imports of the current file",
+                    "signatures" => "Exported API of",
+                    "prisma" => "Prisma schema:",
+                    _ => "Similar existing command:",
+                };
+                format!("// {} {}\n{}", label, section.source, section.content)
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+/// Tokens aproximados (≈ 4 caracteres por token), suficiente para respetar el presupuesto.
+pub fn estimate_tokens(text: &str) -> usize {
+    text.chars().count().div_ceil(4)
+}
+
+/// Arma el contexto de proyecto para autocompletar `text` (contenido actual del editor,
+/// guardado o no) del archivo `file`, relativo a la raíz del proyecto. En orden de prioridad:
+/// sus imports, las firmas de lo que importa de `src/core`, los modelos de Prisma que usa
+/// y comandos parecidos del proyecto.
+pub fn build(index: &ProjectIndex, file: &str, text: &str, budget: usize) -> ProjectContext {
+    let mut context = ProjectContext { budget, ..Default::default() };
+
+    let imports = import_statements(text);
+    if !imports.is_empty() {
+        context.push("imports", file, &imports.join("\n"), budget);
+    }
+
+    // Firmas de los módulos de src/core que importa el archivo
+    let core_dir = format!("{}/core/", index.layout().src_dir.trim_end_matches('/'));
+    let mut seen = HashSet::new();
+    let signatures_cap = budget * SIGNATURES_SHARE / 100;
+    let signatures_start = context.tokens;
+    for specifier in import_specifiers(text) {
+        let Some(module) = index.resolve(file, &specifier) else { continue };
+        if !module.starts_with(&core_dir) || !seen.insert(module.clone()) {
+            continue;
+        }
+        let Ok(source) = fs::read_to_string(index.root().join(&module)) else { continue };
+
+        let used = signatures_cap.saturating_sub(context.tokens - signatures_start);
+        context.push("signatures", &module, &signatures(&source).join("\n"), used);
+    }
+
+    // Modelos de Prisma mencionados en el archivo, más los enums que usan
+    let schema = fs::read_to_string(index.layout().prisma_schema_path(index.root())).unwrap_or_default();
+    let blocks = prisma_blocks(&schema);
+    let mut wanted: Vec<&PrismaBlock> = blocks.iter()
+        .filter(|block| block.kind == "model" && mentions_model(text, &block.name))
+        .collect();
+    let enums: Vec<&PrismaBlock> = blocks.iter()
+        .filter(|block| block.kind == "enum" && wanted.iter().any(|model| uses_type(&model.body, &block.name)))
+        .collect();
+    wanted.extend(enums);
+
+    let prisma_cap = budget * PRISMA_SHARE / 100;
+    let prisma_start = context.tokens;
+    for block in wanted {
+        let used = prisma_cap.saturating_sub(context.tokens - prisma_start);
+        if estimate_tokens(&block.body) + 1 > used {
+            // Un modelo a medias confunde más de lo que ayuda
+            context.truncated = true;
+            continue;
+        }
+        context.push("prisma", &block.name, &block.body, used);
+    }
+
+    // Comandos parecidos: del mismo directorio o con imports en común
+    if import_graph::module_kind(index, file) == "command" {
+        for example in similar_commands(index, file, text).into_iter().take(MAX_EXAMPLES) {
+            let Ok(source) = fs::read_to_string(index.root().join(&example)) else { continue };
+            let remaining = context.remaining();
+            context.push("example", &example, &command_excerpt(&source), remaining);
+        }
+    }
+
+    context
+}
+
+/// Sentencias `import` completas (pueden ocupar varias líneas)
+pub fn import_statements(text: &str) -> Vec<String> {
+    static SIDE_EFFECT_RE: OnceLock<Regex> = OnceLock::new();
+    let side_effect_re = SIDE_EFFECT_RE.get_or_init(|| Regex::new(r#"^import\s+["']"#).unwrap());
+    let mut statements = Vec::new();
+    let mut current: Option<String> = None;
+
+    for line in text.lines() {
+        let trimmed = line.trim();
+        match current.as_mut() {
+            Some(statement) => {
+                statement.push('\n');
+                statement.push_str(line);
+            }
+            None if trimmed.starts_with("import ") || trimmed.starts_with("import{") => {
+                current = Some(line.to_string());
+            }
+            None => continue,
+        }
+
+        let statement = current.as_ref().unwrap();
+        let finished = trimmed.ends_with(';')
+            || trimmed.contains(" from ")
+            || trimmed.starts_with("} from")
+            || side_effect_re.is_match(trimmed);
+        if finished {
+            statements.push(statement.clone());
+            current = None;
+        }
+    }
+
+    statements
+}
+
+fn import_specifiers(text: &str) -> Vec<String> {
+    let re = Regex::new(r#"(?m)^\s*import\b(?:[^;'"]*?\bfrom)?\s*["']([^"']+)["']"#).unwrap();
+    re.captures_iter(text)
+        .map(|cap| cap[1].to_string())
+        .collect()
+}
+
+/// Firmas exportadas de un módulo TypeScript: interfaces, tipos y enums completos; funciones,
+/// constantes y clases sin cuerpo (de las clases, solo los métodos públicos).
+pub fn signatures(source: &str) -> Vec<String> {
+    let type_re = Regex::new(r"^export\s+(?:declare\s+)?(?:interface|type|enum|const\s+enum)\b").unwrap();
+    let class_re = Regex::new(r"^export\s+(?:default\s+)?(?:abstract\s+)?class\b").unwrap();
+    // `export const x: T = valor` que no es una función flecha
+    let value_re = Regex::new(
+        r"^(export\s+(?:const|let|var)\s+[\w$]+(?:\s*:[^=]+?)?)\s*=\s*(?:[^(\s]|\((?:[^)]*\)\s*[^=\s:{]|$))"
+    ).unwrap();
+    let member_re = Regex::new(
+        r"^\s*(?:(?:public|static|async|readonly|get|set)\s+)*[A-Za-z_$][\w$]*\s*(?:<[^>]*>)?\s*\("
+    ).unwrap();
+
+    let mut result = Vec::new();
+    let mut depth: i32 = 0;
+    let mut in_comment = false;
+    let lines: Vec<&str> = source.lines().collect();
+    let mut i = 0;
+
+    while i < lines.len() {
+        let line = lines[i];
+        let trimmed = line.trim_start();
+        let start_depth = depth;
+        depth += brace_delta(line, &mut in_comment);
+        i += 1;
+
+        if start_depth != 0 || !trimmed.starts_with("export ") {
+            continue;
+        }
+
+        if trimmed.starts_with("export {") || trimmed.starts_with("export *") {
+            // Reexportaciones: ya son una firma
+            result.push(trimmed.trim_end().to_string());
+        } else if type_re.is_match(trimmed) {
+            // El bloque entero: la forma del tipo es justo lo que le sirve al modelo
+            let mut block = vec![line.to_string()];
+            while depth > 0 && i < lines.len() {
+                depth += brace_delta(lines[i], &mut in_comment);
+                block.push(lines[i].to_string());
+                i += 1;
+            }
+            result.push(block.join("\n"));
+        } else if class_re.is_match(trimmed) {
+            let mut block = vec![format!("{} {{", header(line))];
+            while depth > 0 && i < lines.len() {
+                let member = lines[i];
+                let member_depth = depth;
+                depth += brace_delta(member, &mut in_comment);
+                i += 1;
+
+                let member_trimmed = member.trim_start();
+                let private = member_trimmed.starts_with("private ")
+                    || member_trimmed.starts_with("protected ")
+                    || member_trimmed.starts_with('#');
+                if member_depth == 1 && !private && member_re.is_match(member) {
+                    let mut head = member_trimmed.trim_end().to_string();
+                    while unbalanced_parens(&head) && i < lines.len() {
+                        join_line(&mut head, lines[i]);
+                        depth += brace_delta(lines[i], &mut in_comment);
+                        i += 1;
+                    }
+                    block.push(format!("    {};", header(&head)));
+                }
+            }
+            block.push("}".to_string());
+            result.push(block.join("\n"));
+        } else if let Some(cap) = value_re.captures(trimmed).filter(|_| !trimmed.contains("=>")) {
+            // Constante que no es una función: el nombre y su tipo, sin el valor (salvo si es corto)
+            while depth > 0 && i < lines.len() {
+                depth += brace_delta(lines[i], &mut in_comment);
+                i += 1;
+            }
+            let trimmed = trimmed.trim_end();
+            result.push(if trimmed.ends_with(';') && trimmed.len() <= 120 {
+                trimmed.to_string()
+            } else {
+                format!("{} = …;", cap[1].trim_end())
+            });
+        } else {
+            // Función: la cabecera, que puede ocupar varias líneas
+            let mut head = trimmed.trim_end().to_string();
+            while unbalanced_parens(&head) && i < lines.len() {
+                join_line(&mut head, lines[i]);
+                depth += brace_delta(lines[i], &mut in_comment);
+                i += 1;
+            }
+            while depth > 0 && i < lines.len() {
+                depth += brace_delta(lines[i], &mut in_comment);
+                i += 1;
+            }
+            let head = header(&head);
+            result.push(if head.ends_with(';') { head } else { format!("{};", head) });
+        }
+    }
+
+    result
+}
+
+fn unbalanced_parens(text: &str) -> bool {
+    text.matches('(').count() > text.matches(')').count()
+}
+
+// Une una línea de continuación a una cabecera partida en varias líneas
+fn join_line(head: &mut String, line: &str) {
+    let line = line.trim();
+    if line.starts_with(')') && head.ends_with(',') {
+        head.pop();
+    }
+    if !head.ends_with('(') && !line.starts_with(')') {
+        head.push(' ');
+    }
+    head.push_str(line);
+}
+
+// La declaración sin el cuerpo que empieza en su último `{`
+fn header(line: &str) -> String {
+    let line = line.trim_end();
+    let cut = match line.strip_suffix('{') {
+        Some(rest) => rest,
+        None => line.find(" {").map(|i| &line[..i]).unwrap_or(line),
+    };
+    cut.trim_end().trim_end_matches("=>").trim_end().to_string()
+}
+
+// Cambio de profundidad de llaves en la línea, ignorando strings y comentarios
+fn brace_delta(line: &str, in_comment: &mut bool) -> i32 {
+    let chars: Vec<char> = line.chars().collect();
+    let mut delta = 0;
+    let mut quote: Option<char> = None;
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        let next = chars.get(i + 1).copied();
+
+        if *in_comment {
+            if c == '*' && next == Some('/') {
+                *in_comment = false;
+                i += 1;
+            }
+        } else if let Some(q) = quote {
+            if c == '\\' {
+                i += 1;
+            } else if c == q {
+                quote = None;
+            }
+        } else {
+            match c {
+                '/' if next == Some('/') => break,
+                '/' if next == Some('*') => {
+                    *in_comment = true;
+                    i += 1;
+                }
+                '"' | '\'' | '`' => quote = Some(c),
+                '{' => delta += 1,
+                '}' => delta -= 1,
+                _ => {}
+            }
+        }
+        i += 1;
+    }
+
+    delta
+}
+
+// Imports y el objeto exportado del comando; las funciones auxiliares no ayudan como ejemplo
+pub fn command_excerpt(source: &str) -> String {
+    let export_re = Regex::new(r"^\s*export\s+(?:const\s+\w+|default)\b").unwrap();
+    let lines: Vec<&str> = source.lines().collect();
+
+    let excerpt = match lines.iter().position(|line| export_re.is_match(line)) {
+        Some(start) => format!("{}\n\n{}", import_statements(source).join("\n"), lines[start..].join("\n")),
+        None => source.to_string(),
+    };
+    excerpt.lines().take(MAX_EXAMPLE_LINES).collect::<Vec<_>>().join("\n")
+}
+
+/// Modelos y enums del schema de Prisma: (nombre, definición completa).
+pub fn prisma_definitions(schema: &str) -> Vec<(String, String)> {
+    prisma_blocks(schema).into_iter()
+        .map(|block| (block.name, block.body))
+        .collect()
+}
+
+struct PrismaBlock {
+    kind: String,
+    name: String,
+    body: String,
+}
+
+fn prisma_blocks(schema: &str) -> Vec<PrismaBlock> {
+    let start_re = Regex::new(r"^\s*(model|enum)\s+(\w+)\s*\{").unwrap();
+    let mut blocks = Vec::new();
+    let mut current: Option<PrismaBlock> = None;
+
+    for line in schema.lines() {
+        if let Some(block) = current.as_mut() {
+            block.body.push('\n');
+            block.body.push_str(line);
+            if line.trim_start().starts_with('}') {
+                blocks.push(current.take().unwrap());
+            }
+        } else if let Some(cap) = start_re.captures(line) {
+            current = Some(PrismaBlock {
+                kind: cap[1].to_string(),
+                name: cap[2].to_string(),
+                body: line.to_string(),
+            });
+        }
+    }
+
+    blocks
+}
+
+// `prisma.economyWallet.…`, o el tipo `EconomyWallet` si el archivo importa de `@prisma/client`
+// (sin ese import, nombres como `Guild` o `User` suelen ser de discord.js)
+pub fn mentions_model(text: &str, model: &str) -> bool {
+    let mut chars = model.chars();
+    let delegate = match chars.next() {
+        Some(first) => format!("{}{}", first.to_lowercase(), chars.as_str()),
+        None => return false,
+    };
+    text.contains(&format!(".{}.", delegate))
+        || (text.contains("@prisma/client") && uses_type(text, model))
+}
+
+fn uses_type(text: &str, name: &str) -> bool {
+    // Una regex por modelo o enum del schema: se compilan una sola vez
+    static WORD_RES: OnceLock<Mutex<HashMap<String, Regex>>> = OnceLock::new();
+    let mut cache = WORD_RES.get_or_init(Default::default).lock().unwrap();
+    let re = match cache.get(name) {
+        Some(re) => re.clone(),
+        None => match Regex::new(&format!(r"\b{}\b", regex::escape(name))) {
+            Ok(re) => cache.entry(name.to_string()).or_insert(re).clone(),
+            Err(_) => return false,
+        },
+    };
+    drop(cache);
+    re.is_match(text)
+}
+
+// Otros comandos ordenados por parecido: +2 si están en la misma carpeta, +1 por import en común
+fn similar_commands(index: &ProjectIndex, file: &str, text: &str) -> Vec<String> {
+    let imports: HashSet<String> = import_specifiers(text).iter()
+        .filter_map(|specifier| index.resolve(file, specifier))
+        .collect();
+    let dir = file.rsplit_once('/').map(|(dir, _)| dir).unwrap_or("");
+
+    let mut scored: Vec<(usize, String)> = index.files().values()
+        .filter(|other| other.relative_path != file && import_graph::module_kind(index, &other.relative_path) == "command")
+        .map(|other| {
+            let same_dir = other.relative_path.rsplit_once('/').map(|(d, _)| d).unwrap_or("") == dir;
+            let shared = other.imports.iter()
+                .filter_map(|import| index.resolve(&other.relative_path, &import.specifier))
+                .filter(|module| imports.contains(module))
+                .count();
+            (if same_dir { 2 } else { 0 } + shared, other.relative_path.clone())
+        })
+        .filter(|(score, _)| *score > 0)
+        .collect();
+
+    scored.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.cmp(&b.1)));
+    scored.into_iter().map(|(_, path)| path).collect()
+}
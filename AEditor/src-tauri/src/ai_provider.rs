@@ -0,0 +1,773 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::fs;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::OnceLock;
+use std::time::Duration;
+use tokio::sync::Notify;
+
+use crate::secrets;
+
+pub const GEMINI_BASE_URL: &str = "https://generativelanguage.googleapis.com/v1beta";
+pub const OPENAI_BASE_URL: &str = "https://api.openai.com/v1";
+// Ollama y el servidor de llama.cpp exponen la API de OpenAI en `/v1`
+pub const LOCAL_BASE_URL: &str = "http://localhost:11434/v1";
+
+// Modelos de embeddings por defecto de cada proveedor (se cambian con `embedding_model`)
+const GEMINI_EMBEDDING_MODEL: &str = "text-embedding-004";
+const OPENAI_EMBEDDING_MODEL: &str = "text-embedding-3-small";
+const LOCAL_EMBEDDING_MODEL: &str = "nomic-embed-text";
+
+const CONFIG_FILE: &str = "gemini_config.json";
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ProviderKind {
+    #[default]
+    Gemini,
+    // Cualquier endpoint compatible con `/chat/completions` de OpenAI
+    OpenAi,
+    // Ollama / llama.cpp en la máquina local: sin API key, el código no sale del equipo
+    Local,
+}
+
+/// Configuración guardada en `gemini_config.json` (se mantiene el nombre y el
+/// formato snake_case que ya usa el frontend). La API key nunca se escribe aquí:
+/// vive cifrada en `secrets` y solo se carga en memoria.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AiConfig {
+    pub provider: ProviderKind,
+    pub base_url: Option<String>,
+    // Solo se lee para migrar configuraciones viejas que la tenían en texto plano
+    #[serde(skip_serializing)]
+    pub api_key: String,
+    pub model: String,
+    pub enabled: bool,
+    pub agent_mode: bool,
+    pub inline_suggestions_enabled: bool,
+    // Modelo para el índice semántico; vacío = el del proveedor
+    pub embedding_model: String,
+}
+
+impl Default for AiConfig {
+    fn default() -> Self {
+        AiConfig {
+            provider: ProviderKind::Gemini,
+            base_url: None,
+            api_key: String::new(),
+            model: "gemini-2.5-flash".to_string(),
+            enabled: true,
+            agent_mode: false,
+            inline_suggestions_enabled: false,
+            embedding_model: String::new(),
+        }
+    }
+}
+
+impl AiConfig {
+    pub fn load(app_dir: &Path) -> Option<Self> {
+        let content = fs::read_to_string(app_dir.join(CONFIG_FILE)).ok()?;
+        let mut config: AiConfig = serde_json::from_str(&content).ok()?;
+
+        // Mover la key en texto plano de versiones anteriores al almacenamiento cifrado
+        if !config.api_key.is_empty() {
+            match secrets::store_api_key(app_dir, &config.api_key) {
+                Ok(_) => {
+                    if let Err(e) = config.save(app_dir) {
+                        eprintln!("⚠️ No se pudo quitar la API key de {}: {}", CONFIG_FILE, e);
+                    }
+                }
+                Err(e) => eprintln!("⚠️ No se pudo migrar la API key: {}", e),
+            }
+        }
+
+        config.api_key = secrets::load_api_key(app_dir).unwrap_or(config.api_key);
+        Some(config)
+    }
+
+    pub fn save(&self, app_dir: &Path) -> Result<(), String> {
+        if !app_dir.exists() {
+            fs::create_dir_all(app_dir).map_err(|e| format!("Error creando directorio: {}", e))?;
+        }
+
+        let json = serde_json::to_string_pretty(self).map_err(|e| e.to_string())?;
+        fs::write(app_dir.join(CONFIG_FILE), json)
+            .map_err(|e| format!("Error guardando archivo: {}", e))
+    }
+
+    /// URL base configurada, sin la barra final. Gemini siempre usa la oficial:
+    /// su API key no se manda a otro servidor.
+    pub fn endpoint(&self) -> Option<String> {
+        if self.provider == ProviderKind::Gemini {
+            return None;
+        }
+        self.base_url.as_deref()
+            .map(|url| url.trim())
+            .filter(|url| !url.is_empty())
+            .map(|url| url.trim_end_matches('/').to_string())
+    }
+
+    /// Crea el proveedor configurado. `api_key` y `model` vacíos usan los guardados.
+    pub fn provider(&self, api_key: &str, model: &str) -> Box<dyn AiProvider> {
+        let api_key = if api_key.is_empty() { self.api_key.clone() } else { api_key.to_string() };
+        let model = if model.is_empty() { self.model.clone() } else { model.to_string() };
+        let base_url = self.endpoint();
+        let embedding_model = |default: &str| {
+            if self.embedding_model.trim().is_empty() { default.to_string() } else { self.embedding_model.trim().to_string() }
+        };
+
+        match self.provider {
+            ProviderKind::Gemini => Box::new(GeminiProvider {
+                base_url: GEMINI_BASE_URL.to_string(),
+                api_key,
+                model,
+                embedding_model: embedding_model(GEMINI_EMBEDDING_MODEL),
+            }),
+            ProviderKind::OpenAi => Box::new(OpenAiProvider {
+                base_url: base_url.unwrap_or_else(|| OPENAI_BASE_URL.to_string()),
+                api_key,
+                model,
+                embedding_model: embedding_model(OPENAI_EMBEDDING_MODEL),
+            }),
+            ProviderKind::Local => Box::new(LocalProvider {
+                base_url: base_url.unwrap_or_else(|| LOCAL_BASE_URL.to_string()),
+                api_key: Some(api_key).filter(|k| !k.is_empty()),
+                model,
+                embedding_model: embedding_model(LOCAL_EMBEDDING_MODEL),
+            }),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AiMessage {
+    // "system", "user" o "assistant"
+    pub role: String,
+    pub text: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct GenerateRequest {
+    pub messages: Vec<AiMessage>,
+    pub temperature: f32,
+    pub max_output_tokens: i32,
+    // Razonamiento antes de responder (solo en los proveedores que lo soportan)
+    pub thinking: bool,
+    pub timeout: Duration,
+}
+
+impl GenerateRequest {
+    pub fn prompt(prompt: String) -> Self {
+        GenerateRequest {
+            messages: vec![AiMessage { role: "user".to_string(), text: prompt }],
+            temperature: 0.3,
+            max_output_tokens: 1024,
+            thinking: false,
+            timeout: Duration::from_secs(15),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct GenerateResponse {
+    // Texto de la respuesta, sin los pensamientos
+    pub text: String,
+    pub thoughts: Vec<String>,
+    // Tokens que informó el proveedor (`usageMetadata` en Gemini, `usage` en OpenAI)
+    pub usage: Option<TokenUsage>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TokenUsage {
+    pub prompt_tokens: u64,
+    // Tokens de la respuesta, sin los pensamientos
+    pub candidates_tokens: u64,
+    pub thought_tokens: u64,
+}
+
+/// Un backend de IA: cómo armar la petición HTTP y cómo leer la respuesta.
+/// El envío es común a todos (`generate`), así el trait no necesita métodos async.
+pub trait AiProvider: Send + Sync {
+    fn name(&self) -> &'static str;
+    fn model(&self) -> &str;
+    // Gemini y OpenAI no funcionan sin API key; el servidor local sí
+    fn requires_api_key(&self) -> bool;
+    fn has_api_key(&self) -> bool;
+    fn url(&self) -> String;
+    fn headers(&self) -> Vec<(&'static str, String)>;
+    fn body(&self, request: &GenerateRequest) -> Value;
+    fn parse(&self, response: &Value) -> Result<GenerateResponse, String>;
+
+    // Streaming por Server-Sent Events: cada evento `data:` se lee con `parse_stream`
+    fn stream_url(&self) -> String;
+    fn stream_body(&self, request: &GenerateRequest) -> Value;
+    fn parse_stream(&self, event: &Value) -> Result<GenerateResponse, String>;
+
+    // Embeddings (índice semántico), con su propio modelo
+    fn embedding_model(&self) -> &str;
+    fn embed_url(&self) -> String;
+    fn embed_body(&self, texts: &[String]) -> Value;
+    fn parse_embeddings(&self, response: &Value) -> Result<Vec<Vec<f32>>, String>;
+}
+
+/// Cancelación de una petición en curso: `cancel` despierta a quien espera en `cancelled`.
+#[derive(Default)]
+pub struct CancelToken {
+    cancelled: AtomicBool,
+    notify: Notify,
+}
+
+impl CancelToken {
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+        self.notify.notify_one();
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    pub async fn cancelled(&self) {
+        if !self.is_cancelled() {
+            self.notify.notified().await;
+        }
+    }
+}
+
+// Un solo cliente para todas las peticiones: reutiliza conexiones (y el handshake TLS)
+fn http_client() -> &'static reqwest::Client {
+    static CLIENT: OnceLock<reqwest::Client> = OnceLock::new();
+    CLIENT.get_or_init(|| {
+        reqwest::Client::builder()
+            .pool_idle_timeout(Duration::from_secs(90))
+            .build()
+            .unwrap_or_default()
+    })
+}
+
+async fn send(
+    provider: &dyn AiProvider,
+    url: String,
+    body: &Value,
+    timeout: Option<Duration>,
+) -> Result<reqwest::Response, String> {
+    if provider.requires_api_key() && !provider.has_api_key() {
+        return Err("No API key provided".to_string());
+    }
+
+    println!("🚀 Llamando a {} ({})...", provider.name(), provider.model());
+
+    let mut builder = http_client()
+        .post(url)
+        .header("Content-Type", "application/json")
+        .json(body);
+    if let Some(timeout) = timeout {
+        builder = builder.timeout(timeout);
+    }
+    for (name, value) in provider.headers() {
+        builder = builder.header(name, value);
+    }
+
+    let response = builder.send().await.map_err(|e| {
+        eprintln!("❌ Error calling {}: {:?}", provider.name(), e);
+        format!("Network error: {}", e)
+    })?;
+
+    let status = response.status();
+    println!("📡 Status: {}", status);
+
+    if !status.is_success() {
+        let error_text = response.text().await.unwrap_or_default();
+        eprintln!("❌ {} API error {}: {}", provider.name(), status, error_text);
+        return Err(format!("API error: {}", error_text));
+    }
+    Ok(response)
+}
+
+pub async fn generate(provider: &dyn AiProvider, request: &GenerateRequest) -> Result<GenerateResponse, String> {
+    let response = send(provider, provider.url(), &provider.body(request), Some(request.timeout)).await?;
+    let response_text = response.text().await.map_err(|e| e.to_string())?;
+
+    let json: Value = serde_json::from_str(&response_text)
+        .map_err(|e| format!("Error parsing response: {}", e))?;
+    provider.parse(&json)
+}
+
+/// Un vector por texto, en el mismo orden.
+pub async fn embed(provider: &dyn AiProvider, texts: &[String]) -> Result<Vec<Vec<f32>>, String> {
+    let response = send(provider, provider.embed_url(), &provider.embed_body(texts), Some(Duration::from_secs(60))).await?;
+    let json: Value = response.json().await
+        .map_err(|e| format!("Error parsing response: {}", e))?;
+
+    let vectors = provider.parse_embeddings(&json)?;
+    if vectors.len() != texts.len() {
+        return Err(format!("Se esperaban {} embeddings y llegaron {}", texts.len(), vectors.len()));
+    }
+    Ok(vectors)
+}
+
+/// Igual que `generate`, pero llama a `on_delta` con cada fragmento a medida que llega.
+/// `request.timeout` es el tiempo máximo sin recibir datos, no el total.
+/// Si se cancela, devuelve lo recibido hasta ese momento.
+pub async fn generate_stream(
+    provider: &dyn AiProvider,
+    request: &GenerateRequest,
+    cancel: &CancelToken,
+    mut on_delta: impl FnMut(&GenerateResponse),
+) -> Result<GenerateResponse, String> {
+    let body = provider.stream_body(request);
+    let mut response = tokio::select! {
+        response = tokio::time::timeout(request.timeout, send(provider, provider.stream_url(), &body, None)) => {
+            response.map_err(|_| "Tiempo de espera agotado".to_string())??
+        }
+        _ = cancel.cancelled() => return Ok(GenerateResponse::default()),
+    };
+
+    let mut result = GenerateResponse::default();
+    let mut buffer: Vec<u8> = Vec::new();
+
+    loop {
+        let chunk = tokio::select! {
+            chunk = tokio::time::timeout(request.timeout, response.chunk()) => {
+                chunk.map_err(|_| "Tiempo de espera agotado".to_string())?
+                    .map_err(|e| format!("Network error: {}", e))?
+            }
+            _ = cancel.cancelled() => break,
+        };
+        let Some(chunk) = chunk else {
+            break;
+        };
+        buffer.extend_from_slice(&chunk);
+
+        // Un evento SSE termina con una línea vacía
+        while let Some((end, separator)) = find_event_end(&buffer) {
+            let event: Vec<u8> = buffer.drain(..end + separator).collect();
+            let event = String::from_utf8_lossy(&event[..end]);
+
+            let data: Vec<&str> = event.lines()
+                .filter_map(|line| line.strip_prefix("data:"))
+                .map(|data| data.trim_start())
+                .collect();
+            if data.is_empty() {
+                continue;
+            }
+            let data = data.join("\n");
+            if data == "[DONE]" {
+                return Ok(result);
+            }
+
+            let json: Value = serde_json::from_str(&data)
+                .map_err(|e| format!("Error parsing response: {}", e))?;
+            if let Some(error) = json.get("error") {
+                return Err(format!("API error: {}", error));
+            }
+
+            let delta = provider.parse_stream(&json)?;
+            if delta.usage.is_some() {
+                result.usage = delta.usage;
+            }
+            if delta.text.is_empty() && delta.thoughts.is_empty() {
+                continue;
+            }
+            result.text.push_str(&delta.text);
+            result.thoughts.extend(delta.thoughts.iter().cloned());
+            on_delta(&delta);
+        }
+    }
+
+    Ok(result)
+}
+
+// Posición y largo del separador del primer evento completo (`\n\n` o `\r\n\r\n`)
+fn find_event_end(buffer: &[u8]) -> Option<(usize, usize)> {
+    let lf = buffer.windows(2).position(|w| w == b"\n\n").map(|i| (i, 2));
+    let crlf = buffer.windows(4).position(|w| w == b"\r\n\r\n").map(|i| (i, 4));
+    match (lf, crlf) {
+        (Some(a), Some(b)) => Some(if a.0 < b.0 { a } else { b }),
+        (a, b) => a.or(b),
+    }
+}
+
+// ============================================
+// GEMINI
+// ============================================
+
+pub struct GeminiProvider {
+    base_url: String,
+    api_key: String,
+    model: String,
+    embedding_model: String,
+}
+
+impl AiProvider for GeminiProvider {
+    fn name(&self) -> &'static str {
+        "Gemini"
+    }
+
+    fn model(&self) -> &str {
+        &self.model
+    }
+
+    fn requires_api_key(&self) -> bool {
+        true
+    }
+
+    fn has_api_key(&self) -> bool {
+        !self.api_key.is_empty()
+    }
+
+    fn url(&self) -> String {
+        format!("{}/models/{}:generateContent", self.base_url, self.model)
+    }
+
+    // En un header y no en la URL, así la key no aparece en logs ni en errores de red
+    fn headers(&self) -> Vec<(&'static str, String)> {
+        vec![("x-goog-api-key", self.api_key.clone())]
+    }
+
+    fn body(&self, request: &GenerateRequest) -> Value {
+        let system: Vec<&str> = request.messages.iter()
+            .filter(|m| m.role == "system")
+            .map(|m| m.text.as_str())
+            .collect();
+        let contents: Vec<Value> = request.messages.iter()
+            .filter(|m| m.role != "system")
+            .map(|m| json!({
+                "role": if m.role == "assistant" { "model" } else { "user" },
+                "parts": [{ "text": m.text }],
+            }))
+            .collect();
+
+        let mut body = json!({
+            "contents": contents,
+            "generationConfig": {
+                "temperature": request.temperature,
+                "maxOutputTokens": request.max_output_tokens,
+                "candidateCount": 1,
+                // -1 = dinámico: el modelo decide cuánto "pensar"
+                "thinkingConfig": {
+                    "thinkingBudget": if request.thinking { -1 } else { 0 },
+                    "includeThoughts": request.thinking,
+                },
+            },
+        });
+        if !system.is_empty() {
+            body["systemInstruction"] = json!({ "parts": [{ "text": system.join("\n\n") }] });
+        }
+        body
+    }
+
+    fn parse(&self, response: &Value) -> Result<GenerateResponse, String> {
+        if response["candidates"][0]["content"]["parts"].as_array().is_none() {
+            return Err("No se encontró contenido en la respuesta".to_string());
+        }
+        self.parse_stream(response)
+    }
+
+    fn stream_url(&self) -> String {
+        format!("{}/models/{}:streamGenerateContent?alt=sse", self.base_url, self.model)
+    }
+
+    fn stream_body(&self, request: &GenerateRequest) -> Value {
+        self.body(request)
+    }
+
+    // Cada evento es una respuesta parcial con el mismo formato; el último puede venir sin `parts`
+    fn parse_stream(&self, event: &Value) -> Result<GenerateResponse, String> {
+        let mut result = GenerateResponse::default();
+        // En streaming cada evento trae el acumulado, así que vale el último
+        let usage = &event["usageMetadata"];
+        if usage.is_object() {
+            result.usage = Some(TokenUsage {
+                prompt_tokens: usage["promptTokenCount"].as_u64().unwrap_or(0),
+                candidates_tokens: usage["candidatesTokenCount"].as_u64().unwrap_or(0),
+                thought_tokens: usage["thoughtsTokenCount"].as_u64().unwrap_or(0),
+            });
+        }
+        let Some(parts) = event["candidates"][0]["content"]["parts"].as_array() else {
+            return Ok(result);
+        };
+
+        for part in parts {
+            let Some(text) = part["text"].as_str() else {
+                continue;
+            };
+            // Los pensamientos del modelo no son parte de la respuesta
+            if part["thought"].as_bool().unwrap_or(false) {
+                result.thoughts.push(text.to_string());
+            } else {
+                result.text.push_str(text);
+            }
+        }
+        Ok(result)
+    }
+
+    fn embedding_model(&self) -> &str {
+        &self.embedding_model
+    }
+
+    fn embed_url(&self) -> String {
+        format!("{}/models/{}:batchEmbedContents", self.base_url, self.embedding_model)
+    }
+
+    fn embed_body(&self, texts: &[String]) -> Value {
+        let requests: Vec<Value> = texts.iter()
+            .map(|text| json!({
+                "model": format!("models/{}", self.embedding_model),
+                "content": { "parts": [{ "text": text }] },
+            }))
+            .collect();
+        json!({ "requests": requests })
+    }
+
+    fn parse_embeddings(&self, response: &Value) -> Result<Vec<Vec<f32>>, String> {
+        response["embeddings"].as_array()
+            .ok_or("No se encontraron embeddings en la respuesta")?
+            .iter()
+            .map(|embedding| vector(&embedding["values"]))
+            .collect()
+    }
+}
+
+// ============================================
+// OPENAI-COMPATIBLE
+// ============================================
+
+pub struct OpenAiProvider {
+    base_url: String,
+    api_key: String,
+    model: String,
+    embedding_model: String,
+}
+
+impl AiProvider for OpenAiProvider {
+    fn name(&self) -> &'static str {
+        "OpenAI"
+    }
+
+    fn model(&self) -> &str {
+        &self.model
+    }
+
+    fn requires_api_key(&self) -> bool {
+        true
+    }
+
+    fn has_api_key(&self) -> bool {
+        !self.api_key.is_empty()
+    }
+
+    fn url(&self) -> String {
+        format!("{}/chat/completions", self.base_url)
+    }
+
+    fn headers(&self) -> Vec<(&'static str, String)> {
+        vec![("Authorization", format!("Bearer {}", self.api_key))]
+    }
+
+    fn body(&self, request: &GenerateRequest) -> Value {
+        chat_completions_body(&self.model, request)
+    }
+
+    fn parse(&self, response: &Value) -> Result<GenerateResponse, String> {
+        parse_chat_completion(response)
+    }
+
+    fn stream_url(&self) -> String {
+        self.url()
+    }
+
+    fn stream_body(&self, request: &GenerateRequest) -> Value {
+        let mut body = self.body(request);
+        body["stream"] = json!(true);
+        // Sin esto el streaming no informa los tokens usados
+        body["stream_options"] = json!({ "include_usage": true });
+        body
+    }
+
+    fn parse_stream(&self, event: &Value) -> Result<GenerateResponse, String> {
+        Ok(parse_chat_delta(event))
+    }
+
+    fn embedding_model(&self) -> &str {
+        &self.embedding_model
+    }
+
+    fn embed_url(&self) -> String {
+        format!("{}/embeddings", self.base_url)
+    }
+
+    fn embed_body(&self, texts: &[String]) -> Value {
+        json!({ "model": self.embedding_model, "input": texts })
+    }
+
+    fn parse_embeddings(&self, response: &Value) -> Result<Vec<Vec<f32>>, String> {
+        parse_embeddings_data(response)
+    }
+}
+
+// ============================================
+// LOCAL (Ollama / llama.cpp)
+// ============================================
+
+pub struct LocalProvider {
+    base_url: String,
+    // Solo si el servidor se levantó con `--api-key`
+    api_key: Option<String>,
+    model: String,
+    embedding_model: String,
+}
+
+impl AiProvider for LocalProvider {
+    fn name(&self) -> &'static str {
+        "Local"
+    }
+
+    fn model(&self) -> &str {
+        &self.model
+    }
+
+    fn requires_api_key(&self) -> bool {
+        false
+    }
+
+    fn has_api_key(&self) -> bool {
+        self.api_key.is_some()
+    }
+
+    fn url(&self) -> String {
+        format!("{}/chat/completions", self.base_url)
+    }
+
+    fn headers(&self) -> Vec<(&'static str, String)> {
+        self.api_key.iter()
+            .map(|key| ("Authorization", format!("Bearer {}", key)))
+            .collect()
+    }
+
+    fn body(&self, request: &GenerateRequest) -> Value {
+        let mut body = chat_completions_body(&self.model, request);
+        // llama.cpp acepta un modelo vacío (usa el cargado); Ollama necesita el nombre
+        if let (true, Some(object)) = (self.model.is_empty(), body.as_object_mut()) {
+            object.remove("model");
+        }
+        body
+    }
+
+    fn parse(&self, response: &Value) -> Result<GenerateResponse, String> {
+        parse_chat_completion(response)
+    }
+
+    fn stream_url(&self) -> String {
+        self.url()
+    }
+
+    fn stream_body(&self, request: &GenerateRequest) -> Value {
+        let mut body = self.body(request);
+        body["stream"] = json!(true);
+        body
+    }
+
+    fn parse_stream(&self, event: &Value) -> Result<GenerateResponse, String> {
+        Ok(parse_chat_delta(event))
+    }
+
+    fn embedding_model(&self) -> &str {
+        &self.embedding_model
+    }
+
+    fn embed_url(&self) -> String {
+        format!("{}/embeddings", self.base_url)
+    }
+
+    fn embed_body(&self, texts: &[String]) -> Value {
+        json!({ "model": self.embedding_model, "input": texts })
+    }
+
+    fn parse_embeddings(&self, response: &Value) -> Result<Vec<Vec<f32>>, String> {
+        parse_embeddings_data(response)
+    }
+}
+
+fn chat_completions_body(model: &str, request: &GenerateRequest) -> Value {
+    let messages: Vec<Value> = request.messages.iter()
+        .map(|m| json!({ "role": m.role, "content": m.text }))
+        .collect();
+
+    json!({
+        "model": model,
+        "messages": messages,
+        "temperature": request.temperature,
+        "max_tokens": request.max_output_tokens,
+        "stream": false,
+    })
+}
+
+fn parse_chat_completion(response: &Value) -> Result<GenerateResponse, String> {
+    let message = &response["choices"][0]["message"];
+    let text = message["content"].as_str()
+        .ok_or("No se encontró contenido en la respuesta")?;
+
+    Ok(GenerateResponse {
+        text: text.to_string(),
+        thoughts: reasoning(message),
+        usage: chat_usage(response),
+    })
+}
+
+// `/embeddings` devuelve `data[].embedding`, no necesariamente en orden
+fn parse_embeddings_data(response: &Value) -> Result<Vec<Vec<f32>>, String> {
+    let mut data: Vec<&Value> = response["data"].as_array()
+        .ok_or("No se encontraron embeddings en la respuesta")?
+        .iter()
+        .collect();
+    data.sort_by_key(|item| item["index"].as_u64().unwrap_or(0));
+    data.iter().map(|item| vector(&item["embedding"])).collect()
+}
+
+fn vector(values: &Value) -> Result<Vec<f32>, String> {
+    values.as_array()
+        .ok_or("Embedding con formato inesperado")?
+        .iter()
+        .map(|v| v.as_f64().map(|v| v as f32).ok_or_else(|| "Embedding con formato inesperado".to_string()))
+        .collect()
+}
+
+// En streaming el texto llega en `choices[0].delta`
+fn parse_chat_delta(event: &Value) -> GenerateResponse {
+    let delta = &event["choices"][0]["delta"];
+    GenerateResponse {
+        text: delta["content"].as_str().unwrap_or("").to_string(),
+        thoughts: reasoning(delta),
+        // Llega en el último evento, con `choices` vacío
+        usage: chat_usage(event),
+    }
+}
+
+// `completion_tokens` incluye el razonamiento; se separa para contarlo como pensamiento
+fn chat_usage(response: &Value) -> Option<TokenUsage> {
+    let usage = response["usage"].as_object()?;
+    let completion = usage.get("completion_tokens").and_then(|v| v.as_u64()).unwrap_or(0);
+    let reasoning = usage.get("completion_tokens_details")
+        .and_then(|details| details["reasoning_tokens"].as_u64())
+        .unwrap_or(0);
+    Some(TokenUsage {
+        prompt_tokens: usage.get("prompt_tokens").and_then(|v| v.as_u64()).unwrap_or(0),
+        candidates_tokens: completion.saturating_sub(reasoning),
+        thought_tokens: reasoning,
+    })
+}
+
+// Los modelos de razonamiento locales devuelven el "thinking" aparte
+// (`reasoning_content` en llama.cpp, `reasoning` en Ollama)
+fn reasoning(message: &Value) -> Vec<String> {
+    ["reasoning_content", "reasoning"].iter()
+        .filter_map(|field| message[*field].as_str())
+        .filter(|t| !t.is_empty())
+        .map(|t| t.to_string())
+        .collect()
+}
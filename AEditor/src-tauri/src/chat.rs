@@ -0,0 +1,347 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::ai_context::estimate_tokens;
+use crate::ai_provider::AiMessage;
+use crate::file_io;
+
+// Tokens de la conversación que se mandan al proveedor; al pasar el umbral se resume lo antiguo
+pub const CONTEXT_BUDGET: usize = 24_000;
+const SUMMARY_THRESHOLD: usize = 75;
+// Mensajes recientes que nunca se resumen
+const KEEP_RECENT: usize = 6;
+const MAX_ATTACHMENT_CHARS: usize = 20_000;
+const DEFAULT_TITLE: &str = "Nueva conversación";
+
+const SYSTEM_PROMPT: &str = "You are the assistant of AEditor, an editor for Discord bots written in TypeScript \
+(discord.js, Prisma). Answer in the user's language, be concise and use fenced code blocks for code.";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChatAttachment {
+    pub path: String,
+    // Solo para selecciones; sin rango es el archivo completo
+    pub start_line: Option<usize>,
+    pub end_line: Option<usize>,
+    // Vacío al enviar: el backend lee el archivo
+    #[serde(default)]
+    pub content: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChatMessage {
+    pub id: String,
+    // "user" o "assistant"
+    pub role: String,
+    pub text: String,
+    #[serde(default)]
+    pub attachments: Vec<ChatAttachment>,
+    pub timestamp: u64,
+    pub tokens: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChatSession {
+    pub id: String,
+    pub title: String,
+    pub created_at: u64,
+    pub updated_at: u64,
+    pub messages: Vec<ChatMessage>,
+    // Resumen de los mensajes anteriores a `summarized_until`, que ya no se envían
+    pub summary: Option<String>,
+    pub summarized_until: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChatSessionInfo {
+    pub id: String,
+    pub title: String,
+    pub created_at: u64,
+    pub updated_at: u64,
+    pub message_count: usize,
+    // Lo que ocupa la ventana que se envía (resumen + mensajes sin resumir)
+    pub tokens: usize,
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_millis() as u64
+}
+
+/// Líneas `start..=end` (desde 1) de `content`.
+pub fn select_lines(content: &str, start: Option<usize>, end: Option<usize>) -> String {
+    if start.is_none() && end.is_none() {
+        return content.to_string();
+    }
+    let start = start.unwrap_or(1).max(1);
+    content.lines()
+        .skip(start - 1)
+        .take(end.map(|end| end.saturating_sub(start) + 1).unwrap_or(usize::MAX))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+impl ChatAttachment {
+    /// Guarda el contenido recortado: la sesión se escribe entera en cada mensaje y exportación.
+    pub fn set_content(&mut self, content: &str) {
+        self.content = match content.char_indices().nth(MAX_ATTACHMENT_CHARS) {
+            Some((cut, _)) => format!("{}\n… (truncated)", &content[..cut]),
+            None => content.to_string(),
+        };
+    }
+
+    fn render(&self) -> String {
+        let range = match (self.start_line, self.end_line) {
+            (Some(start), Some(end)) => format!(" (lines {}-{})", start, end),
+            (Some(start), None) => format!(" (from line {})", start),
+            _ => String::new(),
+        };
+        let content: String = self.content.chars().take(MAX_ATTACHMENT_CHARS).collect();
+        let truncated = if content.len() < self.content.len() { "\n… (truncated)" } else { "" };
+        format!("Attached `{}`{}:\n```\n{}{}\n```", self.path, range, content, truncated)
+    }
+}
+
+impl ChatMessage {
+    pub fn new(role: &str, text: String, attachments: Vec<ChatAttachment>) -> Self {
+        let mut message = ChatMessage {
+            id: uuid::Uuid::new_v4().to_string(),
+            role: role.to_string(),
+            text,
+            attachments,
+            timestamp: now(),
+            tokens: 0,
+        };
+        message.tokens = estimate_tokens(&message.rendered());
+        message
+    }
+
+    // Texto que recibe el proveedor: los adjuntos van antes de la pregunta
+    fn rendered(&self) -> String {
+        let mut parts: Vec<String> = self.attachments.iter().map(|a| a.render()).collect();
+        parts.push(self.text.clone());
+        parts.join("\n\n")
+    }
+}
+
+impl ChatSession {
+    pub fn info(&self) -> ChatSessionInfo {
+        ChatSessionInfo {
+            id: self.id.clone(),
+            title: self.title.clone(),
+            created_at: self.created_at,
+            updated_at: self.updated_at,
+            message_count: self.messages.len(),
+            tokens: self.window_tokens(),
+        }
+    }
+
+    // Mensajes sin resumir; un archivo editado a mano puede traer un `summarized_until` fuera de rango
+    fn recent(&self) -> &[ChatMessage] {
+        &self.messages[self.summarized_until.min(self.messages.len())..]
+    }
+
+    pub fn window_tokens(&self) -> usize {
+        let summary = self.summary.as_deref().map(estimate_tokens).unwrap_or(0);
+        summary + self.recent().iter().map(|m| m.tokens).sum::<usize>()
+    }
+
+    pub fn push(&mut self, message: ChatMessage) {
+        // La primera pregunta da título a la conversación
+        if self.title == DEFAULT_TITLE && message.role == "user" {
+            let title: String = message.text.lines().next().unwrap_or("").chars().take(60).collect();
+            if !title.trim().is_empty() {
+                self.title = title.trim().to_string();
+            }
+        }
+        self.updated_at = message.timestamp;
+        self.messages.push(message);
+    }
+
+    /// Mensajes que habría que resumir para no pasar del presupuesto: todos los no
+    /// resumidos salvo los `KEEP_RECENT` últimos. `None` si aún cabe todo.
+    pub fn pending_summary(&self, budget: usize) -> Option<usize> {
+        let until = self.messages.len().saturating_sub(KEEP_RECENT);
+        if self.window_tokens() * 100 < budget * SUMMARY_THRESHOLD || until <= self.summarized_until {
+            return None;
+        }
+        Some(until)
+    }
+
+    /// Petición para resumir hasta `until` (incluye el resumen anterior).
+    pub fn summary_prompt(&self, until: usize) -> String {
+        let mut prompt = String::from(
+            "Summarize the following conversation between a developer and an assistant so it can replace it \
+             as context. Keep decisions, file names, code identifiers, open questions and any code that is still \
+             relevant. Write it in the language of the conversation, at most 300 words.\n\n",
+        );
+        if let Some(summary) = &self.summary {
+            prompt.push_str(&format!("Earlier summary:\n{}\n\n", summary));
+        }
+        for message in &self.messages[self.summarized_until.min(until)..until] {
+            prompt.push_str(&format!("{}:\n{}\n\n", message.role, message.rendered()));
+        }
+        prompt
+    }
+
+    pub fn set_summary(&mut self, summary: String, until: usize) {
+        self.summary = Some(summary);
+        self.summarized_until = until;
+    }
+
+    /// Conversación para el proveedor. `last_user_text` reemplaza el texto del último
+    /// mensaje (p. ej. con el contexto del proyecto añadido) sin guardarlo en la sesión.
+    pub fn ai_messages(&self, last_user_text: Option<String>) -> Vec<AiMessage> {
+        let mut messages = vec![AiMessage { role: "system".to_string(), text: SYSTEM_PROMPT.to_string() }];
+        if let Some(summary) = &self.summary {
+            messages.push(AiMessage {
+                role: "system".to_string(),
+                text: format!("Summary of the earlier conversation:\n{}", summary),
+            });
+        }
+
+        let recent = self.recent();
+        for (i, message) in recent.iter().enumerate() {
+            let text = match &last_user_text {
+                Some(text) if i + 1 == recent.len() && message.role == "user" => {
+                    let mut with_text = message.clone();
+                    with_text.text = text.clone();
+                    with_text.rendered()
+                }
+                _ => message.rendered(),
+            };
+            messages.push(AiMessage { role: message.role.clone(), text });
+        }
+        messages
+    }
+
+    pub fn to_markdown(&self) -> String {
+        let mut out = format!("# {}\n\n", self.title);
+        if let Some(summary) = &self.summary {
+            out.push_str(&format!("> Resumen de los mensajes anteriores:\n> {}\n\n", summary.replace('\n', "\n> ")));
+        }
+        for message in &self.messages {
+            let who = if message.role == "user" { "Tú" } else { "Asistente" };
+            out.push_str(&format!("## {}\n\n", who));
+            for attachment in &message.attachments {
+                out.push_str(&format!("{}\n\n", attachment.render()));
+            }
+            out.push_str(&format!("{}\n\n", message.text.trim_end()));
+        }
+        out
+    }
+}
+
+/// Conversaciones guardadas en `<app_data>/chats/<id>.json`.
+pub struct ChatManager {
+    chats_dir: PathBuf,
+}
+
+impl ChatManager {
+    pub fn new(app_dir: &Path) -> Result<Self, String> {
+        let chats_dir = app_dir.join("chats");
+        fs::create_dir_all(&chats_dir)
+            .map_err(|e| format!("Failed to create chats directory: {}", e))?;
+        Ok(ChatManager { chats_dir })
+    }
+
+    fn session_file(&self, id: &str) -> Result<PathBuf, String> {
+        // El id viene del frontend: que no pueda salir de la carpeta
+        if id.is_empty() || !id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+            return Err(format!("Id de conversación inválido: {}", id));
+        }
+        Ok(self.chats_dir.join(format!("{}.json", id)))
+    }
+
+    pub fn create(&self, title: Option<String>) -> Result<ChatSession, String> {
+        let created_at = now();
+        let session = ChatSession {
+            id: uuid::Uuid::new_v4().to_string(),
+            title: title.filter(|t| !t.trim().is_empty()).unwrap_or_else(|| DEFAULT_TITLE.to_string()),
+            created_at,
+            updated_at: created_at,
+            messages: Vec::new(),
+            summary: None,
+            summarized_until: 0,
+        };
+        self.save(&session)?;
+        Ok(session)
+    }
+
+    pub fn load(&self, id: &str) -> Result<ChatSession, String> {
+        let content = fs::read_to_string(self.session_file(id)?)
+            .map_err(|_| format!("No existe la conversación: {}", id))?;
+        let mut session: ChatSession = serde_json::from_str(&content)
+            .map_err(|e| format!("Conversación dañada {}: {}", id, e))?;
+        session.summarized_until = session.summarized_until.min(session.messages.len());
+        Ok(session)
+    }
+
+    pub fn save(&self, session: &ChatSession) -> Result<(), String> {
+        let json = serde_json::to_string_pretty(session).map_err(|e| e.to_string())?;
+        file_io::write_atomic(&self.session_file(&session.id)?, json.as_bytes())?;
+        Ok(())
+    }
+
+    /// Todas las conversaciones, la más reciente primero.
+    pub fn list(&self) -> Result<Vec<ChatSessionInfo>, String> {
+        let entries = fs::read_dir(&self.chats_dir).map_err(|e| e.to_string())?;
+        let mut sessions: Vec<ChatSessionInfo> = entries
+            .flatten()
+            .filter(|entry| entry.path().extension().map(|e| e == "json").unwrap_or(false))
+            .filter_map(|entry| fs::read_to_string(entry.path()).ok())
+            .filter_map(|content| serde_json::from_str::<ChatSession>(&content).ok())
+            .map(|session| session.info())
+            .collect();
+        sessions.sort_by_key(|s| std::cmp::Reverse(s.updated_at));
+        Ok(sessions)
+    }
+
+    /// Añade los mensajes (y el resumen) de un envío sobre la versión actual del archivo,
+    /// para no pisar lo que cambió mientras esperaba al proveedor (p. ej. un rename).
+    pub fn append(&self, id: &str, messages: Vec<ChatMessage>, summary: Option<(String, usize)>) -> Result<ChatSession, String> {
+        let mut session = self.load(id)?;
+        if let Some((text, until)) = summary {
+            let until = until.min(session.messages.len());
+            session.set_summary(text, until);
+        }
+        for message in messages {
+            session.push(message);
+        }
+        self.save(&session)?;
+        Ok(session)
+    }
+
+    pub fn rename(&self, id: &str, title: &str) -> Result<ChatSessionInfo, String> {
+        let title = title.trim();
+        if title.is_empty() {
+            return Err("El título no puede estar vacío".to_string());
+        }
+        let mut session = self.load(id)?;
+        session.title = title.to_string();
+        self.save(&session)?;
+        Ok(session.info())
+    }
+
+    pub fn delete(&self, id: &str) -> Result<(), String> {
+        fs::remove_file(self.session_file(id)?)
+            .map_err(|_| format!("No existe la conversación: {}", id))
+    }
+
+    /// La conversación como Markdown (`markdown`) o JSON (`json`).
+    pub fn export(&self, id: &str, format: &str) -> Result<String, String> {
+        let session = self.load(id)?;
+        match format {
+            "markdown" | "md" => Ok(session.to_markdown()),
+            "json" => serde_json::to_string_pretty(&session).map_err(|e| e.to_string()),
+            other => Err(format!("Formato de exportación desconocido: {}", other)),
+        }
+    }
+}
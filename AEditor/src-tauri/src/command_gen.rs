@@ -0,0 +1,415 @@
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::Duration;
+
+use crate::ai_context::{self, ContextSection};
+use crate::ai_provider::{self, AiMessage, AiProvider, GenerateRequest};
+use crate::diagnostics::DiagnosticError;
+use crate::project_config::ProjectLayout;
+use crate::refactor;
+use crate::search;
+use crate::templates::{self, ScaffoldKind, ScaffoldRequest};
+use crate::usage::UsageTotals;
+
+const MAX_MODELS: usize = 3;
+const MAX_EXAMPLES: usize = 2;
+// Rondas extra en las que se le devuelven al modelo los errores de tsc
+const MAX_REPAIRS: usize = 1;
+const DEFAULT_CATEGORY: &str = "general";
+// Marca de los archivos temporales de `type_check` (`.ping.1a2b3c4d.aeditor-check.ts`)
+const TYPE_CHECK_MARKER: &str = ".aeditor-check.";
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TypeCheck {
+    // false si no se pudo ejecutar tsc (sin node_modules, sin TypeScript...)
+    pub checked: bool,
+    pub errors: Vec<DiagnosticError>,
+    pub message: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GeneratedCommand {
+    pub name: String,
+    pub category: String,
+    pub path: String,
+    pub relative_path: String,
+    pub content: String,
+    // Ya hay un archivo en esa ruta: no se puede crear sin cambiar el nombre
+    pub exists: bool,
+    pub context: Vec<ContextSection>,
+    pub type_check: TypeCheck,
+    pub attempts: usize,
+    pub usage: UsageTotals,
+}
+
+// Ruta donde la plantilla de comandos slash del proyecto guardaría el comando
+fn target(project_root: &Path, name: &str, category: &str) -> Result<templates::ScaffoldResult, String> {
+    templates::render_scaffold(project_root, &ScaffoldRequest {
+        kind: Some(ScaffoldKind::SlashCommand),
+        name: name.to_string(),
+        category: category.to_string(),
+        // Solo se usa la ruta, pero la plantilla la exige
+        description: "Comando generado".to_string(),
+        ..Default::default()
+    })
+}
+
+/// Contexto para generar el comando: el tipo `CommandSlash`, los modelos de Prisma que
+/// nombra la descripción y los comandos existentes más parecidos.
+pub fn gather_context(
+    project_root: &Path,
+    layout: &ProjectLayout,
+    description: &str,
+    target_path: &str,
+) -> Result<Vec<ContextSection>, String> {
+    let mut sections = Vec::new();
+    let files = search::project_files(project_root)?;
+    let src_prefix = format!("{}/", layout.src_dir.trim_end_matches('/'));
+    let commands_prefix = format!("{}/", layout.commands_dir.trim_end_matches('/'));
+
+    // Dónde está definido `CommandSlash` y cómo importarlo desde el archivo nuevo
+    let types = files.iter()
+        .filter(|(_, relative)| relative.starts_with(&src_prefix) && relative.ends_with(".ts"))
+        .find_map(|(path, relative)| {
+            let content = fs::read_to_string(path).ok()?;
+            content.contains("interface CommandSlash").then(|| (relative.clone(), content))
+        });
+    if let Some((relative, content)) = types {
+        let specifier = refactor::relative_specifier(target_path, relative.trim_end_matches(".ts"));
+        let content = format!(
+            "// import type {{ CommandSlash }} from \"{}\";\n{}",
+            specifier,
+            ai_context::signatures(&content).join("\n"),
+        );
+        sections.push(section("types", &relative, content));
+    }
+
+    let schema = fs::read_to_string(layout.prisma_schema_path(project_root)).unwrap_or_default();
+    let definitions = ai_context::prisma_definitions(&schema);
+    let models = relevant_models(&definitions, description);
+    for (name, body) in &models {
+        sections.push(section("prisma", name, body.clone()));
+    }
+    // Enums que usan esos modelos
+    for (name, body) in &definitions {
+        if body.trim_start().starts_with("enum")
+            && models.iter().any(|(_, model)| model.split_whitespace().any(|word| word.trim_end_matches(['?', '[', ']']) == name))
+        {
+            sections.push(section("prisma", name, body.clone()));
+        }
+    }
+
+    // Ejemplos: el comando slash más parecido y el que mejor muestre cómo se usan los modelos
+    // (a igualdad, el más corto)
+    let mut examples: Vec<(bool, bool, usize, String, String)> = files.iter()
+        .filter(|(_, relative)| relative.starts_with(&commands_prefix) && relative.ends_with(".ts"))
+        .filter_map(|(path, relative)| {
+            let content = fs::read_to_string(path).ok()?;
+            let lines: Vec<String> = content.lines().map(|l| l.to_string()).collect();
+            refactor::find_command_name(&lines)?;
+
+            let slash = content.contains("CommandSlash");
+            let uses_models = models.iter().any(|(name, _)| ai_context::mentions_model(&content, name));
+            Some((uses_models, slash, content.len(), relative.clone(), content))
+        })
+        .collect();
+    examples.sort_by(|a, b| b.0.cmp(&a.0).then(b.1.cmp(&a.1)).then(a.2.cmp(&b.2)));
+
+    let mut chosen: Vec<usize> = Vec::new();
+    let preferred = [examples.iter().position(|e| e.1), examples.iter().position(|e| e.0)];
+    for i in preferred.into_iter().flatten().chain(0..examples.len()) {
+        if chosen.len() >= MAX_EXAMPLES {
+            break;
+        }
+        if !chosen.contains(&i) {
+            chosen.push(i);
+        }
+    }
+    for i in chosen {
+        let (_, _, _, relative, content) = &examples[i];
+        sections.push(section("example", relative, ai_context::command_excerpt(content)));
+    }
+
+    Ok(sections)
+}
+
+fn section(kind: &str, source: &str, content: String) -> ContextSection {
+    ContextSection {
+        kind: kind.to_string(),
+        source: source.to_string(),
+        tokens: ai_context::estimate_tokens(&content),
+        content,
+    }
+}
+
+// Modelos nombrados en la descripción: primero los que aparecen con su nombre exacto,
+// después los que contienen alguna palabra de la descripción ("wallet" → EconomyWallet)
+fn relevant_models(definitions: &[(String, String)], description: &str) -> Vec<(String, String)> {
+    let lower = description.to_lowercase();
+    let names: Vec<String> = definitions.iter().map(|(name, _)| name.to_lowercase()).collect();
+    // Una palabra que ya es el nombre de un modelo no cuenta como parcial ("user" no trae UserMusicPreferences)
+    let words: Vec<&str> = lower
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| word.len() >= 4 && !names.iter().any(|name| name == word))
+        .collect();
+
+    let mut scored: Vec<(usize, &(String, String))> = definitions.iter()
+        .filter(|(_, body)| body.trim_start().starts_with("model"))
+        .filter_map(|definition| {
+            let name = definition.0.to_lowercase();
+            let exact = lower.split(|c: char| !c.is_alphanumeric()).any(|word| word == name);
+            let partial = words.iter().filter(|word| name.contains(*word)).count();
+            match (exact, partial) {
+                (true, _) => Some((100, definition)),
+                (false, 0) => None,
+                (false, n) => Some((n, definition)),
+            }
+        })
+        .collect();
+    scored.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.0.len().cmp(&b.1.0.len())));
+    scored.into_iter().take(MAX_MODELS).map(|(_, definition)| definition.clone()).collect()
+}
+
+fn prompt(description: &str, target_path: &str, sections: &[ContextSection]) -> String {
+    let mut prompt = format!(
+        "You write commands for a Discord bot in TypeScript (discord.js v14, Prisma).\n\
+         Write a new slash command module for this request:\n{}\n\n\
+         The file will be saved as `{}`.\n\
+         It must `export const command: CommandSlash = {{ ... }}` with `name` (lowercase, no spaces), \
+         `description`, `type: \"slash\"`, optional `options` and an async `run(interaction, client)`.\n\
+         Use only the types, Prisma models and APIs shown below, access the database the way the examples do \
+         and follow their style. Write user-facing messages in Spanish.\n",
+        description.trim(),
+        target_path,
+    );
+    for section in sections {
+        let lang = if section.kind == "prisma" { "prisma" } else { "ts" };
+        prompt.push_str(&format!("\n### {}: {}\n```{}\n{}\n```\n", section.kind, section.source, lang, section.content));
+    }
+    prompt.push_str("\nReply with the complete file in a single ```ts code block and nothing else.");
+    prompt
+}
+
+// El código del primer bloque ``` de la respuesta (o la respuesta entera si no hay bloque)
+fn extract_code(text: &str) -> String {
+    let block_re = Regex::new(r"(?s)```[\w-]*\r?\n(.*?)```").unwrap();
+    let code = block_re.captures(text)
+        .map(|cap| cap[1].to_string())
+        .unwrap_or_else(|| text.to_string());
+    format!("{}\n", code.trim_end())
+}
+
+/// Genera un comando slash a partir de una descripción y lo valida con tsc.
+/// No escribe nada: el resultado es para previsualizar. `record` recibe cada petición
+/// al mandarla y sus tokens al llegar la respuesta.
+pub async fn generate(
+    provider: &dyn AiProvider,
+    project_root: &Path,
+    description: &str,
+    category: Option<&str>,
+    thinking: bool,
+    record: &(dyn Fn(&UsageTotals) + Sync),
+) -> Result<GeneratedCommand, String> {
+    if description.trim().is_empty() {
+        return Err("Describe el comando a generar".to_string());
+    }
+    let layout = ProjectLayout::load(project_root)?;
+    let category = category.map(str::trim).filter(|c| !c.is_empty()).unwrap_or(DEFAULT_CATEGORY);
+
+    // El nombre aún no se conoce; para los imports basta con la carpeta de destino
+    let draft_path = target(project_root, "command", category)?.relative_path;
+    let context = gather_context(project_root, &layout, description, &draft_path)?;
+
+    let mut messages = vec![AiMessage { role: "user".to_string(), text: prompt(description, &draft_path, &context) }];
+    let mut attempts = 0;
+    let mut usage = UsageTotals::default();
+    loop {
+        attempts += 1;
+        let request = GenerateRequest {
+            messages: messages.clone(),
+            temperature: 0.2,
+            max_output_tokens: 4096,
+            thinking,
+            timeout: Duration::from_secs(60),
+        };
+        record(&UsageTotals::request(None));
+        let response = ai_provider::generate(provider, &request).await?;
+        usage.add_request(response.usage.as_ref());
+        record(&UsageTotals::tokens(response.usage.as_ref()));
+        let content = extract_code(&response.text);
+
+        let lines: Vec<String> = content.lines().map(|l| l.to_string()).collect();
+        let (_, _, name) = refactor::find_command_name(&lines)
+            .ok_or_else(|| "La respuesta no contiene un comando con `name`".to_string())?;
+        let scaffold = target(project_root, &name, category)?;
+
+        let root = project_root.to_path_buf();
+        let (relative, code) = (scaffold.relative_path.clone(), content.clone());
+        let type_check = tokio::task::spawn_blocking(move || type_check(&root, &relative, &code))
+            .await
+            .map_err(|e| e.to_string())?;
+
+        if type_check.errors.is_empty() || attempts > MAX_REPAIRS {
+            return Ok(GeneratedCommand {
+                name,
+                category: category.to_string(),
+                exists: Path::new(&scaffold.path).exists(),
+                path: scaffold.path,
+                relative_path: scaffold.relative_path,
+                content,
+                context,
+                type_check,
+                attempts,
+                usage,
+            });
+        }
+
+        let errors: Vec<String> = type_check.errors.iter()
+            .map(|e| format!("line {}:{} {} {}", e.line, e.column, e.code.as_deref().unwrap_or(""), e.message))
+            .collect();
+        messages.push(AiMessage { role: "assistant".to_string(), text: response.text });
+        messages.push(AiMessage {
+            role: "user".to_string(),
+            text: format!(
+                "The TypeScript checker reported these errors:\n{}\n\nReply with the corrected complete file in a single ```ts code block.",
+                errors.join("\n"),
+            ),
+        });
+    }
+}
+
+/// Archivos temporales de `type_check`, escritos junto al comando mientras corre tsc.
+pub fn is_type_check_file(name: &str) -> bool {
+    name.starts_with('.') && name.contains(TYPE_CHECK_MARKER)
+}
+
+/// Comprueba `content` con el tsc del proyecto como si estuviera en `relative_path`.
+/// Usa un archivo temporal junto al destino (para que los imports relativos resuelvan)
+/// y un tsconfig que extiende el del proyecto; los dos se borran al terminar.
+pub fn type_check(project_root: &Path, relative_path: &str, content: &str) -> TypeCheck {
+    // Solo el tsc del proyecto: `npx tsc` sin TypeScript instalado descargaría otro paquete
+    let tsc = project_root.join("node_modules").join(".bin").join(if cfg!(windows) { "tsc.cmd" } else { "tsc" });
+    if !tsc.exists() {
+        return TypeCheck {
+            message: Some("TypeScript no está instalado en el proyecto (falta node_modules/.bin/tsc)".to_string()),
+            ..Default::default()
+        };
+    }
+
+    let target = project_root.join(relative_path);
+    let Some(dir) = target.parent() else {
+        return TypeCheck { message: Some("Ruta inválida".to_string()), ..Default::default() };
+    };
+
+    // Carpetas que no existían y hay que quitar después
+    let mut created: Vec<PathBuf> = Vec::new();
+    let mut missing = Some(dir);
+    while let Some(d) = missing.filter(|d| !d.exists()) {
+        created.push(d.to_path_buf());
+        missing = d.parent();
+    }
+    if let Err(e) = fs::create_dir_all(dir) {
+        return TypeCheck { message: Some(e.to_string()), ..Default::default() };
+    }
+
+    let id = uuid::Uuid::new_v4().simple().to_string();
+    let stem = target.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+    // Ocultos y con `TYPE_CHECK_MARKER`: el loader del bot y los escaneos del editor los saltan
+    let temp_name = format!(".{}.{}{}ts", stem, &id[..8], TYPE_CHECK_MARKER);
+    let config_name = format!(".{}.{}{}tsconfig.json", stem, &id[..8], TYPE_CHECK_MARKER);
+    let temp = dir.join(&temp_name);
+    let config = dir.join(&config_name);
+
+    let relative_dir = relative_path.rsplit_once('/').map(|(d, _)| d).unwrap_or("");
+    let mut tsconfig = serde_json::json!({
+        "compilerOptions": { "noEmit": true, "incremental": false },
+        "files": [temp_name],
+        "include": [],
+    });
+    if project_root.join("tsconfig.json").exists() {
+        tsconfig["extends"] = serde_json::json!(refactor::relative_specifier(&format!("{}/{}", relative_dir, config_name), "tsconfig.json"));
+    }
+
+    let result = fs::write(&temp, content)
+        .and_then(|_| fs::write(&config, tsconfig.to_string()))
+        .map_err(|e| e.to_string())
+        .and_then(|_| run_tsc(&tsc, project_root, &config));
+
+    let _ = fs::remove_file(&temp);
+    let _ = fs::remove_file(&config);
+    for d in &created {
+        let _ = fs::remove_dir(d);
+    }
+
+    let output = match result {
+        Ok(output) => output,
+        Err(e) => return TypeCheck { message: Some(e), ..Default::default() },
+    };
+
+    let (errors, others) = parse_tsc(&output, &temp_name, relative_path);
+    if errors.is_empty() && others == 0 && !output.contains("error TS") && !output.trim().is_empty() {
+        // tsc no llegó a compilar (no está instalado, tsconfig inválido...)
+        let message: String = output.trim().chars().take(300).collect();
+        return TypeCheck { message: Some(message), ..Default::default() };
+    }
+
+    TypeCheck {
+        checked: true,
+        errors,
+        message: (others > 0).then(|| format!("{} errores en otros archivos del proyecto (ignorados)", others)),
+    }
+}
+
+fn run_tsc(tsc: &Path, project_root: &Path, config: &Path) -> Result<String, String> {
+    let output = Command::new(tsc)
+        .arg("-p")
+        .arg(config)
+        .args(["--pretty", "false"])
+        .current_dir(project_root)
+        .output()
+        .map_err(|e| format!("No se pudo ejecutar tsc: {}", e))?;
+
+    Ok(format!("{}{}", String::from_utf8_lossy(&output.stdout), String::from_utf8_lossy(&output.stderr)))
+}
+
+// Errores del archivo temporal (con la ruta real) y cuántos hay en otros archivos
+fn parse_tsc(output: &str, temp_name: &str, relative_path: &str) -> (Vec<DiagnosticError>, usize) {
+    let error_re = Regex::new(r"^(.+?)\((\d+),(\d+)\): (error|warning) (TS\d+): (.*)$").unwrap();
+    let mut errors: Vec<DiagnosticError> = Vec::new();
+    let mut others = 0;
+    let mut in_temp = false;
+
+    for line in output.lines() {
+        if let Some(cap) = error_re.captures(line) {
+            in_temp = cap[1].replace('\\', "/").ends_with(temp_name);
+            if !in_temp {
+                others += 1;
+                continue;
+            }
+            errors.push(DiagnosticError {
+                id: uuid::Uuid::new_v4().to_string(),
+                severity: cap[4].to_string(),
+                message: cap[6].to_string(),
+                file: relative_path.to_string(),
+                line: cap[2].parse().unwrap_or(1),
+                column: cap[3].parse().unwrap_or(1),
+                code: Some(cap[5].to_string()),
+                suggestion: None,
+                fixable: Some(false),
+                source: Some("tsc".to_string()),
+            });
+        } else if in_temp && line.starts_with(' ') {
+            // Continuación del mensaje anterior
+            if let Some(last) = errors.last_mut() {
+                last.message.push('\n');
+                last.message.push_str(line.trim());
+            }
+        }
+    }
+    (errors, others)
+}
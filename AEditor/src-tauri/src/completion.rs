@@ -0,0 +1,129 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::ai_provider::CancelToken;
+use crate::file_io;
+
+// Espera antes de llamar a la API; si llega otra petición del mismo documento, esta se descarta
+pub const DEBOUNCE: Duration = Duration::from_millis(150);
+const CACHE_CAPACITY: usize = 256;
+const MAX_REQUESTS_PER_MINUTE: usize = 30;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CompletionStats {
+    pub requests: u64,
+    pub cache_hits: u64,
+    // Peticiones reemplazadas por otra más nueva del mismo documento
+    pub cancelled: u64,
+    pub rate_limited: u64,
+    pub api_calls: u64,
+}
+
+struct CacheEntry {
+    suggestions: Vec<String>,
+    last_used: u64,
+}
+
+/// Estado compartido del autocompletado inline: la petición en curso de cada documento,
+/// una caché LRU de sugerencias y el límite de llamadas por minuto.
+#[derive(Default)]
+pub struct CompletionService {
+    in_flight: HashMap<String, Arc<CancelToken>>,
+    cache: HashMap<String, CacheEntry>,
+    // Contador para el orden LRU
+    tick: u64,
+    recent_calls: VecDeque<Instant>,
+    stats: CompletionStats,
+}
+
+impl CompletionService {
+    /// Clave de caché: el mismo modelo con el mismo texto antes y después del cursor
+    /// y el mismo contexto del proyecto (imports, índice, schema...) da la misma sugerencia.
+    /// `context_key` resume ese contexto sin armarlo.
+    pub fn cache_key(model: &str, language: &str, prefix: &str, suffix: &str, context_key: &str) -> String {
+        file_io::hash_bytes(
+            format!("{}\0{}\0{}\0{}\0{}", model, language, prefix, suffix, context_key).as_bytes(),
+        )
+    }
+
+    pub fn cached(&mut self, key: &str) -> Option<Vec<String>> {
+        self.stats.requests += 1;
+        self.tick += 1;
+
+        let entry = self.cache.get_mut(key)?;
+        entry.last_used = self.tick;
+        self.stats.cache_hits += 1;
+        Some(entry.suggestions.clone())
+    }
+
+    /// Registra una petición nueva para `document` y cancela la anterior, que ya no sirve.
+    pub fn begin(&mut self, document: &str) -> Arc<CancelToken> {
+        let token = Arc::new(CancelToken::default());
+        if let Some(previous) = self.in_flight.insert(document.to_string(), token.clone()) {
+            previous.cancel();
+            self.stats.cancelled += 1;
+        }
+        token
+    }
+
+    /// Cancela la petición en curso de `document` (por ejemplo, al cerrar el archivo).
+    pub fn cancel(&mut self, document: &str) {
+        if let Some(token) = self.in_flight.remove(document) {
+            token.cancel();
+            self.stats.cancelled += 1;
+        }
+    }
+
+    /// Consume un lugar del límite por minuto; `false` si ya se alcanzó.
+    pub fn try_acquire(&mut self) -> bool {
+        let now = Instant::now();
+        while self.recent_calls.front().map(|t| now.duration_since(*t) > Duration::from_secs(60)).unwrap_or(false) {
+            self.recent_calls.pop_front();
+        }
+
+        if self.recent_calls.len() >= MAX_REQUESTS_PER_MINUTE {
+            self.stats.rate_limited += 1;
+            return false;
+        }
+        self.recent_calls.push_back(now);
+        self.stats.api_calls += 1;
+        true
+    }
+
+    /// Termina la petición de `document` y guarda las sugerencias (si las hubo) en la caché.
+    pub fn finish(&mut self, document: &str, token: &Arc<CancelToken>, key: &str, suggestions: Option<&[String]>) {
+        if self.in_flight.get(document).map(|t| Arc::ptr_eq(t, token)).unwrap_or(false) {
+            self.in_flight.remove(document);
+        }
+
+        let Some(suggestions) = suggestions.filter(|s| !s.is_empty()) else {
+            return;
+        };
+
+        if self.cache.len() >= CACHE_CAPACITY && !self.cache.contains_key(key) {
+            let oldest = self.cache.iter()
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(key, _)| key.clone());
+            if let Some(oldest) = oldest {
+                self.cache.remove(&oldest);
+            }
+        }
+
+        self.tick += 1;
+        self.cache.insert(key.to_string(), CacheEntry {
+            suggestions: suggestions.to_vec(),
+            last_used: self.tick,
+        });
+    }
+
+    pub fn clear_cache(&mut self) {
+        self.cache.clear();
+    }
+
+    pub fn stats(&self) -> CompletionStats {
+        self.stats.clone()
+    }
+}
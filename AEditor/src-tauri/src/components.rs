@@ -0,0 +1,319 @@
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ComponentInfo {
+    pub name: String,
+    pub path: String,
+    pub relative_path: String,
+    pub kind: String,
+    pub custom_id: Option<String>,
+    pub folder: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CustomIdReference {
+    pub custom_id: String,
+    pub file: String,
+    pub line: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ComponentScan {
+    pub handlers: Vec<ComponentInfo>,
+    // customIds emitidos en el código que ningún handler atiende
+    pub unhandled_ids: Vec<CustomIdReference>,
+    // Handlers cuyo customId nunca se emite en el proyecto
+    pub unused_handlers: Vec<ComponentInfo>,
+}
+
+/// Determina el tipo de componente según la carpeta, igual que `loadComponents` en el bot.
+pub fn component_kind(relative_path: &str) -> &'static str {
+    let lower = relative_path.to_lowercase();
+    if lower.contains("buttons") {
+        "button"
+    } else if lower.contains("modals") {
+        "modal"
+    } else if lower.contains("selectmenus") {
+        "selectMenu"
+    } else if lower.contains("contextmenu") {
+        "contextMenu"
+    } else {
+        "unknown"
+    }
+}
+
+/// Escanea los handlers de `components_dir` y cruza sus customIds con los emitidos en `src_dir`.
+pub fn scan_components(components_dir: &Path, src_dir: &Path) -> Result<ComponentScan, String> {
+    let handler_re = Regex::new(r#"customId\s*:\s*["'`]([^"'`]+)["'`]"#).unwrap();
+
+    let mut handler_files = Vec::new();
+    collect_source_files(components_dir, &mut handler_files)?;
+
+    let mut handlers = Vec::new();
+    for path in &handler_files {
+        let relative_path = path.strip_prefix(components_dir)
+            .unwrap_or(path)
+            .to_string_lossy()
+            .to_string();
+
+        let folder = path.parent()
+            .and_then(|p| p.strip_prefix(components_dir).ok())
+            .and_then(|p| p.to_str())
+            .filter(|s| !s.is_empty())
+            .map(|s| s.to_string());
+
+        // El customId del handler está en el objeto exportado por defecto
+        let custom_id = fs::read_to_string(path)
+            .ok()
+            .and_then(|content| {
+                let start = content.find("export default").unwrap_or(0);
+                handler_re.captures(&content[start..])
+                    .and_then(|cap| cap.get(1))
+                    .map(|m| m.as_str().to_string())
+            });
+
+        handlers.push(ComponentInfo {
+            name: path.file_stem()
+                .and_then(|s| s.to_str())
+                .unwrap_or("unknown")
+                .to_string(),
+            path: path.to_string_lossy().to_string(),
+            relative_path: relative_path.clone(),
+            kind: component_kind(&relative_path).to_string(),
+            custom_id,
+            folder,
+        });
+    }
+
+    let mut files = Vec::new();
+    collect_source_files(src_dir, &mut files)?;
+
+    let sources: Vec<(PathBuf, String)> = files.into_iter()
+        .filter_map(|path| fs::read_to_string(&path).ok().map(|content| (path, content)))
+        .collect();
+
+    let references = scan_custom_id_references(src_dir, &sources, &handlers);
+
+    // Un customId emitido como "prefijo:datos" lo atiende el handler "prefijo"
+    let is_handled = |id: &str| {
+        let prefix = id.split(':').next().unwrap_or(id);
+        handlers.iter().any(|h| {
+            h.custom_id.as_deref() == Some(id) || h.custom_id.as_deref() == Some(prefix)
+        })
+    };
+
+    let unhandled_ids = references.iter()
+        .filter(|r| !is_handled(&r.custom_id))
+        .cloned()
+        .collect();
+
+    // Además de las emisiones directas, cuenta el id como literal en otro archivo
+    // (p. ej. pasado como argumento a un helper que construye los botones)
+    let unused_handlers = handlers.iter()
+        .filter(|h| match &h.custom_id {
+            Some(id) => {
+                let emitted = references.iter().any(|r| {
+                    r.custom_id == *id || r.custom_id.split(':').next() == Some(id.as_str())
+                });
+                !emitted && !sources.iter().any(|(path, content)| {
+                    path.to_string_lossy() != h.path && contains_id_literal(content, id)
+                })
+            }
+            None => false,
+        })
+        .cloned()
+        .collect();
+
+    Ok(ComponentScan {
+        handlers,
+        unhandled_ids,
+        unused_handlers,
+    })
+}
+
+/// Busca los customIds que el código emite (`custom_id: "..."`, `customId: "..."`, `.setCustomId("...")`).
+/// Los ids que se comparan en el mismo archivo (`i.customId === "..."` o un `case "..."` de un
+/// `switch` sobre `customId`, p. ej. en un collector propio) se consideran atendidos localmente y
+/// no se reportan si coinciden exactamente; los de `customId.startsWith("...")`, por prefijo.
+/// Tampoco se reportan los campos de texto de modales (`getTextInputValue("...")`).
+fn scan_custom_id_references(
+    src_dir: &Path,
+    sources: &[(PathBuf, String)],
+    handlers: &[ComponentInfo],
+) -> Vec<CustomIdReference> {
+    let emit_re = Regex::new(
+        r#"(?:custom_id\s*:|customId\s*:|setCustomId\()\s*["'`]([^"'`$]+)"#
+    ).unwrap();
+    let local_re = Regex::new(r#"customId\s*===?\s*["'`]([^"'`$]+)"#).unwrap();
+    let prefix_re = Regex::new(r#"customId\.startsWith\(\s*["'`]([^"'`$]+)"#).unwrap();
+    let switch_re = Regex::new(r"switch\s*\(\s*[\w.?]*\bcustomId\s*\)\s*\{").unwrap();
+    let case_re = Regex::new(r#"case\s+["'`]([^"'`$]+)"#).unwrap();
+    let text_input_re = Regex::new(r#"getTextInputValue\(\s*["'`]([^"'`$]+)"#).unwrap();
+
+    // El customId propio de cada handler no cuenta como emisión
+    let own_ids: HashMap<&str, &str> = handlers.iter()
+        .filter_map(|h| h.custom_id.as_deref().map(|id| (h.path.as_str(), id)))
+        .collect();
+
+    let text_inputs: Vec<String> = sources.iter()
+        .flat_map(|(_, content)| text_input_re.captures_iter(content))
+        .filter_map(|cap| cap.get(1).map(|m| m.as_str().to_string()))
+        .collect();
+
+    let mut references = Vec::new();
+    for (path, content) in sources {
+        let path_str = path.to_string_lossy().to_string();
+        let mut local_ids: Vec<String> = local_re.captures_iter(content)
+            .filter_map(|cap| cap.get(1).map(|m| m.as_str().to_string()))
+            .collect();
+        for block in switch_re.find_iter(content).map(|m| block_body(content, m.end())) {
+            local_ids.extend(case_re.captures_iter(block)
+                .filter_map(|cap| cap.get(1).map(|m| m.as_str().to_string())));
+        }
+        let local_prefixes: Vec<String> = prefix_re.captures_iter(content)
+            .filter_map(|cap| cap.get(1).map(|m| m.as_str().to_string()))
+            .collect();
+
+        for (line_num, line) in content.lines().enumerate() {
+            for cap in emit_re.captures_iter(line) {
+                let id = match cap.get(1) {
+                    Some(m) => m.as_str().to_string(),
+                    None => continue,
+                };
+
+                if own_ids.get(path_str.as_str()) == Some(&id.as_str()) {
+                    continue;
+                }
+                if local_ids.contains(&id) || local_prefixes.iter().any(|prefix| id.starts_with(prefix.as_str())) {
+                    continue;
+                }
+                if text_inputs.contains(&id) {
+                    continue;
+                }
+
+                references.push(CustomIdReference {
+                    custom_id: id,
+                    file: path.strip_prefix(src_dir)
+                        .unwrap_or(path)
+                        .to_string_lossy()
+                        .to_string(),
+                    line: line_num + 1,
+                });
+            }
+        }
+    }
+
+    references
+}
+
+// Cuerpo de un bloque desde justo después de su `{` hasta la `}` que lo cierra
+fn block_body(content: &str, start: usize) -> &str {
+    let mut depth = 1;
+    for (i, c) in content[start..].char_indices() {
+        match c {
+            '{' => depth += 1,
+            '}' => {
+                depth -= 1;
+                if depth == 0 {
+                    return &content[start..start + i];
+                }
+            }
+            _ => {}
+        }
+    }
+    &content[start..]
+}
+
+fn contains_id_literal(content: &str, id: &str) -> bool {
+    ['"', '\'', '`'].iter().any(|quote| {
+        content.contains(&format!("{}{}{}", quote, id, quote))
+            || content.contains(&format!("{}{}:", quote, id))
+    })
+}
+
+fn collect_source_files(dir: &Path, files: &mut Vec<PathBuf>) -> Result<(), String> {
+    if !dir.exists() {
+        return Ok(());
+    }
+
+    for entry in fs::read_dir(dir).map_err(|e| e.to_string())? {
+        let entry = entry.map_err(|e| e.to_string())?;
+        let path = entry.path();
+
+        if path.is_dir() {
+            collect_source_files(&path, files)?;
+        } else if let Some(name) = path.file_name().and_then(|n| n.to_str()) {
+            if (name.ends_with(".ts") || name.ends_with(".js")) && !name.ends_with(".d.ts") {
+                files.push(path);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn local_handlers_only_hide_their_exact_ids() {
+        let root = std::env::temp_dir().join(format!("aeditor-components-{}", uuid::Uuid::new_v4().simple()));
+        let src = root.join("src");
+        fs::create_dir_all(src.join("components")).unwrap();
+        fs::write(src.join("commands.ts"), r#"
+client.on("interactionCreate", async (i) => {
+    if (i.customId === "confirm") return;
+});
+const row = [
+    new ButtonBuilder().setCustomId("confirm"),
+    new ButtonBuilder().setCustomId("confirm_delete"),
+];
+const collector = message.createMessageComponentCollector();
+collector.on("collect", (i) => {
+    switch (i.customId) {
+        case "page_next": break;
+    }
+});
+new ButtonBuilder().setCustomId("page_next");
+new ButtonBuilder().setCustomId("page_nextAll");
+"#).unwrap();
+
+        let scan = scan_components(&src.join("components"), &src).unwrap();
+        let unhandled: Vec<&str> = scan.unhandled_ids.iter().map(|r| r.custom_id.as_str()).collect();
+        assert_eq!(unhandled, vec!["confirm_delete", "page_nextAll"]);
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn prefix_collectors_and_unrelated_switches() {
+        let root = std::env::temp_dir().join(format!("aeditor-components-{}", uuid::Uuid::new_v4().simple()));
+        let src = root.join("src");
+        fs::create_dir_all(src.join("components")).unwrap();
+        fs::write(src.join("commands.ts"), r#"
+collector.on("collect", (i) => {
+    if (i.customId.startsWith("page_")) return paginate(i);
+});
+new ButtonBuilder().setCustomId("page_1");
+new ButtonBuilder().setCustomId("page_2");
+switch (action) {
+    case "ban": break;
+}
+new ButtonBuilder().setCustomId("ban");
+"#).unwrap();
+
+        let scan = scan_components(&src.join("components"), &src).unwrap();
+        let unhandled: Vec<&str> = scan.unhandled_ids.iter().map(|r| r.custom_id.as_str()).collect();
+        assert_eq!(unhandled, vec!["ban"]);
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,165 @@
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::file_io;
+use crate::ignore_rules::IgnoreRules;
+use crate::refactor;
+use crate::sandbox::FsError;
+use crate::templates::to_case;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CommandRename {
+    pub file: String,
+    pub old_name: String,
+    pub new_name: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CopyResult {
+    // Destino final, con el sufijo " copy" si hubo colisión
+    pub path: String,
+    pub copied: Vec<String>,
+    pub renamed_commands: Vec<CommandRename>,
+}
+
+/// Devuelve `path` si está libre o la primera variante libre al estilo del explorador:
+/// `ping.ts` → `ping copy.ts` → `ping copy 2.ts`. En carpetas el sufijo va al final del nombre.
+pub fn unique_destination(path: &Path) -> PathBuf {
+    if fs::symlink_metadata(path).is_err() {
+        return path.to_path_buf();
+    }
+
+    let parent = path.parent().unwrap_or(Path::new(""));
+    let name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
+    let (stem, ext) = match name.rfind('.') {
+        Some(i) if i > 0 && !path.is_dir() => (&name[..i], &name[i..]),
+        _ => (name, ""),
+    };
+
+    // Duplicar "ping copy.ts" da "ping copy 2.ts", no "ping copy copy.ts"
+    let suffix_re = Regex::new(r" copy(?: \d+)?$").unwrap();
+    let base = suffix_re.replace(stem, "");
+
+    (1..)
+        .map(|n| match n {
+            1 => parent.join(format!("{} copy{}", base, ext)),
+            n => parent.join(format!("{} copy {}{}", base, n, ext)),
+        })
+        .find(|candidate| fs::symlink_metadata(candidate).is_err())
+        .unwrap()
+}
+
+/// Copia un archivo a `destination` (o a un nombre libre junto a él si ya existe).
+pub fn copy_file(source: &Path, destination: &Path, rename_command: bool) -> Result<CopyResult, FsError> {
+    if !source.is_file() {
+        return Err(FsError::NotAFile(source.display().to_string()));
+    }
+
+    let target = unique_destination(destination);
+    if let Some(parent) = target.parent() {
+        fs::create_dir_all(parent).map_err(|e| FsError::from_io(e, parent))?;
+    }
+    fs::copy(source, &target).map_err(|e| FsError::from_io(e, source))?;
+
+    let mut renamed_commands = Vec::new();
+    if rename_command {
+        renamed_commands.extend(rename_copied_command(&target)?);
+    }
+
+    Ok(CopyResult {
+        path: target.to_string_lossy().to_string(),
+        copied: vec![target.to_string_lossy().to_string()],
+        renamed_commands,
+    })
+}
+
+/// Copia una carpeta de forma recursiva saltando lo que ignoran `.gitignore`/`.aeditorignore`
+/// (y siempre `node_modules`, `.git`...). Las carpetas que quedan vacías no se copian.
+pub fn copy_folder(
+    project_root: &Path,
+    source: &Path,
+    destination: &Path,
+    rename_commands: bool,
+) -> Result<CopyResult, FsError> {
+    if !source.is_dir() {
+        return Err(FsError::NotADirectory(source.display().to_string()));
+    }
+
+    // Se listan los archivos antes de copiar, así copiar una carpeta dentro de sí misma no se repite
+    let mut files = Vec::new();
+    IgnoreRules::load(project_root)
+        .collect_files(project_root, source, &mut files)
+        .map_err(|e| FsError::Io(source.display().to_string(), e))?;
+
+    let target = unique_destination(destination);
+    fs::create_dir_all(&target).map_err(|e| FsError::from_io(e, &target))?;
+
+    let mut copied = Vec::new();
+    let mut renamed_commands = Vec::new();
+    for file in files {
+        let Ok(relative) = file.strip_prefix(source) else {
+            continue;
+        };
+        let to = target.join(relative);
+        if let Some(parent) = to.parent() {
+            fs::create_dir_all(parent).map_err(|e| FsError::from_io(e, parent))?;
+        }
+        fs::copy(&file, &to).map_err(|e| FsError::from_io(e, &file))?;
+
+        if rename_commands {
+            renamed_commands.extend(rename_copied_command(&to)?);
+        }
+        copied.push(to.to_string_lossy().to_string());
+    }
+
+    Ok(CopyResult {
+        path: target.to_string_lossy().to_string(),
+        copied,
+        renamed_commands,
+    })
+}
+
+// Si el archivo es un módulo de comando, cambia su `name` para que la copia no choque
+// al registrarse: se usa el nombre del archivo nuevo (`ping copy.ts` → `ping-copy`) o,
+// si coincide con el actual, se le agrega `-copy`.
+fn rename_copied_command(path: &Path) -> Result<Option<CommandRename>, FsError> {
+    let is_module = matches!(
+        path.extension().and_then(|e| e.to_str()),
+        Some("ts" | "js" | "mts" | "mjs")
+    );
+    if !is_module {
+        return Ok(None);
+    }
+
+    let file = file_io::read_versioned(path)?;
+    if file.binary || !(file.content.contains("CommandSlash") || file.content.contains("CommandMessage")) {
+        return Ok(None);
+    }
+
+    let mut lines: Vec<String> = file.content.split('\n').map(|l| l.to_string()).collect();
+    let Some((line, column, old_name)) = refactor::find_command_name(&lines) else {
+        return Ok(None);
+    };
+
+    let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or("");
+    let mut new_name = to_case(stem, "kebab");
+    if new_name.is_empty() || new_name == old_name {
+        new_name = format!("{}-copy", old_name);
+    }
+
+    let text = &mut lines[line - 1];
+    text.replace_range(column..column + old_name.len(), &new_name);
+
+    let bytes = file_io::encode(path, &lines.join("\n"), file.format)?;
+    file_io::write_atomic(path, &bytes)?;
+
+    Ok(Some(CommandRename {
+        file: path.to_string_lossy().to_string(),
+        old_name,
+        new_name,
+    }))
+}
@@ -0,0 +1,278 @@
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+// Enum `Events` de discord.js v14: (clave, nombre del evento)
+const DISCORD_EVENTS: &[(&str, &str)] = &[
+    ("ApplicationCommandPermissionsUpdate", "applicationCommandPermissionsUpdate"),
+    ("AutoModerationActionExecution", "autoModerationActionExecution"),
+    ("AutoModerationRuleCreate", "autoModerationRuleCreate"),
+    ("AutoModerationRuleDelete", "autoModerationRuleDelete"),
+    ("AutoModerationRuleUpdate", "autoModerationRuleUpdate"),
+    ("CacheSweep", "cacheSweep"),
+    ("ChannelCreate", "channelCreate"),
+    ("ChannelDelete", "channelDelete"),
+    ("ChannelPinsUpdate", "channelPinsUpdate"),
+    ("ChannelUpdate", "channelUpdate"),
+    ("ClientReady", "ready"),
+    ("Debug", "debug"),
+    ("EntitlementCreate", "entitlementCreate"),
+    ("EntitlementDelete", "entitlementDelete"),
+    ("EntitlementUpdate", "entitlementUpdate"),
+    ("Error", "error"),
+    ("GuildAuditLogEntryCreate", "guildAuditLogEntryCreate"),
+    ("GuildAvailable", "guildAvailable"),
+    ("GuildBanAdd", "guildBanAdd"),
+    ("GuildBanRemove", "guildBanRemove"),
+    ("GuildCreate", "guildCreate"),
+    ("GuildDelete", "guildDelete"),
+    ("GuildEmojiCreate", "emojiCreate"),
+    ("GuildEmojiDelete", "emojiDelete"),
+    ("GuildEmojiUpdate", "emojiUpdate"),
+    ("GuildIntegrationsUpdate", "guildIntegrationsUpdate"),
+    ("GuildMemberAdd", "guildMemberAdd"),
+    ("GuildMemberAvailable", "guildMemberAvailable"),
+    ("GuildMemberRemove", "guildMemberRemove"),
+    ("GuildMembersChunk", "guildMembersChunk"),
+    ("GuildMemberUpdate", "guildMemberUpdate"),
+    ("GuildRoleCreate", "roleCreate"),
+    ("GuildRoleDelete", "roleDelete"),
+    ("GuildRoleUpdate", "roleUpdate"),
+    ("GuildScheduledEventCreate", "guildScheduledEventCreate"),
+    ("GuildScheduledEventDelete", "guildScheduledEventDelete"),
+    ("GuildScheduledEventUpdate", "guildScheduledEventUpdate"),
+    ("GuildScheduledEventUserAdd", "guildScheduledEventUserAdd"),
+    ("GuildScheduledEventUserRemove", "guildScheduledEventUserRemove"),
+    ("GuildStickerCreate", "stickerCreate"),
+    ("GuildStickerDelete", "stickerDelete"),
+    ("GuildStickerUpdate", "stickerUpdate"),
+    ("GuildUnavailable", "guildUnavailable"),
+    ("GuildUpdate", "guildUpdate"),
+    ("InteractionCreate", "interactionCreate"),
+    ("Invalidated", "invalidated"),
+    ("InviteCreate", "inviteCreate"),
+    ("InviteDelete", "inviteDelete"),
+    ("MessageBulkDelete", "messageDeleteBulk"),
+    ("MessageCreate", "messageCreate"),
+    ("MessageDelete", "messageDelete"),
+    ("MessagePollVoteAdd", "messagePollVoteAdd"),
+    ("MessagePollVoteRemove", "messagePollVoteRemove"),
+    ("MessageReactionAdd", "messageReactionAdd"),
+    ("MessageReactionRemove", "messageReactionRemove"),
+    ("MessageReactionRemoveAll", "messageReactionRemoveAll"),
+    ("MessageReactionRemoveEmoji", "messageReactionRemoveEmoji"),
+    ("MessageUpdate", "messageUpdate"),
+    ("PresenceUpdate", "presenceUpdate"),
+    ("Raw", "raw"),
+    ("ShardDisconnect", "shardDisconnect"),
+    ("ShardError", "shardError"),
+    ("ShardReady", "shardReady"),
+    ("ShardReconnecting", "shardReconnecting"),
+    ("ShardResume", "shardResume"),
+    ("StageInstanceCreate", "stageInstanceCreate"),
+    ("StageInstanceDelete", "stageInstanceDelete"),
+    ("StageInstanceUpdate", "stageInstanceUpdate"),
+    ("ThreadCreate", "threadCreate"),
+    ("ThreadDelete", "threadDelete"),
+    ("ThreadListSync", "threadListSync"),
+    ("ThreadMembersUpdate", "threadMembersUpdate"),
+    ("ThreadMemberUpdate", "threadMemberUpdate"),
+    ("ThreadUpdate", "threadUpdate"),
+    ("TypingStart", "typingStart"),
+    ("UserUpdate", "userUpdate"),
+    ("VoiceServerUpdate", "voiceServerUpdate"),
+    ("VoiceStateUpdate", "voiceStateUpdate"),
+    ("Warn", "warn"),
+    ("WebhooksUpdate", "webhookUpdate"),
+];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EventBinding {
+    pub event_name: String,
+    // Tal como aparece en el código: `Events.MessageCreate` o `"messageCreate"`
+    pub raw: String,
+    pub once: bool,
+    pub known: bool,
+    pub file: String,
+    pub line: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EventScan {
+    pub bindings: Vec<EventBinding>,
+    // Eventos con más de un handler registrado
+    pub duplicates: BTreeMap<String, Vec<EventBinding>>,
+    pub unknown: Vec<EventBinding>,
+    pub counts: BTreeMap<String, u32>,
+}
+
+/// Resuelve `Events.X` o un nombre en string al nombre real del evento.
+/// Devuelve `None` si no pertenece al enum `Events` de discord.js v14.
+pub fn resolve_event_name(raw: &str) -> Option<&'static str> {
+    if let Some(key) = raw.strip_prefix("Events.") {
+        DISCORD_EVENTS.iter().find(|(k, _)| *k == key).map(|(_, v)| *v)
+    } else {
+        DISCORD_EVENTS.iter().find(|(_, v)| *v == raw).map(|(_, v)| *v)
+    }
+}
+
+/// Clave del enum `Events` (`MessageCreate`) a partir de la clave o del nombre del evento.
+pub fn event_key(raw: &str) -> Option<&'static str> {
+    let raw = raw.strip_prefix("Events.").unwrap_or(raw);
+    DISCORD_EVENTS.iter()
+        .find(|(k, v)| *k == raw || *v == raw)
+        .map(|(k, _)| *k)
+}
+
+/// Extrae los eventos a los que se suscribe un archivo, tanto con
+/// `bot.on(Events.X, ...)` / `client.once("x", ...)` como con el formato
+/// `export default { name, once, execute }` que usa `loadEvents`. Lo comentado no cuenta.
+pub fn extract_bindings(content: &str, file: &str) -> Vec<EventBinding> {
+    let content = &strip_comments(content);
+    let listener_re = Regex::new(
+        r#"\b(?:bot|client)\s*\.\s*(on|once)\(\s*(Events\.\w+|["'`]\w+["'`])"#
+    ).unwrap();
+    let export_name_re = Regex::new(r#"\bname\s*:\s*(Events\.\w+|["'`]\w+["'`])"#).unwrap();
+    let once_re = Regex::new(r"\bonce\s*:\s*true").unwrap();
+
+    let mut bindings = Vec::new();
+
+    for (line_num, line) in content.lines().enumerate() {
+        for cap in listener_re.captures_iter(line) {
+            let raw = cap[2].trim_matches(|c| c == '"' || c == '\'' || c == '`').to_string();
+            bindings.push(make_binding(raw, &cap[1] == "once", file, line_num + 1));
+        }
+    }
+
+    if let Some(start) = content.find("export default") {
+        let exported = &content[start..];
+        if exported.contains("execute") {
+            if let Some(cap) = export_name_re.captures(exported) {
+                let raw = cap[1].trim_matches(|c| c == '"' || c == '\'' || c == '`').to_string();
+                let line = content[..start].lines().count() + 1;
+                bindings.push(make_binding(raw, once_re.is_match(exported), file, line));
+            }
+        }
+    }
+
+    bindings
+}
+
+// Cambia los comentarios `//` y `/* */` por espacios, conservando los saltos de línea
+// (los números de línea no cambian) y sin tocar el contenido de los strings
+fn strip_comments(content: &str) -> String {
+    let mut result = String::with_capacity(content.len());
+    let mut chars = content.chars().peekable();
+    let mut quote: Option<char> = None;
+
+    while let Some(c) = chars.next() {
+        if let Some(q) = quote {
+            result.push(c);
+            if c == '\\' {
+                if let Some(escaped) = chars.next() {
+                    result.push(escaped);
+                }
+            } else if c == q {
+                quote = None;
+            }
+            continue;
+        }
+
+        match (c, chars.peek()) {
+            ('/', Some('/')) => {
+                while let Some(&next) = chars.peek() {
+                    if next == '\n' {
+                        break;
+                    }
+                    chars.next();
+                }
+            }
+            ('/', Some('*')) => {
+                chars.next();
+                let mut previous = ' ';
+                for next in chars.by_ref() {
+                    if next == '\n' {
+                        result.push('\n');
+                    }
+                    if previous == '*' && next == '/' {
+                        break;
+                    }
+                    previous = next;
+                }
+                result.push(' ');
+            }
+            ('"' | '\'' | '`', _) => {
+                quote = Some(c);
+                result.push(c);
+            }
+            _ => result.push(c),
+        }
+    }
+
+    result
+}
+
+fn make_binding(raw: String, once: bool, file: &str, line: usize) -> EventBinding {
+    let resolved = resolve_event_name(&raw);
+    EventBinding {
+        event_name: resolved
+            .map(|name| name.to_string())
+            .unwrap_or_else(|| raw.trim_start_matches("Events.").to_string()),
+        raw,
+        once,
+        known: resolved.is_some(),
+        file: file.to_string(),
+        line,
+    }
+}
+
+/// Agrupa los bindings encontrados y detecta duplicados y nombres desconocidos.
+pub fn build_scan(bindings: Vec<EventBinding>) -> EventScan {
+    let mut by_name: BTreeMap<String, Vec<EventBinding>> = BTreeMap::new();
+    for binding in &bindings {
+        by_name.entry(binding.event_name.clone())
+            .or_default()
+            .push(binding.clone());
+    }
+
+    let counts = by_name.iter()
+        .map(|(name, list)| (name.clone(), list.len() as u32))
+        .collect();
+
+    let duplicates = by_name.into_iter()
+        .filter(|(_, list)| list.len() > 1)
+        .collect();
+
+    let unknown = bindings.iter()
+        .filter(|b| !b.known)
+        .cloned()
+        .collect();
+
+    EventScan {
+        bindings,
+        duplicates,
+        unknown,
+        counts,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn commented_listeners_are_ignored() {
+        let content = r#"
+// bot.on(Events.MessageCreate, handler);
+/* client.once("ready", () => {});
+   bot.on(Events.GuildCreate, handler); */
+const url = "https://discord.com"; bot.on(Events.InteractionCreate, handler);
+client.once("ready", () => {}); // bot.on(Events.MessageCreate, x)
+"#;
+        let bindings = extract_bindings(content, "index.ts");
+        let found: Vec<(&str, usize)> = bindings.iter().map(|b| (b.event_name.as_str(), b.line)).collect();
+        assert_eq!(found, vec![("interactionCreate", 5), ("ready", 6)]);
+    }
+}
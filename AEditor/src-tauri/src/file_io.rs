@@ -0,0 +1,280 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::Path;
+use std::time::UNIX_EPOCH;
+
+use crate::sandbox::FsError;
+
+/// Versión de un archivo tal como estaba al leerlo. El frontend la devuelve
+/// al guardar para detectar si alguien lo modificó en disco mientras tanto.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FileVersion {
+    pub hash: String,
+    pub modified: u64,
+    pub size: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum TextEncoding {
+    Utf8,
+    Utf8Bom,
+    Utf16Le,
+    Utf16Be,
+    Latin1,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum LineEnding {
+    Lf,
+    Crlf,
+}
+
+/// Formato en disco de un archivo de texto. Se conserva al guardar salvo
+/// que el usuario pida convertirlo explícitamente.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TextFormat {
+    pub encoding: TextEncoding,
+    pub line_ending: LineEnding,
+}
+
+impl Default for TextFormat {
+    fn default() -> Self {
+        TextFormat {
+            encoding: TextEncoding::Utf8,
+            line_ending: LineEnding::Lf,
+        }
+    }
+}
+
+/// Contenido de un archivo para el editor, siempre con saltos de línea `\n`.
+/// Si el archivo es binario, `content` queda vacío y `binary` es `true`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FileContent {
+    pub content: String,
+    pub version: FileVersion,
+    pub format: TextFormat,
+    pub binary: bool,
+    // El archivo mezclaba LF y CRLF; al guardar se unifica en `format.line_ending`
+    pub mixed_line_endings: bool,
+}
+
+pub fn hash_bytes(bytes: &[u8]) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(bytes);
+    format!("{:x}", hasher.finalize())
+}
+
+fn version_of(path: &Path, bytes: &[u8]) -> Result<FileVersion, FsError> {
+    let metadata = fs::metadata(path).map_err(|e| FsError::from_io(e, path))?;
+    let modified = metadata.modified()
+        .ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0);
+
+    Ok(FileVersion {
+        hash: hash_bytes(bytes),
+        modified,
+        size: bytes.len() as u64,
+    })
+}
+
+pub fn read_versioned(path: &Path) -> Result<FileContent, FsError> {
+    let bytes = fs::read(path).map_err(|e| FsError::from_io(e, path))?;
+    let version = version_of(path, &bytes)?;
+
+    let Some((text, encoding)) = decode(&bytes) else {
+        return Ok(FileContent {
+            content: String::new(),
+            version,
+            format: TextFormat::default(),
+            binary: true,
+            mixed_line_endings: false,
+        });
+    };
+
+    let (line_ending, mixed_line_endings) = detect_line_ending(&text);
+
+    Ok(FileContent {
+        content: text.replace("\r\n", "\n"),
+        version,
+        format: TextFormat { encoding, line_ending },
+        binary: false,
+        mixed_line_endings,
+    })
+}
+
+/// Formato del archivo en disco, o el formato por defecto si no existe o es binario.
+pub fn detect_format(path: &Path) -> TextFormat {
+    fs::read(path)
+        .ok()
+        .and_then(|bytes| {
+            let (text, encoding) = decode(&bytes)?;
+            let (line_ending, _) = detect_line_ending(&text);
+            Some(TextFormat { encoding, line_ending })
+        })
+        .unwrap_or_default()
+}
+
+// Salto de línea mayoritario y si el archivo mezcla ambos
+fn detect_line_ending(text: &str) -> (LineEnding, bool) {
+    let crlf = text.matches("\r\n").count();
+    let lf = text.matches('\n').count() - crlf;
+    let line_ending = if crlf > lf { LineEnding::Crlf } else { LineEnding::Lf };
+    (line_ending, crlf > 0 && lf > 0)
+}
+
+/// Decodifica según el BOM; sin BOM prueba UTF-8 y si no es válido usa Latin-1.
+/// Devuelve `None` si el contenido parece binario o si tiene BOM pero no es válido en esa
+/// codificación: se trata como binario para que guardarlo no cambie bytes por U+FFFD.
+pub fn decode(bytes: &[u8]) -> Option<(String, TextEncoding)> {
+    if let Some(rest) = bytes.strip_prefix(&[0xEF, 0xBB, 0xBF]) {
+        let text = std::str::from_utf8(rest).ok()?;
+        return Some((text.to_string(), TextEncoding::Utf8Bom));
+    }
+    if let Some(rest) = bytes.strip_prefix(&[0xFF, 0xFE]) {
+        return Some((decode_utf16(rest, u16::from_le_bytes)?, TextEncoding::Utf16Le));
+    }
+    if let Some(rest) = bytes.strip_prefix(&[0xFE, 0xFF]) {
+        return Some((decode_utf16(rest, u16::from_be_bytes)?, TextEncoding::Utf16Be));
+    }
+
+    if looks_binary(bytes) {
+        return None;
+    }
+
+    match std::str::from_utf8(bytes) {
+        Ok(text) => Some((text.to_string(), TextEncoding::Utf8)),
+        Err(_) => Some((bytes.iter().map(|b| *b as char).collect(), TextEncoding::Latin1)),
+    }
+}
+
+// Sin pérdidas: un byte suelto al final o un surrogate sin pareja no se pueden guardar igual
+fn decode_utf16(bytes: &[u8], unit: fn([u8; 2]) -> u16) -> Option<String> {
+    if !bytes.len().is_multiple_of(2) {
+        return None;
+    }
+    let units = bytes.chunks_exact(2).map(|c| unit([c[0], c[1]]));
+    char::decode_utf16(units).collect::<Result<String, _>>().ok()
+}
+
+/// Un NUL o muchos caracteres de control en los primeros 8 KB
+pub fn looks_binary(bytes: &[u8]) -> bool {
+    let sample = &bytes[..bytes.len().min(8000)];
+    if sample.contains(&0) {
+        return true;
+    }
+    let control = sample.iter()
+        .filter(|b| **b < 0x20 && !matches!(**b, b'\t' | b'\n' | b'\r' | 0x0C | 0x1B))
+        .count();
+    control * 10 > sample.len()
+}
+
+/// Convierte el texto del editor al formato indicado (saltos de línea y codificación).
+pub fn encode(path: &Path, content: &str, format: TextFormat) -> Result<Vec<u8>, FsError> {
+    let normalized = content.replace("\r\n", "\n");
+    let text = match format.line_ending {
+        LineEnding::Lf => normalized,
+        LineEnding::Crlf => normalized.replace('\n', "\r\n"),
+    };
+
+    let bytes = match format.encoding {
+        TextEncoding::Utf8 => text.into_bytes(),
+        TextEncoding::Utf8Bom => [&[0xEF, 0xBB, 0xBF][..], text.as_bytes()].concat(),
+        TextEncoding::Utf16Le => [0xFF, 0xFE].into_iter()
+            .chain(text.encode_utf16().flat_map(|u| u.to_le_bytes()))
+            .collect(),
+        TextEncoding::Utf16Be => [0xFE, 0xFF].into_iter()
+            .chain(text.encode_utf16().flat_map(|u| u.to_be_bytes()))
+            .collect(),
+        TextEncoding::Latin1 => {
+            let mut bytes = Vec::with_capacity(text.len());
+            for c in text.chars() {
+                let code = c as u32;
+                if code > 0xFF {
+                    return Err(FsError::Encoding(
+                        path.display().to_string(),
+                        format!("el carácter '{}' no existe en Latin-1", c),
+                    ));
+                }
+                bytes.push(code as u8);
+            }
+            bytes
+        }
+    };
+
+    Ok(bytes)
+}
+
+/// Versión actual en disco, o `None` si el archivo no existe.
+pub fn current_version(path: &Path) -> Result<Option<FileVersion>, FsError> {
+    match fs::read(path) {
+        Ok(bytes) => version_of(path, &bytes).map(Some),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(FsError::from_io(e, path)),
+    }
+}
+
+/// Falla con `FsError::Conflict` si el archivo cambió respecto a `expected`.
+/// Se compara el hash: un `touch` o un guardado con el mismo contenido no es un conflicto.
+pub fn check_version(path: &Path, expected: &FileVersion) -> Result<(), FsError> {
+    match current_version(path)? {
+        Some(current) if current.hash == expected.hash => Ok(()),
+        _ => Err(FsError::Conflict(path.display().to_string())),
+    }
+}
+
+/// Escribe en un archivo temporal en la misma carpeta, hace fsync y lo renombra
+/// sobre el destino, para que un corte a mitad de escritura nunca deje el archivo truncado.
+pub fn write_atomic(path: &Path, bytes: &[u8]) -> Result<FileVersion, FsError> {
+    // Un symlink se conserva: se escribe sobre su destino
+    let target;
+    let path = if fs::symlink_metadata(path).map(|m| m.file_type().is_symlink()).unwrap_or(false) {
+        target = path.canonicalize().map_err(|e| FsError::from_io(e, path))?;
+        target.as_path()
+    } else {
+        path
+    };
+
+    let parent = path.parent()
+        .ok_or_else(|| FsError::NotAFile(path.display().to_string()))?;
+    fs::create_dir_all(parent).map_err(|e| FsError::from_io(e, parent))?;
+
+    let file_name = path.file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or("file");
+    let temp_path = parent.join(format!(".{}.{}.tmp", file_name, uuid::Uuid::new_v4()));
+
+    let result = (|| {
+        let mut file = File::create(&temp_path).map_err(|e| FsError::from_io(e, &temp_path))?;
+        file.write_all(bytes).map_err(|e| FsError::from_io(e, &temp_path))?;
+        file.sync_all().map_err(|e| FsError::from_io(e, &temp_path))?;
+
+        // Conservar los permisos del archivo original (p. ej. scripts ejecutables)
+        if let Ok(metadata) = fs::metadata(path) {
+            let _ = fs::set_permissions(&temp_path, metadata.permissions());
+        }
+
+        fs::rename(&temp_path, path).map_err(|e| FsError::from_io(e, path))
+    })();
+
+    if result.is_err() {
+        let _ = fs::remove_file(&temp_path);
+    }
+    result?;
+
+    // En Unix el rename solo es durable cuando se sincroniza la carpeta
+    #[cfg(unix)]
+    if let Ok(dir) = File::open(parent) {
+        let _ = dir.sync_all();
+    }
+
+    version_of(path, bytes)
+}
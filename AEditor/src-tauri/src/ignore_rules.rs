@@ -0,0 +1,131 @@
+use regex::Regex;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::command_gen;
+
+// Carpetas que nunca se recorren, haya o no .gitignore
+const ALWAYS_IGNORED: &[&str] = &[".git", "node_modules", "target", "dist", "build"];
+
+struct Rule {
+    regex: Regex,
+    negated: bool,
+    dir_only: bool,
+}
+
+/// Subconjunto de la sintaxis de `.gitignore` (comentarios, `!`, `/` inicial y final,
+/// `*`, `?` y `**`) leído de la raíz del proyecto.
+pub struct IgnoreRules {
+    rules: Vec<Rule>,
+}
+
+impl IgnoreRules {
+    pub fn load(project_root: &Path) -> Self {
+        let mut rules = Vec::new();
+
+        for file in [".gitignore", ".aeditorignore"] {
+            if let Ok(content) = fs::read_to_string(project_root.join(file)) {
+                rules.extend(content.lines().filter_map(parse_rule));
+            }
+        }
+
+        IgnoreRules { rules }
+    }
+
+    fn matches(&self, path: &str, name: &str, is_dir: bool) -> bool {
+        if is_dir && ALWAYS_IGNORED.contains(&name) {
+            return true;
+        }
+        // Temporales de la comprobación de tipos de los comandos generados
+        if !is_dir && command_gen::is_type_check_file(name) {
+            return true;
+        }
+
+        // Como en git, la última regla que coincide gana
+        let mut ignored = false;
+        for rule in &self.rules {
+            if rule.dir_only && !is_dir {
+                continue;
+            }
+            if rule.regex.is_match(path) {
+                ignored = !rule.negated;
+            }
+        }
+        ignored
+    }
+
+    /// Recorre `dir` recursivamente y devuelve los archivos no ignorados.
+    /// Las carpetas ignoradas no se recorren.
+    pub fn collect_files(&self, project_root: &Path, dir: &Path, files: &mut Vec<PathBuf>) -> Result<(), String> {
+        for entry in fs::read_dir(dir).map_err(|e| e.to_string())? {
+            let entry = entry.map_err(|e| e.to_string())?;
+            let path = entry.path();
+            let is_dir = path.is_dir();
+
+            let relative = path.strip_prefix(project_root)
+                .unwrap_or(&path)
+                .to_string_lossy()
+                .replace('\\', "/");
+            let name = relative.rsplit('/').next().unwrap_or("");
+            if self.matches(&relative, name, is_dir) {
+                continue;
+            }
+
+            if is_dir {
+                self.collect_files(project_root, &path, files)?;
+            } else {
+                files.push(path);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn parse_rule(line: &str) -> Option<Rule> {
+    let line = line.trim_end();
+    if line.is_empty() || line.starts_with('#') {
+        return None;
+    }
+
+    let (negated, pattern) = match line.strip_prefix('!') {
+        Some(rest) => (true, rest),
+        None => (false, line),
+    };
+
+    let dir_only = pattern.ends_with('/');
+    let pattern = pattern.trim_end_matches('/');
+
+    // Un patrón con `/` (que no sea final) es relativo a la raíz; si no, coincide a cualquier nivel
+    let anchored = pattern.contains('/');
+    let pattern = pattern.trim_start_matches('/');
+
+    let mut regex = String::from(if anchored { "^" } else { "(?:^|/)" });
+    let chars: Vec<char> = pattern.chars().collect();
+    let mut i = 0;
+    while i < chars.len() {
+        match chars[i] {
+            '*' if chars.get(i + 1) == Some(&'*') => {
+                if chars.get(i + 2) == Some(&'/') {
+                    regex.push_str("(?:.*/)?");
+                    i += 3;
+                } else {
+                    regex.push_str(".*");
+                    i += 2;
+                }
+                continue;
+            }
+            '*' => regex.push_str("[^/]*"),
+            '?' => regex.push_str("[^/]"),
+            c => regex.push_str(&regex::escape(&c.to_string())),
+        }
+        i += 1;
+    }
+    regex.push('$');
+
+    Regex::new(&regex).ok().map(|regex| Rule {
+        regex,
+        negated,
+        dir_only,
+    })
+}
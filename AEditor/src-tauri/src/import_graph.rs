@@ -0,0 +1,257 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap, VecDeque};
+
+use crate::project_index::ProjectIndex;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ModuleNode {
+    pub path: String,
+    pub kind: String,
+    pub imports: Vec<String>,
+    pub imported_by: Vec<String>,
+    pub external: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UnresolvedImport {
+    pub file: String,
+    pub specifier: String,
+    pub line: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ModuleGraph {
+    pub nodes: Vec<ModuleNode>,
+    pub cycles: Vec<Vec<String>>,
+    // Módulos que nadie importa y que no son comandos, eventos, componentes ni el punto de entrada
+    pub unused: Vec<String>,
+    pub unresolved: Vec<UnresolvedImport>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RelatedModule {
+    pub path: String,
+    pub kind: String,
+    // Distancia en imports desde el módulo consultado (1 = directo)
+    pub depth: usize,
+}
+
+/// Clasifica un módulo según la estructura del proyecto.
+pub fn module_kind(index: &ProjectIndex, path: &str) -> &'static str {
+    let layout = index.layout();
+    let under = |dir: &str| {
+        let dir = dir.trim_end_matches('/');
+        path.starts_with(&format!("{}/", dir))
+    };
+
+    if under(&layout.commands_dir) {
+        "command"
+    } else if under(&layout.events_dir) {
+        "event"
+    } else if under(&layout.components_dir) {
+        "component"
+    } else if path.contains("/services/") {
+        "service"
+    } else {
+        "module"
+    }
+}
+
+fn is_entry_point(index: &ProjectIndex, path: &str) -> bool {
+    if matches!(module_kind(index, path), "command" | "event" | "component") {
+        return true;
+    }
+
+    // src/main.ts o src/index.ts
+    let src = index.layout().src_dir.trim_end_matches('/');
+    ["main", "index"].iter().any(|name| {
+        path.strip_prefix(&format!("{}/{}.", src, name)).is_some()
+    })
+}
+
+fn edges(index: &ProjectIndex) -> BTreeMap<String, BTreeSet<String>> {
+    let mut edges = BTreeMap::new();
+    for (path, file) in index.files() {
+        let targets = file.imports.iter()
+            .filter_map(|i| index.resolve(path, &i.specifier))
+            .collect();
+        edges.insert(path.clone(), targets);
+    }
+    edges
+}
+
+pub fn build_graph(index: &ProjectIndex) -> ModuleGraph {
+    let edges = edges(index);
+
+    let mut imported_by: HashMap<&str, BTreeSet<String>> = HashMap::new();
+    for (from, targets) in &edges {
+        for to in targets {
+            imported_by.entry(to.as_str()).or_default().insert(from.clone());
+        }
+    }
+
+    let mut nodes = Vec::new();
+    let mut unresolved = Vec::new();
+    let mut unused = Vec::new();
+
+    for (path, targets) in &edges {
+        let file = &index.files()[path];
+
+        let mut external = BTreeSet::new();
+        for import in &file.imports {
+            if !import.specifier.starts_with('.') {
+                external.insert(import.specifier.clone());
+            } else if index.resolve(path, &import.specifier).is_none() {
+                unresolved.push(UnresolvedImport {
+                    file: path.clone(),
+                    specifier: import.specifier.clone(),
+                    line: import.line,
+                });
+            }
+        }
+
+        let importers: Vec<String> = imported_by.get(path.as_str())
+            .map(|set| set.iter().cloned().collect())
+            .unwrap_or_default();
+
+        if importers.is_empty() && !is_entry_point(index, path) {
+            unused.push(path.clone());
+        }
+
+        nodes.push(ModuleNode {
+            path: path.clone(),
+            kind: module_kind(index, path).to_string(),
+            imports: targets.iter().cloned().collect(),
+            imported_by: importers,
+            external: external.into_iter().collect(),
+        });
+    }
+
+    ModuleGraph {
+        nodes,
+        cycles: find_cycles(&edges),
+        unused,
+        unresolved,
+    }
+}
+
+/// Módulos que dependen (directa o transitivamente) de `module`.
+/// Responde a "¿qué comandos se rompen si cambio este servicio?".
+pub fn dependents(index: &ProjectIndex, module: &str) -> Vec<RelatedModule> {
+    let mut reverse: HashMap<String, Vec<String>> = HashMap::new();
+    for (from, targets) in edges(index) {
+        for to in targets {
+            reverse.entry(to).or_default().push(from.clone());
+        }
+    }
+    walk(index, module, |m| reverse.get(m).cloned().unwrap_or_default())
+}
+
+/// Módulos de los que depende `module` (directa o transitivamente).
+pub fn dependencies(index: &ProjectIndex, module: &str) -> Vec<RelatedModule> {
+    let edges = edges(index);
+    walk(index, module, |m| {
+        edges.get(m).map(|t| t.iter().cloned().collect()).unwrap_or_default()
+    })
+}
+
+fn walk<F>(index: &ProjectIndex, start: &str, next: F) -> Vec<RelatedModule>
+where
+    F: Fn(&str) -> Vec<String>,
+{
+    let mut visited = BTreeSet::new();
+    let mut queue = VecDeque::new();
+    let mut result = Vec::new();
+
+    visited.insert(start.to_string());
+    queue.push_back((start.to_string(), 0));
+
+    while let Some((current, depth)) = queue.pop_front() {
+        for neighbour in next(&current) {
+            if visited.insert(neighbour.clone()) {
+                result.push(RelatedModule {
+                    kind: module_kind(index, &neighbour).to_string(),
+                    path: neighbour.clone(),
+                    depth: depth + 1,
+                });
+                queue.push_back((neighbour, depth + 1));
+            }
+        }
+    }
+
+    result
+}
+
+/// Componentes fuertemente conexos (Tarjan) con más de un módulo o con auto-import.
+fn find_cycles(edges: &BTreeMap<String, BTreeSet<String>>) -> Vec<Vec<String>> {
+    struct State<'a> {
+        edges: &'a BTreeMap<String, BTreeSet<String>>,
+        counter: usize,
+        index: HashMap<&'a str, usize>,
+        low: HashMap<&'a str, usize>,
+        stack: Vec<&'a str>,
+        on_stack: BTreeSet<&'a str>,
+        cycles: Vec<Vec<String>>,
+    }
+
+    fn connect<'a>(state: &mut State<'a>, node: &'a str) {
+        state.index.insert(node, state.counter);
+        state.low.insert(node, state.counter);
+        state.counter += 1;
+        state.stack.push(node);
+        state.on_stack.insert(node);
+
+        if let Some(targets) = state.edges.get(node) {
+            for target in targets {
+                let target = target.as_str();
+                if !state.index.contains_key(target) {
+                    connect(state, target);
+                    let low = state.low[node].min(state.low[target]);
+                    state.low.insert(node, low);
+                } else if state.on_stack.contains(target) {
+                    let low = state.low[node].min(state.index[target]);
+                    state.low.insert(node, low);
+                }
+            }
+        }
+
+        if state.low[node] == state.index[node] {
+            let mut component = Vec::new();
+            while let Some(member) = state.stack.pop() {
+                state.on_stack.remove(member);
+                component.push(member.to_string());
+                if member == node {
+                    break;
+                }
+            }
+
+            let self_loop = state.edges.get(node).map(|t| t.contains(node)).unwrap_or(false);
+            if component.len() > 1 || self_loop {
+                component.reverse();
+                state.cycles.push(component);
+            }
+        }
+    }
+
+    let mut state = State {
+        edges,
+        counter: 0,
+        index: HashMap::new(),
+        low: HashMap::new(),
+        stack: Vec::new(),
+        on_stack: BTreeSet::new(),
+        cycles: Vec::new(),
+    };
+
+    for node in edges.keys() {
+        if !state.index.contains_key(node.as_str()) {
+            connect(&mut state, node);
+        }
+    }
+
+    state.cycles
+}
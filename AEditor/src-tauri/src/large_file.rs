@@ -0,0 +1,361 @@
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+use crate::file_io::{self, TextEncoding};
+use crate::sandbox::FsError;
+
+// Cada cuántas líneas se guarda un offset en el índice de líneas
+const CHECKPOINT_EVERY: u64 = 1000;
+const MAX_CHUNK_SIZE: usize = 4 * 1024 * 1024;
+
+/// Límites para abrir archivos grandes. Hasta `read_only_size` se abren en el editor
+/// normal; hasta `stream_size`, en solo lectura con scroll virtual por líneas;
+/// por encima, en el visor por streaming.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct FileLimits {
+    pub read_only_size: u64,
+    pub stream_size: u64,
+}
+
+impl Default for FileLimits {
+    fn default() -> Self {
+        FileLimits {
+            read_only_size: 1024 * 1024,
+            stream_size: 50 * 1024 * 1024,
+        }
+    }
+}
+
+impl FileLimits {
+    pub fn load(app_dir: &Path) -> Self {
+        fs::read_to_string(app_dir.join("file_limits.json"))
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self, app_dir: &Path) -> Result<(), String> {
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|e| format!("Failed to serialize file limits: {}", e))?;
+
+        fs::write(app_dir.join("file_limits.json"), json)
+            .map_err(|e| format!("Failed to write file limits: {}", e))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum OpenMode {
+    Editor,
+    ReadOnly,
+    Stream,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FileProbe {
+    pub size: u64,
+    // `None` en modo streaming: contar líneas implicaría leer todo el archivo
+    pub line_count: Option<u64>,
+    pub encoding: TextEncoding,
+    pub binary: bool,
+    pub open_mode: OpenMode,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LineChunk {
+    pub start_line: u64,
+    pub lines: Vec<String>,
+    pub total_lines: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ByteChunk {
+    pub offset: u64,
+    pub next_offset: u64,
+    pub size: u64,
+    pub content: String,
+    pub eof: bool,
+}
+
+/// Offsets de inicio de cada `CHECKPOINT_EVERY` líneas, para saltar a cualquier
+/// línea sin leer el archivo desde el principio.
+pub struct LineIndex {
+    modified: u64,
+    size: u64,
+    checkpoints: Vec<u64>,
+    total_lines: u64,
+}
+
+impl LineIndex {
+    pub fn build(path: &Path) -> Result<Self, FsError> {
+        let (modified, size) = stamp(path)?;
+        let file = File::open(path).map_err(|e| FsError::from_io(e, path))?;
+        let mut reader = BufReader::with_capacity(64 * 1024, file);
+
+        let mut checkpoints = vec![bom_len(path)?];
+        reader.seek(SeekFrom::Start(checkpoints[0])).map_err(|e| FsError::from_io(e, path))?;
+
+        let mut offset = checkpoints[0];
+        let mut total_lines = 0u64;
+        let mut line = Vec::new();
+        loop {
+            line.clear();
+            let read = reader.read_until(b'\n', &mut line).map_err(|e| FsError::from_io(e, path))?;
+            if read == 0 {
+                break;
+            }
+            offset += read as u64;
+            total_lines += 1;
+            if total_lines % CHECKPOINT_EVERY == 0 {
+                checkpoints.push(offset);
+            }
+        }
+
+        Ok(LineIndex {
+            modified,
+            size,
+            checkpoints,
+            total_lines,
+        })
+    }
+
+    /// Si el archivo cambió desde que se construyó el índice hay que reconstruirlo
+    pub fn is_stale(&self, path: &Path) -> bool {
+        stamp(path).map(|s| s != (self.modified, self.size)).unwrap_or(true)
+    }
+}
+
+fn stamp(path: &Path) -> Result<(u64, u64), FsError> {
+    let metadata = fs::metadata(path).map_err(|e| FsError::from_io(e, path))?;
+    let modified = metadata.modified()
+        .ok()
+        .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0);
+    Ok((modified, metadata.len()))
+}
+
+fn read_head(path: &Path, len: usize) -> Result<Vec<u8>, FsError> {
+    let file = File::open(path).map_err(|e| FsError::from_io(e, path))?;
+    let mut head = Vec::with_capacity(len);
+    file.take(len as u64).read_to_end(&mut head).map_err(|e| FsError::from_io(e, path))?;
+    Ok(head)
+}
+
+fn bom_len(path: &Path) -> Result<u64, FsError> {
+    let head = read_head(path, 3)?;
+    Ok(if head.starts_with(&[0xEF, 0xBB, 0xBF]) { 3 } else { 0 })
+}
+
+fn detect_encoding(head: &[u8]) -> TextEncoding {
+    if head.starts_with(&[0xEF, 0xBB, 0xBF]) {
+        TextEncoding::Utf8Bom
+    } else if head.starts_with(&[0xFF, 0xFE]) {
+        TextEncoding::Utf16Le
+    } else if head.starts_with(&[0xFE, 0xFF]) {
+        TextEncoding::Utf16Be
+    } else if std::str::from_utf8(head).is_ok() || invalid_only_at_end(head) {
+        TextEncoding::Utf8
+    } else {
+        TextEncoding::Latin1
+    }
+}
+
+// La muestra puede cortar un carácter UTF-8 multibyte al final
+fn invalid_only_at_end(head: &[u8]) -> bool {
+    match std::str::from_utf8(head) {
+        Ok(_) => true,
+        Err(e) => e.error_len().is_none(),
+    }
+}
+
+fn is_utf16(encoding: TextEncoding) -> bool {
+    matches!(encoding, TextEncoding::Utf16Le | TextEncoding::Utf16Be)
+}
+
+/// Tamaño, líneas, codificación y modo en que conviene abrir el archivo.
+pub fn probe(path: &Path, limits: &FileLimits) -> Result<FileProbe, FsError> {
+    let size = fs::metadata(path).map_err(|e| FsError::from_io(e, path))?.len();
+    let head = read_head(path, 8000)?;
+    let encoding = detect_encoding(&head);
+    let binary = !is_utf16(encoding) && file_io::looks_binary(&head);
+
+    // El índice por líneas trabaja sobre bytes `\n`, así que UTF-16 grande va directo a streaming
+    let open_mode = if size <= limits.read_only_size {
+        OpenMode::Editor
+    } else if size <= limits.stream_size && !is_utf16(encoding) {
+        OpenMode::ReadOnly
+    } else {
+        OpenMode::Stream
+    };
+
+    let line_count = match open_mode {
+        OpenMode::Stream => None,
+        _ if binary || is_utf16(encoding) => None,
+        _ => Some(count_lines(path)?),
+    };
+
+    Ok(FileProbe {
+        size,
+        line_count,
+        encoding,
+        binary,
+        open_mode,
+    })
+}
+
+fn count_lines(path: &Path) -> Result<u64, FsError> {
+    let mut file = File::open(path).map_err(|e| FsError::from_io(e, path))?;
+    let mut buffer = vec![0u8; 64 * 1024];
+    let mut newlines = 0u64;
+    let mut last = b'\n';
+
+    loop {
+        let read = file.read(&mut buffer).map_err(|e| FsError::from_io(e, path))?;
+        if read == 0 {
+            break;
+        }
+        newlines += buffer[..read].iter().filter(|b| **b == b'\n').count() as u64;
+        last = buffer[read - 1];
+    }
+
+    // Una última línea sin `\n` también cuenta
+    Ok(if last == b'\n' { newlines } else { newlines + 1 })
+}
+
+/// Lee `count` líneas a partir de `start_line` (desde 0), sin los saltos de línea.
+pub fn read_lines(path: &Path, index: &LineIndex, start_line: u64, count: u64) -> Result<LineChunk, FsError> {
+    let encoding = detect_encoding(&read_head(path, 8000)?);
+    if is_utf16(encoding) {
+        return Err(FsError::Encoding(
+            path.display().to_string(),
+            "los archivos UTF-16 grandes solo se pueden leer por bloques".to_string(),
+        ));
+    }
+
+    let mut lines = Vec::new();
+    if start_line < index.total_lines {
+        let checkpoint = (start_line / CHECKPOINT_EVERY) as usize;
+        let mut file = File::open(path).map_err(|e| FsError::from_io(e, path))?;
+        file.seek(SeekFrom::Start(index.checkpoints[checkpoint])).map_err(|e| FsError::from_io(e, path))?;
+        let mut reader = BufReader::with_capacity(64 * 1024, file);
+
+        let mut current = checkpoint as u64 * CHECKPOINT_EVERY;
+        let end = start_line.saturating_add(count).min(index.total_lines);
+        let mut line = Vec::new();
+        while current < end {
+            line.clear();
+            if reader.read_until(b'\n', &mut line).map_err(|e| FsError::from_io(e, path))? == 0 {
+                break;
+            }
+            if current >= start_line {
+                while matches!(line.last(), Some(b'\n') | Some(b'\r')) {
+                    line.pop();
+                }
+                lines.push(decode_bytes(&line, encoding));
+            }
+            current += 1;
+        }
+    }
+
+    Ok(LineChunk {
+        start_line,
+        lines,
+        total_lines: index.total_lines,
+    })
+}
+
+/// Lee hasta `max_bytes` desde `offset`. El bloque se corta en el último salto de línea
+/// (o en un límite de carácter) para no partir líneas ni caracteres entre dos bloques.
+pub fn read_chunk(path: &Path, offset: u64, max_bytes: usize) -> Result<ByteChunk, FsError> {
+    let size = fs::metadata(path).map_err(|e| FsError::from_io(e, path))?.len();
+    let encoding = detect_encoding(&read_head(path, 8000)?);
+
+    // Saltar el BOM en el primer bloque y mantener UTF-16 alineado a 2 bytes
+    let bom = match encoding {
+        TextEncoding::Utf8Bom => 3,
+        TextEncoding::Utf16Le | TextEncoding::Utf16Be => 2,
+        _ => 0,
+    };
+    let mut start = offset.max(bom);
+    if is_utf16(encoding) && (start - bom) % 2 == 1 {
+        start -= 1;
+    }
+
+    let mut file = File::open(path).map_err(|e| FsError::from_io(e, path))?;
+    file.seek(SeekFrom::Start(start)).map_err(|e| FsError::from_io(e, path))?;
+    let mut bytes = Vec::new();
+    file.take(max_bytes.clamp(1, MAX_CHUNK_SIZE) as u64)
+        .read_to_end(&mut bytes)
+        .map_err(|e| FsError::from_io(e, path))?;
+
+    let eof = start + bytes.len() as u64 >= size;
+    if !eof {
+        let cut = chunk_boundary(&bytes, encoding);
+        bytes.truncate(cut);
+    }
+
+    let content = decode_bytes(&bytes, encoding).replace("\r\n", "\n");
+
+    Ok(ByteChunk {
+        offset: start,
+        next_offset: start + bytes.len() as u64,
+        size,
+        content,
+        eof,
+    })
+}
+
+fn chunk_boundary(bytes: &[u8], encoding: TextEncoding) -> usize {
+    if is_utf16(encoding) {
+        let newline: [u8; 2] = if encoding == TextEncoding::Utf16Le { [b'\n', 0] } else { [0, b'\n'] };
+        return bytes.chunks_exact(2)
+            .rposition(|unit| unit == newline)
+            .map(|i| (i + 1) * 2)
+            .unwrap_or(bytes.len() - bytes.len() % 2);
+    }
+
+    if let Some(i) = bytes.iter().rposition(|b| *b == b'\n') {
+        return i + 1;
+    }
+    if encoding == TextEncoding::Latin1 {
+        return bytes.len();
+    }
+    // Sin saltos de línea: retroceder hasta el inicio de un carácter UTF-8
+    let mut cut = bytes.len();
+    while cut > 0 && (bytes[cut - 1] & 0xC0) == 0x80 {
+        cut -= 1;
+    }
+    if cut > 0 && bytes[cut - 1] >= 0xC0 {
+        cut -= 1;
+    }
+    if cut == 0 { bytes.len() } else { cut }
+}
+
+fn decode_bytes(bytes: &[u8], encoding: TextEncoding) -> String {
+    match encoding {
+        TextEncoding::Utf8 | TextEncoding::Utf8Bom => String::from_utf8_lossy(bytes).into_owned(),
+        TextEncoding::Latin1 => bytes.iter().map(|b| *b as char).collect(),
+        TextEncoding::Utf16Le | TextEncoding::Utf16Be => {
+            let units = bytes.chunks_exact(2).map(|c| {
+                if encoding == TextEncoding::Utf16Le {
+                    u16::from_le_bytes([c[0], c[1]])
+                } else {
+                    u16::from_be_bytes([c[0], c[1]])
+                }
+            });
+            char::decode_utf16(units).map(|c| c.unwrap_or('\u{FFFD}')).collect()
+        }
+    }
+}
+
+/// Ruta canónica usada como clave del caché de índices de líneas
+pub fn cache_key(path: &Path) -> PathBuf {
+    path.canonicalize().unwrap_or_else(|_| path.to_path_buf())
+}
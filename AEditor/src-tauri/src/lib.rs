@@ -1,24 +1,94 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fs;
-use std::path::Path;
-use std::sync::Mutex;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
 use std::time::{SystemTime, UNIX_EPOCH};
 use serde::{Deserialize, Serialize};
 use discord_rich_presence::{activity, DiscordIpc, DiscordIpcClient};
+use tauri::{Emitter, Manager};
+use tauri_plugin_dialog::{DialogExt, MessageDialogButtons, MessageDialogKind};
 
 // Módulos nuevos
 mod activity_log;
+mod agent;
+mod ai_context;
+mod ai_provider;
 mod backup;
+mod chat;
+mod command_gen;
+mod completion;
+mod components;
 mod diagnostics;
+mod duplicate;
+mod events;
+mod file_io;
+mod ignore_rules;
+mod import_graph;
+mod large_file;
+mod local_history;
+mod project_config;
+mod project_index;
+mod refactor;
+mod sandbox;
+mod search;
+mod secrets;
+mod semantic_index;
+mod templates;
+mod trash;
+mod usage;
+mod workspace_edit;
 
 use activity_log::{ActivityLog, LogEntry};
+use agent::AgentRun;
+use ai_context::ProjectContext;
+use ai_provider::{AiConfig, AiProvider, CancelToken, GenerateRequest, ProviderKind, TokenUsage};
 use backup::{Backup, BackupManager};
+use chat::{ChatAttachment, ChatManager, ChatMessage, ChatSession, ChatSessionInfo};
+use command_gen::GeneratedCommand;
+use completion::{CompletionService, CompletionStats};
+use components::ComponentScan;
 use diagnostics::{DiagnosticsManager, DiagnosticError};
+use duplicate::CopyResult;
+use events::EventScan;
+use file_io::{FileContent, FileVersion, TextFormat};
+use import_graph::{ModuleGraph, RelatedModule};
+use large_file::{ByteChunk, FileLimits, FileProbe, LineChunk, LineIndex};
+use local_history::{HistoryDiff, HistoryEntry, HistorySettings, LocalHistory};
+use project_config::ProjectLayout;
+use project_index::ProjectIndex;
+use refactor::RenamePreview;
+use sandbox::{FsError, Sandbox, TrustedProjects};
+use search::{FileMatch, SearchOptions};
+use secrets::ApiKeyStatus;
+use semantic_index::{SemanticHit, SemanticIndex, SemanticIndexStatus, SemanticSyncResult};
+use templates::{ScaffoldRequest, ScaffoldResult, TemplateInfo};
+use trash::{TrashEntry, TrashManager};
+use usage::{UsageBudgets, UsageMeter, UsageSummary, UsageTotals};
+use workspace_edit::{EditFailure, EditOperation, WorkspaceEditResult};
 
 // Cliente Discord RPC global
+// Directorio de datos de la app, resuelto por el backend en `init_managers`
+static APP_DIR: Mutex<Option<PathBuf>> = Mutex::new(None);
 static DISCORD_CLIENT: Mutex<Option<DiscordIpcClient>> = Mutex::new(None);
 static ACTIVITY_LOG: Mutex<Option<ActivityLog>> = Mutex::new(None);
 static BACKUP_MANAGER: Mutex<Option<BackupManager>> = Mutex::new(None);
 static DIAGNOSTICS: Mutex<Option<DiagnosticsManager>> = Mutex::new(None);
+static PROJECT_INDEX: Mutex<Option<ProjectIndex>> = Mutex::new(None);
+static SANDBOX: Mutex<Option<Sandbox>> = Mutex::new(None);
+static TRUSTED_PROJECTS: Mutex<Option<TrustedProjects>> = Mutex::new(None);
+static ACTIVE_SEARCHES: Mutex<Option<HashMap<String, Arc<AtomicBool>>>> = Mutex::new(None);
+static TRASH: Mutex<Option<TrashManager>> = Mutex::new(None);
+static FILE_LIMITS: Mutex<Option<FileLimits>> = Mutex::new(None);
+static LINE_INDEXES: Mutex<Option<HashMap<PathBuf, LineIndex>>> = Mutex::new(None);
+static LOCAL_HISTORY: Mutex<Option<LocalHistory>> = Mutex::new(None);
+static AI_CONFIG: Mutex<Option<AiConfig>> = Mutex::new(None);
+static COMPLETIONS: Mutex<Option<CompletionService>> = Mutex::new(None);
+static ACTIVE_AI_REQUESTS: Mutex<Option<HashMap<String, Arc<CancelToken>>>> = Mutex::new(None);
+static SEMANTIC_INDEX: Mutex<Option<SemanticIndex>> = Mutex::new(None);
+static SEMANTIC_SYNCING: AtomicBool = AtomicBool::new(false);
+static CHATS: Mutex<Option<ChatManager>> = Mutex::new(None);
+static USAGE: Mutex<Option<UsageMeter>> = Mutex::new(None);
 
 // Structs para Codeium API
 #[derive(Debug, Serialize, Deserialize)]
@@ -53,6 +123,8 @@ struct FileInfo {
     file_type: String,
     command_type: Option<String>,
     event_type: Option<String>,
+    event_name: Option<String>, // Evento de discord.js al que se suscribe (messageCreate, ready...)
+    once: Option<bool>,
     folder: Option<String>, // Nueva: para mostrar la subcarpeta
 }
 
@@ -65,6 +137,12 @@ struct ProjectStats {
     custom_events: u32,
     total_commands: u32,
     total_events: u32,
+    buttons: u32,
+    modals: u32,
+    select_menus: u32,
+    context_menus: u32,
+    total_components: u32,
+    events_by_name: BTreeMap<String, u32>,
 }
 
 // Learn more about Tauri commands at https://tauri.app/develop/calling-rust/
@@ -82,9 +160,17 @@ fn validate_project_path(path: String) -> Result<bool, String> {
         return Ok(false);
     }
     
-    // Verificar que tiene la estructura correcta (src/commands y src/events)
-    let commands_dir = project_path.join("src").join("commands");
-    let events_dir = project_path.join("src").join("events");
+    // Verificar que tiene la estructura correcta (por defecto src/commands y src/events,
+    // o lo que declare aeditor.json)
+    let layout = match ProjectLayout::load(project_path) {
+        Ok(layout) => layout,
+        Err(e) => {
+            println!("❌ {}", e);
+            return Ok(false);
+        }
+    };
+    let commands_dir = layout.commands_path(project_path);
+    let events_dir = layout.events_path(project_path);
     
     Ok(commands_dir.exists() && events_dir.exists())
 }
@@ -103,8 +189,10 @@ fn get_project_root() -> Result<String, String> {
 }
 
 #[tauri::command]
-fn scan_commands(project_root: String) -> Result<Vec<FileInfo>, String> {
-    let commands_dir = Path::new(&project_root).join("src").join("commands");
+fn scan_commands() -> Result<Vec<FileInfo>, String> {
+    let project_root = current_project_root()?;
+    let layout = ProjectLayout::load(Path::new(&project_root))?;
+    let commands_dir = layout.commands_path(Path::new(&project_root));
     let mut commands = Vec::new();
     
     println!("🔍 Escaneando comandos en: {}", commands_dir.display());
@@ -123,8 +211,10 @@ fn scan_commands(project_root: String) -> Result<Vec<FileInfo>, String> {
 }
 
 #[tauri::command]
-fn scan_events(project_root: String) -> Result<Vec<FileInfo>, String> {
-    let events_dir = Path::new(&project_root).join("src").join("events");
+fn scan_events() -> Result<Vec<FileInfo>, String> {
+    let project_root = current_project_root()?;
+    let layout = ProjectLayout::load(Path::new(&project_root))?;
+    let events_dir = layout.events_path(Path::new(&project_root));
     let mut events = Vec::new();
     
     println!("🔍 Escaneando eventos en: {}", events_dir.display());
@@ -143,9 +233,61 @@ fn scan_events(project_root: String) -> Result<Vec<FileInfo>, String> {
 }
 
 #[tauri::command]
-fn get_project_stats(project_root: String) -> Result<ProjectStats, String> {
-    let commands = scan_commands(project_root.clone())?;
-    let events = scan_events(project_root)?;
+fn scan_event_bindings() -> Result<EventScan, String> {
+    let project_root = current_project_root()?;
+    let layout = ProjectLayout::load(Path::new(&project_root))?;
+    let events_dir = layout.events_path(Path::new(&project_root));
+    
+    if !events_dir.exists() {
+        return Err(format!("El directorio de eventos no existe: {}", events_dir.display()));
+    }
+    
+    let mut bindings = Vec::new();
+    for event in scan_events()? {
+        if let Ok(content) = fs::read_to_string(&event.path) {
+            bindings.extend(events::extract_bindings(&content, &event.relative_path));
+        }
+    }
+    
+    let scan = events::build_scan(bindings);
+    
+    for (name, list) in &scan.duplicates {
+        println!("⚠️ Evento '{}' registrado {} veces", name, list.len());
+    }
+    for binding in &scan.unknown {
+        println!("⚠️ Evento desconocido '{}' en {}:{}", binding.raw, binding.file, binding.line);
+    }
+    
+    Ok(scan)
+}
+
+#[tauri::command]
+fn scan_components() -> Result<ComponentScan, String> {
+    let project_root = current_project_root()?;
+    let layout = ProjectLayout::load(Path::new(&project_root))?;
+    let src_dir = layout.src_path(Path::new(&project_root));
+    let components_dir = layout.components_path(Path::new(&project_root));
+    
+    println!("🔍 Escaneando componentes en: {}", components_dir.display());
+    
+    let scan = components::scan_components(&components_dir, &src_dir)?;
+    
+    println!(
+        "✅ Encontrados {} handlers ({} customIds sin handler, {} handlers sin uso)",
+        scan.handlers.len(),
+        scan.unhandled_ids.len(),
+        scan.unused_handlers.len()
+    );
+    
+    Ok(scan)
+}
+
+#[tauri::command]
+fn get_project_stats() -> Result<ProjectStats, String> {
+    let commands = scan_commands()?;
+    let events = scan_events()?;
+    let event_scan = scan_event_bindings()?;
+    let components = scan_components()?;
     
     let mut stats = ProjectStats {
         message_commands: 0,
@@ -154,6 +296,12 @@ fn get_project_stats(project_root: String) -> Result<ProjectStats, String> {
         custom_events: 0,
         total_commands: commands.len() as u32,
         total_events: events.len() as u32,
+        buttons: 0,
+        modals: 0,
+        select_menus: 0,
+        context_menus: 0,
+        total_components: components.handlers.len() as u32,
+        events_by_name: event_scan.counts,
     };
     
     // Contar tipos de comandos leyendo archivos
@@ -178,22 +326,280 @@ fn get_project_stats(project_root: String) -> Result<ProjectStats, String> {
         }
     }
     
+    // Contar tipos de componentes
+    for component in &components.handlers {
+        match component.kind.as_str() {
+            "button" => stats.buttons += 1,
+            "modal" => stats.modals += 1,
+            "selectMenu" => stats.select_menus += 1,
+            "contextMenu" => stats.context_menus += 1,
+            _ => {}
+        }
+    }
+    
     Ok(stats)
 }
 
+// ============================================
+// PROJECT SANDBOX
+// ============================================
+
+// Ventana nativa de Aceptar/Cancelar: el webview no puede contestarla por el usuario
+fn confirm_native(app: &tauri::AppHandle, title: &str, message: String, accept: &str) -> bool {
+    app.dialog()
+        .message(message)
+        .title(title)
+        .kind(MessageDialogKind::Warning)
+        .buttons(MessageDialogButtons::OkCancelCustom(accept.to_string(), "Cancelar".to_string()))
+        .blocking_show()
+}
+
+fn trust_project(root: &Path) {
+    if let Some(trusted) = TRUSTED_PROJECTS.lock().unwrap().as_mut() {
+        if let Err(e) = trusted.trust(root) {
+            println!("⚠️ No se pudo guardar el proyecto de confianza: {}", e);
+        }
+    }
+}
+
+// Selector nativo de carpeta; elegirla cuenta como confirmación para `open_project`
+#[tauri::command]
+async fn pick_project_folder(app: tauri::AppHandle) -> Result<Option<String>, FsError> {
+    let picked = app.dialog()
+        .file()
+        .set_title("Selecciona el directorio raíz del proyecto Amayo")
+        .blocking_pick_folder();
+    let Some(path) = picked.and_then(|p| p.into_path().ok()) else {
+        return Ok(None);
+    };
+    
+    let sandbox = Sandbox::new(&path)?;
+    trust_project(sandbox.root());
+    Ok(Some(path.to_string_lossy().to_string()))
+}
+
+// Abre un proyecto: a partir de aquí los comandos de archivos solo operan dentro de él.
+// Una raíz que el usuario no eligió con el selector nativo se confirma con un diálogo nativo.
+#[tauri::command]
+async fn open_project(app: tauri::AppHandle, project_root: String) -> Result<String, FsError> {
+    let sandbox = Sandbox::new(Path::new(&project_root))?;
+    let root = sandbox.root().to_string_lossy().to_string();
+    
+    let trusted = TRUSTED_PROJECTS.lock().unwrap().as_ref().map(|t| t.contains(sandbox.root())).unwrap_or(false);
+    if !trusted {
+        let message = format!("¿Abrir el proyecto {}?\n\nEl editor podrá leer y modificar todos sus archivos.", root);
+        if !confirm_native(&app, "Abrir proyecto", message, "Abrir") {
+            return Err(FsError::NotAllowed(project_root));
+        }
+        trust_project(sandbox.root());
+    }
+    *SANDBOX.lock().unwrap() = Some(sandbox);
+    
+    println!("📁 Proyecto abierto: {}", root);
+    Ok(root)
+}
+
+// Permite acceder a una ruta fuera del proyecto si el usuario lo confirma en un diálogo nativo.
+// Queda registrado en el activity log.
+#[tauri::command]
+async fn allow_external_path(app: tauri::AppHandle, path: String, reason: Option<String>) -> Result<String, FsError> {
+    if SANDBOX.lock().unwrap().is_none() {
+        return Err(FsError::NoProjectOpen);
+    }
+    let target = sandbox::external_target(Path::new(&path))?;
+    
+    let mut message = format!("¿Permitir que el editor acceda a {} fuera del proyecto?", target.display());
+    if let Some(reason) = &reason {
+        message.push_str(&format!("\n\nMotivo: {}", reason));
+    }
+    if !confirm_native(&app, "Acceso fuera del proyecto", message, "Permitir") {
+        return Err(FsError::NotAllowed(path));
+    }
+    
+    let mut sandbox_lock = SANDBOX.lock().unwrap();
+    let sandbox = sandbox_lock.as_mut().ok_or(FsError::NoProjectOpen)?;
+    let allowed = sandbox.allow_external(&target)?;
+    drop(sandbox_lock);
+    
+    println!("⚠️ Acceso externo permitido: {}", allowed.display());
+    log_activity(
+        "security",
+        "Acceso externo permitido",
+        &allowed.to_string_lossy(),
+        reason,
+    );
+    
+    Ok(allowed.to_string_lossy().to_string())
+}
+
+// Resuelve una ruta del frontend contra el proyecto abierto
+fn sandboxed(path: &str) -> Result<PathBuf, FsError> {
+    let sandbox_lock = SANDBOX.lock().unwrap();
+    let sandbox = sandbox_lock.as_ref().ok_or(FsError::NoProjectOpen)?;
+    let resolved = sandbox.resolve(path)?;
+    
+    if sandbox.is_external(&resolved) {
+        println!("⚠️ Acceso fuera del proyecto: {}", resolved.display());
+    }
+    
+    Ok(resolved)
+}
+
+// Raíz del proyecto abierto: los comandos de proyecto no aceptan otra desde el frontend
+fn current_project_root() -> Result<PathBuf, FsError> {
+    SANDBOX.lock().unwrap().as_ref()
+        .map(|s| s.root().to_path_buf())
+        .ok_or(FsError::NoProjectOpen)
+}
+
+#[tauri::command]
+fn read_file_content(file_path: String) -> Result<FileContent, FsError> {
+    let path = sandboxed(&file_path)?;
+    
+    let size = fs::metadata(&path).map_err(|e| FsError::from_io(e, &path))?.len();
+    if size > file_limits().read_only_size {
+        return Err(FsError::TooLarge(file_path));
+    }
+    
+    file_io::read_versioned(&path)
+}
+
+// Directorio de datos fijado en `init_managers`
+fn app_dir() -> Result<PathBuf, String> {
+    APP_DIR.lock().unwrap().clone().ok_or_else(|| "Managers no inicializados".to_string())
+}
+
+fn file_limits() -> FileLimits {
+    FILE_LIMITS.lock().unwrap().unwrap_or_default()
+}
+
+/// Tamaño, líneas y modo recomendado (editor, solo lectura o streaming) antes de abrir un archivo
+#[tauri::command]
+fn probe_file(file_path: String) -> Result<FileProbe, FsError> {
+    let path = sandboxed(&file_path)?;
+    large_file::probe(&path, &file_limits())
+}
+
+/// Líneas `[start_line, start_line + count)` (desde 0) para el visor de solo lectura
+#[tauri::command]
+fn read_file_lines(file_path: String, start_line: u64, count: u64) -> Result<LineChunk, FsError> {
+    let path = sandboxed(&file_path)?;
+    let key = large_file::cache_key(&path);
+    
+    let mut indexes_lock = LINE_INDEXES.lock().unwrap();
+    let indexes = indexes_lock.get_or_insert_with(HashMap::new);
+    
+    if indexes.get(&key).map(|index| index.is_stale(&path)).unwrap_or(true) {
+        // Pocos archivos grandes abiertos a la vez; no hace falta más que un tope
+        if indexes.len() >= 8 {
+            indexes.clear();
+        }
+        indexes.insert(key.clone(), LineIndex::build(&path)?);
+    }
+    
+    large_file::read_lines(&path, &indexes[&key], start_line, count)
+}
+
+/// Bloque de hasta `max_bytes` desde `offset` para el visor por streaming
+#[tauri::command]
+fn read_file_chunk(file_path: String, offset: u64, max_bytes: usize) -> Result<ByteChunk, FsError> {
+    let path = sandboxed(&file_path)?;
+    large_file::read_chunk(&path, offset, max_bytes)
+}
+
+#[tauri::command]
+fn get_file_limits() -> FileLimits {
+    file_limits()
+}
+
 #[tauri::command]
-fn read_file_content(file_path: String) -> Result<String, String> {
-    fs::read_to_string(&file_path).map_err(|e| e.to_string())
+fn set_file_limits(limits: FileLimits) -> Result<(), String> {
+    if limits.stream_size < limits.read_only_size {
+        return Err("El límite de streaming debe ser mayor o igual al de solo lectura".to_string());
+    }
+    
+    limits.save(&app_dir()?)?;
+    *FILE_LIMITS.lock().unwrap() = Some(limits);
+    Ok(())
 }
 
+/// Guarda de forma atómica. Si se pasa `expected_version` (la que devolvió
+/// `read_file_content`) y el archivo cambió en disco, falla con un error `conflict`.
+/// Sin `format` se conservan la codificación y los saltos de línea del archivo en disco.
 #[tauri::command]
-fn write_file_content(file_path: String, content: String) -> Result<(), String> {
-    // Crear directorios si no existen
-    if let Some(parent) = Path::new(&file_path).parent() {
-        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+fn write_file_content(
+    file_path: String,
+    content: String,
+    expected_version: Option<FileVersion>,
+    format: Option<TextFormat>,
+) -> Result<FileVersion, FsError> {
+    let path = sandboxed(&file_path)?;
+    
+    if let Some(expected) = &expected_version {
+        file_io::check_version(&path, expected)?;
     }
     
-    fs::write(&file_path, content).map_err(|e| e.to_string())
+    let format = format.unwrap_or_else(|| file_io::detect_format(&path));
+    let bytes = file_io::encode(&path, &content, format)?;
+    
+    // Si el archivo cambió fuera del editor, ese estado también queda en el historial
+    if let Ok(previous) = fs::read(&path) {
+        record_history(&path, &previous, "external");
+    }
+    let version = file_io::write_atomic(&path, &bytes)?;
+    record_history(&path, &bytes, "save");
+    
+    Ok(version)
+}
+
+// El historial nunca hace fallar un guardado
+fn record_history(path: &Path, bytes: &[u8], source: &str) {
+    if let Some(history) = LOCAL_HISTORY.lock().unwrap().as_ref() {
+        if let Err(e) = history.record(path, bytes, source) {
+            println!("⚠️ No se pudo guardar el historial de {}: {}", path.display(), e);
+        }
+    }
+}
+
+// Resolver de rutas para ediciones de workspace: como `sandboxed`, pero sin permitir tocar la raíz
+fn workspace_path(path: &str) -> Result<PathBuf, FsError> {
+    let resolved = sandboxed(path)?;
+    if SANDBOX.lock().unwrap().as_ref().map(|s| s.is_root(&resolved)).unwrap_or(false) {
+        return Err(FsError::PermissionDenied(path.to_string()));
+    }
+    Ok(resolved)
+}
+
+/// Valida un lote de operaciones sin aplicarlo (para previsualizar)
+#[tauri::command]
+fn validate_workspace_edit(operations: Vec<EditOperation>) -> Result<(), EditFailure> {
+    workspace_edit::validate(&operations, workspace_path)
+}
+
+/// Aplica un lote de creaciones, renombres, borrados y ediciones de texto como una
+/// transacción: si una operación falla se deshacen todas las anteriores.
+#[tauri::command]
+fn apply_workspace_edit(operations: Vec<EditOperation>) -> Result<WorkspaceEditResult, EditFailure> {
+    let result = workspace_edit::apply(&operations, workspace_path, |staged, original| {
+        let mut trash_lock = TRASH.lock().unwrap();
+        let discarded = match trash_lock.as_mut() {
+            Some(trash) => trash.move_to_trash_as(staged, original).map(|_| ()),
+            None if staged.is_dir() => fs::remove_dir_all(staged).map_err(|e| e.to_string()),
+            None => fs::remove_file(staged).map_err(|e| e.to_string()),
+        };
+        if let Err(e) = discarded {
+            eprintln!("❌ Error descartando {}: {}", staged.display(), e);
+        }
+    })?;
+    
+    log_activity(
+        "edit",
+        "Edición de workspace",
+        result.changed_paths.first().map(|p| p.as_str()).unwrap_or(""),
+        Some(format!("{} operaciones, {} rutas modificadas", result.operations, result.changed_paths.len())),
+    );
+    
+    Ok(result)
 }
 
 #[tauri::command]
@@ -279,8 +685,10 @@ fn disconnect_discord_rpc() -> Result<(), String> {
 }
 
 #[tauri::command]
-fn scan_all_files(project_root: String) -> Result<Vec<FileInfo>, String> {
-    let src_path = Path::new(&project_root).join("src");
+fn scan_all_files() -> Result<Vec<FileInfo>, String> {
+    let project_root = current_project_root()?;
+    let layout = ProjectLayout::load(Path::new(&project_root))?;
+    let src_path = layout.src_path(Path::new(&project_root));
     let mut results = Vec::new();
     
     if src_path.exists() {
@@ -325,6 +733,8 @@ fn scan_all_recursive(
                         file_type: "file".to_string(),
                         command_type: None,
                         event_type: None,
+                        event_name: None,
+                        once: None,
                         folder,
                     });
                 }
@@ -347,7 +757,11 @@ fn scan_directory_recursive(
         let entry = entry.map_err(|e| e.to_string())?;
         let path = entry.path();
         
-        if path.is_file() && path.extension().and_then(|s| s.to_str()) == Some("ts") {
+        let is_check_file = path.file_name()
+            .and_then(|n| n.to_str())
+            .map(command_gen::is_type_check_file)
+            .unwrap_or(false);
+        if path.is_file() && path.extension().and_then(|s| s.to_str()) == Some("ts") && !is_check_file {
             println!("  📄 Archivo encontrado: {}", path.file_name().unwrap().to_string_lossy());
             
             let name = path.file_stem()
@@ -396,6 +810,15 @@ fn scan_directory_recursive(
                 _ => (file_type.to_string(), None, None),
             };
             
+            // Detectar a qué evento de discord.js se suscribe el handler
+            let binding = if ft == "event" {
+                fs::read_to_string(&path)
+                    .ok()
+                    .and_then(|content| events::extract_bindings(&content, &relative_path).into_iter().next())
+            } else {
+                None
+            };
+            
             results.push(FileInfo {
                 name,
                 path: path.to_string_lossy().to_string(),
@@ -403,6 +826,8 @@ fn scan_directory_recursive(
                 file_type: ft,
                 command_type: cmd_type,
                 event_type: evt_type,
+                event_name: binding.as_ref().map(|b| b.event_name.clone()),
+                once: binding.as_ref().map(|b| b.once),
                 folder,
             });
         } else if path.is_dir() {
@@ -466,6 +891,8 @@ fn scan_directory(
                 file_type: ft,
                 command_type: cmd_type,
                 event_type: evt_type,
+                event_name: None,
+                once: None,
                 folder,
             });
         } else if path.is_dir() {
@@ -478,113 +905,137 @@ fn scan_directory(
 
 // Comandos para gestión de archivos y carpetas
 #[tauri::command]
-fn create_file(file_path: String, content: Option<String>) -> Result<(), String> {
-    let path = Path::new(&file_path);
+fn create_file(file_path: String, content: Option<String>) -> Result<(), FsError> {
+    let path = sandboxed(&file_path)?;
     
     // Crear directorios padre si no existen
     if let Some(parent) = path.parent() {
-        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        fs::create_dir_all(parent).map_err(|e| FsError::from_io(e, parent))?;
     }
     
     // Crear archivo con contenido o vacío
     let default_content = content.unwrap_or_default();
-    fs::write(path, default_content).map_err(|e| e.to_string())?;
+    fs::write(&path, default_content).map_err(|e| FsError::from_io(e, &path))?;
     
     Ok(())
 }
 
 #[tauri::command]
-fn create_folder(folder_path: String) -> Result<(), String> {
-    fs::create_dir_all(&folder_path).map_err(|e| e.to_string())?;
+fn create_folder(folder_path: String) -> Result<(), FsError> {
+    let path = sandboxed(&folder_path)?;
+    fs::create_dir_all(&path).map_err(|e| FsError::from_io(e, &path))?;
     Ok(())
 }
 
 #[tauri::command]
-fn delete_file(file_path: String) -> Result<(), String> {
-    use std::path::Path;
-    
-    let path = Path::new(&file_path);
+fn delete_file(file_path: String) -> Result<(), FsError> {
+    let path = sandboxed(&file_path)?;
     
     // Verificar si el archivo existe
-    if !path.exists() {
-        return Err(format!("El archivo no existe: {}", file_path));
+    if fs::symlink_metadata(&path).is_err() {
+        return Err(FsError::NotFound(file_path));
     }
     
     // Verificar si es un archivo (no directorio)
-    if !path.is_file() {
-        return Err(format!("La ruta no es un archivo: {}", file_path));
+    if path.is_dir() {
+        return Err(FsError::NotAFile(file_path));
     }
     
-    // Intentar eliminar el archivo
-    match fs::remove_file(&file_path) {
-        Ok(_) => Ok(()),
-        Err(e) => {
-            // Proporcionar más detalles sobre el error
-            let error_msg = match e.kind() {
-                std::io::ErrorKind::PermissionDenied => {
-                    format!("Permiso denegado para eliminar: {}. Ejecuta el editor como administrador.", file_path)
-                },
-                std::io::ErrorKind::NotFound => {
-                    format!("Archivo no encontrado: {}", file_path)
-                },
-                _ => {
-                    format!("Error al eliminar archivo: {} - {}", file_path, e)
-                }
-            };
-            Err(error_msg)
-        }
-    }
+    move_to_trash(&path)
 }
 
 #[tauri::command]
-fn delete_folder(folder_path: String) -> Result<(), String> {
-    use std::path::Path;
-    
-    let path = Path::new(&folder_path);
+fn delete_folder(folder_path: String) -> Result<(), FsError> {
+    let path = sandboxed(&folder_path)?;
     
     // Verificar si el directorio existe
     if !path.exists() {
-        return Err(format!("El directorio no existe: {}", folder_path));
+        return Err(FsError::NotFound(folder_path));
     }
     
     // Verificar si es un directorio
     if !path.is_dir() {
-        return Err(format!("La ruta no es un directorio: {}", folder_path));
+        return Err(FsError::NotADirectory(folder_path));
     }
     
-    // Intentar eliminar el directorio
-    match fs::remove_dir_all(&folder_path) {
-        Ok(_) => Ok(()),
-        Err(e) => {
-            let error_msg = match e.kind() {
-                std::io::ErrorKind::PermissionDenied => {
-                    format!("Permiso denegado para eliminar directorio: {}. Ejecuta el editor como administrador.", folder_path)
-                },
-                std::io::ErrorKind::NotFound => {
-                    format!("Directorio no encontrado: {}", folder_path)
-                },
-                _ => {
-                    format!("Error al eliminar directorio: {} - {}", folder_path, e)
-                }
-            };
-            Err(error_msg)
-        }
+    // Nunca borrar la raíz del proyecto
+    if SANDBOX.lock().unwrap().as_ref().map(|s| s.is_root(&path)).unwrap_or(false) {
+        return Err(FsError::PermissionDenied(folder_path));
     }
+    
+    move_to_trash(&path)
+}
+
+// Mueve a la papelera; sin papelera no se borra nada (el borrado tiene que poder deshacerse)
+fn move_to_trash(path: &Path) -> Result<(), FsError> {
+    let mut trash_lock = TRASH.lock().unwrap();
+    let trash = trash_lock.as_mut()
+        .ok_or_else(|| FsError::Io(path.display().to_string(), "papelera no inicializada".to_string()))?;
+    
+    trash.move_to_trash(path)
+        .map(|_| ())
+        .map_err(|e| FsError::Io(path.display().to_string(), e))
 }
 
 #[tauri::command]
-fn rename_file(old_path: String, new_path: String) -> Result<(), String> {
-    fs::rename(&old_path, &new_path).map_err(|e| e.to_string())?;
+fn rename_file(old_path: String, new_path: String) -> Result<(), FsError> {
+    // Como en `delete_folder`: la raíz del proyecto no se renombra
+    let from = workspace_path(&old_path)?;
+    let to = workspace_path(&new_path)?;
+    fs::rename(&from, &to).map_err(|e| FsError::from_io(e, &from))?;
     Ok(())
 }
 
+// Copia un archivo; sin destino se duplica junto al original ("ping copy.ts").
+// Con `rename_command` se cambia el `name` del comando copiado para que no choque al registrarse.
+#[tauri::command]
+fn copy_file(
+    source_path: String,
+    destination_path: Option<String>,
+    rename_command: Option<bool>,
+) -> Result<CopyResult, FsError> {
+    let from = sandboxed(&source_path)?;
+    let to = sandboxed(destination_path.as_deref().unwrap_or(&source_path))?;
+    
+    let result = duplicate::copy_file(&from, &to, rename_command.unwrap_or(false))?;
+    log_activity("create", "Archivo copiado", &result.path, Some(format!("Desde: {}", source_path)));
+    Ok(result)
+}
+
+#[tauri::command]
+fn copy_folder(
+    source_path: String,
+    destination_path: Option<String>,
+    rename_commands: Option<bool>,
+) -> Result<CopyResult, FsError> {
+    let from = sandboxed(&source_path)?;
+    let to = workspace_path(destination_path.as_deref().unwrap_or(&source_path))?;
+    let root = SANDBOX.lock().unwrap().as_ref()
+        .map(|s| s.root().to_path_buf())
+        .ok_or(FsError::NoProjectOpen)?;
+    
+    let result = duplicate::copy_folder(&root, &from, &to, rename_commands.unwrap_or(false))?;
+    log_activity(
+        "create",
+        "Carpeta copiada",
+        &result.path,
+        Some(format!("Desde: {} ({} archivos)", source_path, result.copied.len())),
+    );
+    Ok(result)
+}
+
 // Comandos para Prisma
 #[tauri::command]
-fn prisma_db_push(project_root: String) -> Result<String, String> {
+fn prisma_db_push() -> Result<String, String> {
     use std::process::Command;
     
+    let project_root = current_project_root()?;
+    let layout = ProjectLayout::load(Path::new(&project_root))?;
+    let schema = layout.prisma_schema_path(Path::new(&project_root));
+    
     let output = Command::new("npx")
-        .args(&["prisma", "db", "push"])
+        .args(&["prisma", "db", "push", "--schema"])
+        .arg(&schema)
         .current_dir(&project_root)
         .output()
         .map_err(|e| format!("Error ejecutando prisma db push: {}", e))?;
@@ -597,11 +1048,16 @@ fn prisma_db_push(project_root: String) -> Result<String, String> {
 }
 
 #[tauri::command]
-fn prisma_db_pull(project_root: String) -> Result<String, String> {
+fn prisma_db_pull() -> Result<String, String> {
     use std::process::Command;
     
+    let project_root = current_project_root()?;
+    let layout = ProjectLayout::load(Path::new(&project_root))?;
+    let schema = layout.prisma_schema_path(Path::new(&project_root));
+    
     let output = Command::new("npx")
-        .args(&["prisma", "db", "pull"])
+        .args(&["prisma", "db", "pull", "--schema"])
+        .arg(&schema)
         .current_dir(&project_root)
         .output()
         .map_err(|e| format!("Error ejecutando prisma db pull: {}", e))?;
@@ -614,9 +1070,16 @@ fn prisma_db_pull(project_root: String) -> Result<String, String> {
 }
 
 // Comandos para .env
+// El `.env` que indica aeditor.json, resuelto dentro del proyecto abierto
+fn env_file_path() -> Result<PathBuf, String> {
+    let root = current_project_root()?;
+    let layout = ProjectLayout::load(&root)?;
+    Ok(sandboxed(&layout.env_path(&root).to_string_lossy())?)
+}
+
 #[tauri::command]
-fn read_env_file(project_root: String) -> Result<String, String> {
-    let env_path = Path::new(&project_root).join(".env");
+fn read_env_file() -> Result<String, String> {
+    let env_path = env_file_path()?;
     if env_path.exists() {
         fs::read_to_string(&env_path).map_err(|e| e.to_string())
     } else {
@@ -625,27 +1088,29 @@ fn read_env_file(project_root: String) -> Result<String, String> {
 }
 
 #[tauri::command]
-fn write_env_file(project_root: String, content: String) -> Result<(), String> {
-    let env_path = Path::new(&project_root).join(".env");
+fn write_env_file(content: String) -> Result<(), String> {
+    let env_path = env_file_path()?;
     fs::write(&env_path, content).map_err(|e| e.to_string())
 }
 
 #[tauri::command]
-fn scan_env_variables(project_root: String) -> Result<Vec<String>, String> {
+fn scan_env_variables() -> Result<Vec<String>, String> {
+    let project_root = current_project_root()?;
     use std::collections::HashSet;
-    let src_path = Path::new(&project_root).join("src");
-    let prisma_path = Path::new(&project_root).join("prisma");
+    let layout = ProjectLayout::load(Path::new(&project_root))?;
+    let src_path = layout.src_path(Path::new(&project_root));
+    let prisma_path = layout.prisma_path(Path::new(&project_root));
     let mut env_vars = HashSet::new();
     
     // Regex para encontrar process.env.VARIABLE
     let re = regex::Regex::new(r"process\.env\.(\w+)").unwrap();
     
-    // Escanear src/
+    // Escanear código fuente
     if src_path.exists() {
         scan_for_env_vars(&src_path, &re, &mut env_vars)?;
     }
     
-    // Escanear prisma/
+    // Escanear carpeta de Prisma
     if prisma_path.exists() {
         scan_for_env_vars(&prisma_path, &re, &mut env_vars)?;
     }
@@ -663,20 +1128,22 @@ struct VarLocation {
 }
 
 #[tauri::command]
-fn scan_env_variables_with_locations(project_root: String) -> Result<Vec<VarLocation>, String> {
-    let src_path = Path::new(&project_root).join("src");
-    let prisma_path = Path::new(&project_root).join("prisma");
+fn scan_env_variables_with_locations() -> Result<Vec<VarLocation>, String> {
+    let project_root = current_project_root()?;
+    let layout = ProjectLayout::load(Path::new(&project_root))?;
+    let src_path = layout.src_path(Path::new(&project_root));
+    let prisma_path = layout.prisma_path(Path::new(&project_root));
     let mut locations = Vec::new();
     
     // Regex para encontrar process.env.VARIABLE
     let re = regex::Regex::new(r"process\.env\.(\w+)").unwrap();
     
-    // Escanear src/
+    // Escanear código fuente
     if src_path.exists() {
         scan_for_env_vars_with_locations(&src_path, &re, &mut locations, &project_root)?;
     }
     
-    // Escanear prisma/
+    // Escanear carpeta de Prisma
     if prisma_path.exists() {
         scan_for_env_vars_with_locations(&prisma_path, &re, &mut locations, &project_root)?;
     }
@@ -688,7 +1155,7 @@ fn scan_for_env_vars_with_locations(
     dir: &Path,
     re: &regex::Regex,
     locations: &mut Vec<VarLocation>,
-    project_root: &str,
+    project_root: &Path,
 ) -> Result<(), String> {
     for entry in fs::read_dir(dir).map_err(|e| e.to_string())? {
         let entry = entry.map_err(|e| e.to_string())?;
@@ -759,9 +1226,230 @@ fn scan_for_env_vars(
     Ok(())
 }
 
+// ============================================
+// PROJECT INDEX / IMPORT GRAPH
+// ============================================
+
+// Sincroniza el índice del proyecto (solo reparsea lo que cambió) y ejecuta `f` sobre él
+fn with_project_index<T>(project_root: &str, f: impl FnOnce(&ProjectIndex) -> T) -> Result<T, String> {
+    let mut index_lock = PROJECT_INDEX.lock().unwrap();
+    
+    let root = Path::new(project_root);
+    if index_lock.as_ref().map(|index| index.root() != root).unwrap_or(true) {
+        *index_lock = Some(ProjectIndex::new(root)?);
+    }
+    
+    let index = index_lock.as_mut().unwrap();
+    let updated = index.refresh()?;
+    if updated > 0 {
+        println!("🗂️ Índice actualizado: {} archivos", updated);
+    }
+    
+    Ok(f(index))
+}
+
+// Acepta tanto rutas absolutas como relativas a la raíz del proyecto
+fn index_module_path(index: &ProjectIndex, module: &str) -> Result<String, String> {
+    let relative = index.relative(Path::new(module));
+    if index.files().contains_key(&relative) {
+        Ok(relative)
+    } else {
+        Err(format!("El módulo no está en el índice del proyecto: {}", module))
+    }
+}
+
+#[tauri::command]
+fn get_import_graph() -> Result<ModuleGraph, String> {
+    with_project_index(&current_project_root()?.to_string_lossy(), import_graph::build_graph)
+}
+
+#[tauri::command]
+fn get_module_dependents(module: String) -> Result<Vec<RelatedModule>, String> {
+    with_project_index(&current_project_root()?.to_string_lossy(), |index| {
+        let module = index_module_path(index, &module)?;
+        Ok(import_graph::dependents(index, &module))
+    })?
+}
+
+#[tauri::command]
+fn get_module_dependencies(module: String) -> Result<Vec<RelatedModule>, String> {
+    with_project_index(&current_project_root()?.to_string_lossy(), |index| {
+        let module = index_module_path(index, &module)?;
+        Ok(import_graph::dependencies(index, &module))
+    })?
+}
+
+/// Calcula el renombre de un módulo (comando, evento...) sin aplicarlo: el movimiento,
+/// los imports a reescribir y, si se pasa `new_name`, el nuevo `name` del comando.
+#[tauri::command]
+fn preview_rename_module(
+    old_path: String,
+    new_path: String,
+    new_name: Option<String>,
+) -> Result<RenamePreview, String> {
+    sandboxed(&old_path)?;
+    sandboxed(&new_path)?;
+    
+    with_project_index(&current_project_root()?.to_string_lossy(), |index| {
+        let old = index_module_path(index, &old_path)?;
+        let new = index.relative(Path::new(&new_path));
+        refactor::plan_rename(index, &old, &new, new_name.as_deref())
+    })?
+}
+
+#[tauri::command]
+fn rename_module(
+    old_path: String,
+    new_path: String,
+    new_name: Option<String>,
+) -> Result<WorkspaceEditResult, String> {
+    let preview = preview_rename_module(old_path, new_path, new_name)?;
+    
+    apply_workspace_edit(preview.operations)
+        .map_err(|e| format!("Operación {}: {}", e.operation + 1, e.error))
+}
+
+// ============================================
+// TEMPLATE COMMANDS
+// ============================================
+
+#[tauri::command]
+fn list_templates() -> Result<Vec<TemplateInfo>, String> {
+    let project_root = current_project_root()?;
+    templates::list_templates(Path::new(&project_root))
+}
+
+/// Renderiza la plantilla y devuelve la ruta y el contenido sin escribir nada.
+#[tauri::command]
+fn preview_scaffold(request: ScaffoldRequest) -> Result<ScaffoldResult, String> {
+    let project_root = current_project_root()?;
+    templates::render_scaffold(Path::new(&project_root), &request)
+}
+
+#[tauri::command]
+fn scaffold(request: ScaffoldRequest) -> Result<ScaffoldResult, String> {
+    let project_root = current_project_root()?;
+    let result = templates::render_scaffold(Path::new(&project_root), &request)?;
+    let path = sandboxed(&result.path)?;
+    
+    // Nunca pisar un archivo existente
+    if path.exists() {
+        return Err(FsError::AlreadyExists(result.relative_path).into());
+    }
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| FsError::from_io(e, parent))?;
+    }
+    file_io::write_atomic(&path, result.content.as_bytes())?;
+    
+    log_activity(
+        "create",
+        "Archivo generado desde plantilla",
+        &result.path,
+        Some(format!("Plantilla: {}", result.template)),
+    );
+    
+    Ok(result)
+}
+
+// ============================================
+// SEARCH COMMANDS
+// ============================================
+
+#[tauri::command]
+fn fuzzy_search_files(query: String, limit: Option<usize>) -> Result<Vec<FileMatch>, String> {
+    search::fuzzy_find(&current_project_root()?, &query, limit.unwrap_or(50))
+}
+
+// Búsqueda de texto en todo el proyecto. Los resultados se emiten por archivo en el
+// evento "search-result" y al terminar se emite "search-done" con el resumen.
+#[tauri::command]
+fn search_project(
+    app: tauri::AppHandle,
+    search_id: String,
+    query: String,
+    options: Option<SearchOptions>,
+) -> Result<(), String> {
+    let project_root = current_project_root()?;
+    let options = options.unwrap_or_default();
+    let matcher = search::build_matcher(&query, &options)?;
+    
+    let cancelled = Arc::new(AtomicBool::new(false));
+    let previous = ACTIVE_SEARCHES.lock().unwrap()
+        .get_or_insert_with(HashMap::new)
+        .insert(search_id.clone(), cancelled.clone());
+    // Reutilizar el id reemplaza la búsqueda anterior: se cancela para que no mezcle resultados
+    if let Some(previous) = previous {
+        previous.store(true, Ordering::Relaxed);
+    }
+    
+    println!("🔎 Buscando '{}' en {}", query, project_root.display());
+    
+    std::thread::spawn(move || {
+        let result = search::search_content(
+            Path::new(&project_root),
+            &search_id,
+            &matcher,
+            &options,
+            &cancelled,
+            |file_result| {
+                if !cancelled.load(Ordering::Relaxed) {
+                    let _ = app.emit("search-result", file_result);
+                }
+            },
+        );
+        
+        // Solo se quita (y se avisa) si otra búsqueda con el mismo id no la reemplazó
+        let current = ACTIVE_SEARCHES.lock().unwrap().as_mut().is_some_and(|searches| {
+            let current = searches.get(&search_id).is_some_and(|flag| Arc::ptr_eq(flag, &cancelled));
+            if current {
+                searches.remove(&search_id);
+            }
+            current
+        });
+        if !current {
+            return;
+        }
+        
+        match result {
+            Ok(summary) => {
+                println!("✅ Búsqueda terminada: {} coincidencias en {} archivos", summary.total_matches, summary.files_matched);
+                let _ = app.emit("search-done", summary);
+            }
+            Err(e) => {
+                eprintln!("❌ Error en la búsqueda: {}", e);
+                let _ = app.emit("search-error", serde_json::json!({ "searchId": search_id, "error": e }));
+            }
+        }
+    });
+    
+    Ok(())
+}
+
+#[tauri::command]
+fn cancel_search(search_id: String) -> Result<(), String> {
+    if let Some(flag) = ACTIVE_SEARCHES.lock().unwrap().as_ref().and_then(|s| s.get(&search_id)) {
+        flag.store(true, Ordering::Relaxed);
+    }
+    Ok(())
+}
+
+// Comandos para la estructura del proyecto (aeditor.json)
+#[tauri::command]
+fn get_project_layout() -> Result<ProjectLayout, String> {
+    ProjectLayout::load(&current_project_root()?)
+}
+
+#[tauri::command]
+fn save_project_layout(layout: ProjectLayout) -> Result<(), String> {
+    let root = current_project_root()?;
+    // `save` rechaza entradas absolutas o que salgan de la raíz
+    layout.save(&root)
+}
+
 // Comandos para package.json scripts
 #[tauri::command]
-fn get_package_scripts(project_root: String) -> Result<Vec<(String, String)>, String> {
+fn get_package_scripts() -> Result<Vec<(String, String)>, String> {
+    let project_root = current_project_root()?;
     let package_json_path = Path::new(&project_root).join("package.json");
     
     if !package_json_path.exists() {
@@ -785,65 +1473,24 @@ fn get_package_scripts(project_root: String) -> Result<Vec<(String, String)>, St
 }
 
 // ============================================
-// GEMINI API INTEGRATION (Code Completion)
+// AI INTEGRATION (Gemini / OpenAI / local)
 // ============================================
 
-#[derive(serde::Serialize)]
-struct GeminiRequest {
-    contents: Vec<GeminiContent>,
-    #[serde(rename = "generationConfig")]
-    generation_config: GeminiGenerationConfig,
-}
-
-#[derive(serde::Serialize)]
-struct GeminiContent {
-    parts: Vec<GeminiPart>,
-}
-
-#[derive(serde::Serialize)]
-struct GeminiPart {
-    text: String,
-}
-
-#[derive(serde::Serialize)]
-struct GeminiGenerationConfig {
-    temperature: f32,
-    #[serde(rename = "maxOutputTokens")]
-    max_output_tokens: i32,
-    #[serde(rename = "candidateCount")]
-    candidate_count: i32,
-    #[serde(rename = "thinkingConfig", skip_serializing_if = "Option::is_none")]
-    thinking_config: Option<GeminiThinkingConfig>,
-}
-
-#[derive(serde::Serialize)]
-struct GeminiThinkingConfig {
-    #[serde(rename = "thinkingBudget")]
-    thinking_budget: i32,
-    #[serde(rename = "includeThoughts")]
-    include_thoughts: bool,
-}
-
-#[derive(serde::Deserialize)]
-struct GeminiResponse {
-    candidates: Option<Vec<GeminiCandidate>>,
-}
-
-#[derive(serde::Deserialize)]
-struct GeminiCandidate {
-    content: GeminiResponseContent,
-}
-
-#[derive(serde::Deserialize)]
-struct GeminiResponseContent {
-    parts: Vec<GeminiResponsePart>,
+// Proveedor configurado; `api_key` y `model` vacíos usan los de la configuración guardada
+fn ai_provider(api_key: &str, model: &str) -> Box<dyn AiProvider> {
+    AI_CONFIG.lock().unwrap()
+        .clone()
+        .unwrap_or_default()
+        .provider(api_key, model)
 }
 
-#[derive(serde::Deserialize)]
-struct GeminiResponsePart {
-    text: String,
+fn with_completions<T>(f: impl FnOnce(&mut CompletionService) -> T) -> T {
+    let mut lock = COMPLETIONS.lock().unwrap();
+    f(lock.get_or_insert_with(CompletionService::default))
 }
 
+// Autocompletado inline. Las peticiones se agrupan por documento (`document_id`, o la ruta):
+// una nueva cancela la anterior, las repetidas salen de la caché y hay un límite por minuto.
 #[tauri::command]
 async fn get_gemini_completion(
     text: String,
@@ -853,13 +1500,16 @@ async fn get_gemini_completion(
     api_key: String,
     model: String,
     agent_mode: Option<bool>,
+    document_id: Option<String>,
 ) -> Result<Vec<String>, String> {
-    use reqwest::Client;
-    
-    if api_key.is_empty() {
+    let provider = ai_provider(&api_key, &model);
+    if provider.requires_api_key() && !provider.has_api_key() {
         return Ok(vec![]);
     }
-    
+    if usage_budget_exceeded() {
+        return Ok(vec![]);
+    }
+    
     // Extraer contexto antes y después del cursor
     let before = text.chars().take(cursor_position).collect::<String>();
     let after = text.chars().skip(cursor_position).take(200).collect::<String>();
@@ -868,154 +1518,307 @@ async fn get_gemini_completion(
     let context_lines: Vec<&str> = before.lines().rev().take(15).collect();
     let context = context_lines.into_iter().rev().collect::<Vec<&str>>().join("\n");
     
+    let thinking = agent_mode.unwrap_or(false);
+    let document = document_id.unwrap_or_else(|| file_path.clone());
+    let cache_key = CompletionService::cache_key(
+        &format!("{}:{}:{}", provider.name(), provider.model(), thinking),
+        &language,
+        &context,
+        &after,
+        &project_context_key(&file_path, &text),
+    );
+    
+    // Un acierto de caché también deja obsoleta la petición anterior del documento
+    let cached = with_completions(|service| {
+        let cached = service.cached(&cache_key);
+        if cached.is_some() {
+            service.cancel(&document);
+        }
+        cached
+    });
+    if let Some(suggestions) = cached {
+        println!("⚡ Sugerencia desde caché");
+        return Ok(suggestions);
+    }
+    
+    let cancel = with_completions(|service| service.begin(&document));
+    
+    // Si el usuario sigue escribiendo, esta petición se cancela antes de llegar a la API
+    tokio::select! {
+        _ = tokio::time::sleep(completion::DEBOUNCE) => {}
+        _ = cancel.cancelled() => return Ok(vec![]),
+    }
+    
+    if !with_completions(|service| service.try_acquire()) {
+        println!("⏳ Límite de sugerencias por minuto alcanzado");
+        with_completions(|service| service.finish(&document, &cancel, &cache_key, None));
+        return Ok(vec![]);
+    }
+    
+    // Imports, firmas de src/core, modelos de Prisma y comandos parecidos, para que la
+    // sugerencia use las APIs reales del proyecto. Solo se arma si no hubo acierto de caché
+    let project = project_context(&file_path, &text, ai_context::DEFAULT_TOKEN_BUDGET).render();
+    let project = if project.is_empty() {
+        String::new()
+    } else {
+        format!(
+            "Project context (use these real types, services and models; do not invent APIs):\n```{}\n{}```\n\n",
+            language, project
+        )
+    };
+    
     // Crear prompt optimizado para autocompletado
     let prompt = format!(
         "You are a code completion AI. Complete the {} code at the cursor position.
 
-File: {}
+{}File: {}
 Code:
 ```{}
 {}[CURSOR]{}
 ```
 
 Complete ONLY what comes immediately after [CURSOR]. Output raw code only, no markdown, no explanations:",
-        language, file_path, language, context, after
+        language, project, file_path, language, context, after
     );
 
-    // Configurar thinking mode basado en agent_mode
-    let (thinking_config, max_tokens) = if agent_mode.unwrap_or(false) {
-        println!("🤖 Modo Agent activado con thinking dinámico");
-        (
-            Some(GeminiThinkingConfig {
-                thinking_budget: -1, // Dinámico: el modelo decide cuánto "pensar"
-                include_thoughts: true, // Incluir resumen de pensamientos
-            }),
-            512 // Más tokens cuando thinking está activado
-        )
-    } else {
-        println!("⚡ Modo rápido: thinking desactivado");
-        (
-            Some(GeminiThinkingConfig {
-                thinking_budget: 0, // Sin thinking para completions rápidos
-                include_thoughts: false,
-            }),
-            120 // Tokens normales para autocompletado rápido
-        )
-    };
-
-    let request_body = GeminiRequest {
-        contents: vec![GeminiContent {
-            parts: vec![GeminiPart { text: prompt }],
-        }],
-        generation_config: GeminiGenerationConfig {
-            temperature: 0.2,
-            max_output_tokens: max_tokens,
-            candidate_count: 1,
-            thinking_config,
-        },
+    // Con agent_mode el modelo "piensa" antes de sugerir y necesita más tokens
+    let request = GenerateRequest {
+        temperature: 0.2,
+        max_output_tokens: if thinking { 512 } else { 120 },
+        thinking,
+        timeout: std::time::Duration::from_secs(8),
+        ..GenerateRequest::prompt(prompt)
     };
-
-    let url = format!(
-        "https://generativelanguage.googleapis.com/v1beta/models/{}:generateContent?key={}",
-        model, api_key
-    );
-    
-    println!("🚀 Llamando a Gemini API...");
-    println!("   Modelo: {}", model);
-    println!("   URL: {}", url.split("?key=").next().unwrap_or(""));
     
-    let client = Client::new();
+    // Se cuenta al mandarla, aunque después se cancele o falle
+    record_request("completion", provider.as_ref());
+    let result = tokio::select! {
+        result = ai_provider::generate(provider.as_ref(), &request) => result,
+        _ = cancel.cancelled() => {
+            println!("🚫 Sugerencia cancelada: hay una petición más nueva");
+            return Ok(vec![]);
+        }
+    };
     
-    match client
-        .post(&url)
-        .header("Content-Type", "application/json")
-        .json(&request_body)
-        .timeout(std::time::Duration::from_secs(8))
-        .send()
-        .await
-    {
+    let suggestions = match result {
         Ok(response) => {
-            let status = response.status();
-            println!("📡 Status: {}", status);
+            record_tokens("completion", provider.as_ref(), response.usage.as_ref());
+            for thought in &response.thoughts {
+                println!("   💭 Pensamiento del modelo: {}", thought);
+            }
             
-            if response.status().is_success() {
-                // Primero obtener el texto para debuggear
-                let response_text = response.text().await.map_err(|e| e.to_string())?;
-                println!("📦 Respuesta completa: {}", response_text);
-                
-                // Intentar parsear manualmente
-                match serde_json::from_str::<serde_json::Value>(&response_text) {
-                    Ok(json) => {
-                        println!("✅ JSON parseado correctamente");
-                        let mut suggestions = Vec::new();
-                        
-                        // Navegar por la estructura real de Gemini
-                        if let Some(candidates) = json["candidates"].as_array() {
-                            println!("✅ Candidatos encontrados: {}", candidates.len());
-                            for (i, candidate) in candidates.iter().enumerate() {
-                                println!("   📋 Procesando candidato #{}", i + 1);
-                                
-                                // Ver el finishReason
-                                if let Some(finish_reason) = candidate["finishReason"].as_str() {
-                                    println!("   🏁 Finish reason: {}", finish_reason);
-                                }
-                                
-                                // Intentar extraer el texto de diferentes formas
-                                if let Some(content) = candidate.get("content") {
-                                    if let Some(parts) = content["parts"].as_array() {
-                                        println!("   ✅ Parts encontrados: {}", parts.len());
-                                        for part in parts {
-                                            // Detectar si es un "thought" (pensamiento del modelo)
-                                            let is_thought = part["thought"].as_bool().unwrap_or(false);
-                                            
-                                            if let Some(text) = part["text"].as_str() {
-                                                if is_thought {
-                                                    println!("   💭 Pensamiento del modelo: {}", text);
-                                                    // Los pensamientos NO se agregan como sugerencias
-                                                    continue;
-                                                }
-                                                
-                                                let cleaned = text.trim()
-                                                    .trim_start_matches("```")
-                                                    .trim_start_matches(&language)
-                                                    .trim_end_matches("```")
-                                                    .trim();
-                                                
-                                                if !cleaned.is_empty() {
-                                                    println!("   ✨ Sugerencia: {}", cleaned);
-                                                    suggestions.push(cleaned.to_string());
-                                                }
-                                            }
-                                        }
-                                    } else {
-                                        println!("   ⚠️ No hay 'parts' en content");
-                                    }
-                                } else {
-                                    println!("   ⚠️ No hay 'content' en candidate");
-                                }
-                            }
-                        }
-                        
-                        if suggestions.is_empty() {
-                            println!("⚠️ No se pudieron extraer sugerencias del JSON");
-                        }
-                        
-                        Ok(suggestions)
-                    }
-                    Err(e) => {
-                        eprintln!("❌ Error parsing JSON: {:?}", e);
-                        Ok(vec![])
-                    }
-                }
+            let cleaned = response.text.trim()
+                .trim_start_matches("```")
+                .trim_start_matches(&language)
+                .trim_end_matches("```")
+                .trim();
+            
+            if cleaned.is_empty() {
+                println!("⚠️ No se pudieron extraer sugerencias de la respuesta");
+                vec![]
             } else {
-                let error_text = response.text().await.unwrap_or_default();
-                eprintln!("❌ Gemini API error {}: {}", status, error_text);
-                Ok(vec![])
+                println!("   ✨ Sugerencia: {}", cleaned);
+                vec![cleaned.to_string()]
             }
         }
+        // El autocompletado falla en silencio: sin sugerencias
+        Err(_) => vec![],
+    };
+    
+    with_completions(|service| service.finish(&document, &cancel, &cache_key, Some(&suggestions)));
+    Ok(suggestions)
+}
+
+// Contexto de proyecto para `file_path`; vacío si no hay proyecto abierto o el archivo está fuera
+fn project_context(file_path: &str, text: &str, budget: usize) -> ProjectContext {
+    let Some(root) = SANDBOX.lock().unwrap().as_ref().map(|s| s.root().to_path_buf()) else {
+        return ProjectContext::default();
+    };
+    if Path::new(file_path).is_absolute() && !Path::new(file_path).starts_with(&root) {
+        return ProjectContext::default();
+    }
+    
+    with_project_index(&root.to_string_lossy(), |index| {
+        let file = index.relative(Path::new(file_path));
+        ai_context::build(index, &file, text, budget)
+    }).unwrap_or_default()
+}
+
+// Lo que decide el contexto de proyecto, sin armarlo (se calcula en cada tecla): el archivo,
+// su bloque de imports, la generación del índice y la fecha del schema de Prisma
+fn project_context_key(file_path: &str, text: &str) -> String {
+    let imports = ai_context::import_statements(text).join("\n");
+    let (generation, schema_modified) = PROJECT_INDEX.lock().unwrap().as_ref()
+        .map(|index| {
+            let modified = fs::metadata(index.layout().prisma_schema_path(index.root()))
+                .and_then(|m| m.modified())
+                .ok()
+                .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+                .map(|d| d.as_millis())
+                .unwrap_or(0);
+            (index.generation(), modified)
+        })
+        .unwrap_or_default();
+    format!("{}\0{}\0{}\0{}", file_path, imports, generation, schema_modified)
+}
+
+/// El contexto de proyecto que se enviaría con una sugerencia, para inspeccionarlo
+#[tauri::command]
+fn get_completion_context(file_path: String, text: String, budget: Option<usize>) -> ProjectContext {
+    project_context(&file_path, &text, budget.unwrap_or(ai_context::DEFAULT_TOKEN_BUDGET))
+}
+
+// Cancela la sugerencia pendiente de un documento (al cerrarlo o al aceptar otra)
+#[tauri::command]
+fn cancel_completion(document_id: String) -> Result<(), String> {
+    with_completions(|service| service.cancel(&document_id));
+    Ok(())
+}
+
+#[tauri::command]
+fn get_completion_stats() -> CompletionStats {
+    with_completions(|service| service.stats())
+}
+
+#[tauri::command]
+fn clear_completion_cache() -> Result<(), String> {
+    with_completions(|service| service.clear_cache());
+    Ok(())
+}
+
+// ============================================
+// ÍNDICE SEMÁNTICO / RAG
+// ============================================
+
+// Fragmentos por llamada al endpoint de embeddings
+const EMBED_BATCH: usize = 32;
+// Fragmentos del proyecto que se agregan a un prompt con `use_project_context`
+const RAG_RESULTS: usize = 6;
+
+// Abre el índice del proyecto actual y ejecuta `f` sobre él
+fn with_semantic_index<T>(f: impl FnOnce(&mut SemanticIndex) -> Result<T, String>) -> Result<T, String> {
+    let root = SANDBOX.lock().unwrap().as_ref()
+        .map(|s| s.root().to_path_buf())
+        .ok_or("No hay ningún proyecto abierto")?;
+    
+    let mut index_lock = SEMANTIC_INDEX.lock().unwrap();
+    let index = index_lock.as_mut().ok_or("Índice semántico no inicializado")?;
+    index.open(&root);
+    f(index)
+}
+
+fn embedding_provider() -> Result<Box<dyn AiProvider>, String> {
+    let provider = ai_provider("", "");
+    if provider.requires_api_key() && !provider.has_api_key() {
+        return Err("Configura la API key de IA para usar la búsqueda semántica".to_string());
+    }
+    Ok(provider)
+}
+
+// Pone al día el índice: solo se piden embeddings de los fragmentos nuevos o modificados.
+// Con `app` emite "semantic-index-progress" { done, total } entre lote y lote.
+async fn sync_semantic_index(app: Option<&tauri::AppHandle>) -> Result<SemanticSyncResult, String> {
+    if SEMANTIC_SYNCING.swap(true, Ordering::SeqCst) {
+        return Err("El índice semántico ya se está actualizando".to_string());
+    }
+    let result = sync_semantic_index_inner(app).await;
+    SEMANTIC_SYNCING.store(false, Ordering::SeqCst);
+    result
+}
+
+async fn sync_semantic_index_inner(app: Option<&tauri::AppHandle>) -> Result<SemanticSyncResult, String> {
+    let provider = embedding_provider()?;
+    let embedder = format!("{}:{}", provider.name(), provider.embedding_model());
+    
+    let root = SANDBOX.lock().unwrap().as_ref()
+        .map(|s| s.root().to_string_lossy().to_string())
+        .ok_or("No hay ningún proyecto abierto")?;
+    let mut plan = with_project_index(&root, |index| {
+        with_semantic_index(|semantic| Ok(semantic.plan(index, &embedder)))
+    })??;
+    
+    if plan.is_empty() {
+        let total_chunks = with_semantic_index(|semantic| Ok(semantic.status().chunks))?;
+        return Ok(SemanticSyncResult { total_chunks, ..Default::default() });
+    }
+    
+    // Los embeddings se piden sin bloquear el índice: mientras tanto se puede seguir buscando
+    let pending = plan.pending();
+    let total = pending.len();
+    println!("🧭 Índice semántico: {} fragmentos por calcular", total);
+    for (batch_number, batch) in pending.chunks(EMBED_BATCH).enumerate() {
+        let texts: Vec<String> = batch.iter().map(|(_, text)| text.clone()).collect();
+        let vectors = embed(provider.as_ref(), &texts).await?;
+        for ((position, _), vector) in batch.iter().zip(vectors) {
+            plan.set_vector(*position, vector);
+        }
+        
+        if let Some(app) = app {
+            let done = ((batch_number + 1) * EMBED_BATCH).min(total);
+            let _ = app.emit("semantic-index-progress", serde_json::json!({ "done": done, "total": total }));
+        }
+    }
+    
+    with_semantic_index(|semantic| semantic.apply(plan))
+}
+
+/// Actualiza el índice semántico del proyecto abierto (incremental)
+#[tauri::command]
+async fn update_semantic_index(app: tauri::AppHandle) -> Result<SemanticSyncResult, String> {
+    let result = sync_semantic_index(Some(&app)).await?;
+    if result.updated_files > 0 || result.removed_files > 0 {
+        println!(
+            "🧭 Índice semántico actualizado: {} archivos, {} embeddings nuevos",
+            result.updated_files, result.embedded_chunks
+        );
+    }
+    Ok(result)
+}
+
+/// Busca en el proyecto por significado ("¿dónde se suman los puntos de alianza?").
+/// Antes actualiza el índice con los archivos que cambiaron.
+#[tauri::command]
+async fn semantic_search(query: String, limit: Option<usize>) -> Result<Vec<SemanticHit>, String> {
+    // Si ya se está actualizando (o falla), se busca en lo que haya indexado
+    if let Err(e) = sync_semantic_index(None).await {
+        println!("⚠️ Índice semántico sin actualizar: {}", e);
+    }
+    
+    let provider = embedding_provider()?;
+    let query_vector = embed(provider.as_ref(), &[query]).await?
+        .pop()
+        .unwrap_or_default();
+    with_semantic_index(|semantic| Ok(semantic.search(query_vector, limit.unwrap_or(10))))
+}
+
+#[tauri::command]
+fn get_semantic_index_status() -> Result<SemanticIndexStatus, String> {
+    with_semantic_index(|semantic| Ok(semantic.status()))
+}
+
+#[tauri::command]
+fn clear_semantic_index() -> Result<(), String> {
+    with_semantic_index(|semantic| semantic.clear())
+}
+
+// Antepone al prompt los fragmentos del proyecto más parecidos (RAG).
+// Sin índice o sin embeddings disponibles, el prompt se usa tal cual.
+async fn with_project_context(prompt: String) -> String {
+    match semantic_search(prompt.clone(), Some(RAG_RESULTS)).await {
+        Ok(hits) if !hits.is_empty() => {
+            let context = hits.iter()
+                .map(|hit| format!("// {} (lines {}-{})\n{}", hit.file, hit.start_line, hit.end_line, hit.text))
+                .collect::<Vec<_>>()
+                .join("\n\n");
+            format!(
+                "Relevant code from the project (retrieved by similarity, may be partial):\n```\n{}\n```\n\n{}",
+                context, prompt
+            )
+        }
+        Ok(_) => prompt,
         Err(e) => {
-            eprintln!("❌ Error calling Gemini: {:?}", e);
-            Ok(vec![])
+            println!("⚠️ Prompt sin contexto del proyecto: {}", e);
+            prompt
         }
     }
 }
@@ -1027,158 +1830,613 @@ async fn ask_gemini(
     api_key: String,
     model: String,
     use_thinking: bool,
+    use_project_context: Option<bool>,
 ) -> Result<String, String> {
-    use reqwest::Client;
+    let provider = ai_provider(&api_key, &model);
+    let prompt = if use_project_context.unwrap_or(false) {
+        with_project_context(prompt).await
+    } else {
+        prompt
+    };
+    
+    let request = GenerateRequest {
+        // Más tokens para respuestas completas cuando hay thinking
+        max_output_tokens: if use_thinking { 2048 } else { 1024 },
+        thinking: use_thinking,
+        ..GenerateRequest::prompt(prompt)
+    };
     
-    if api_key.is_empty() {
-        return Err("No API key provided".to_string());
+    record_request("ask", provider.as_ref());
+    let response = ai_provider::generate(provider.as_ref(), &request).await?;
+    record_tokens("ask", provider.as_ref(), response.usage.as_ref());
+    if response.text.is_empty() {
+        return Err("No se encontró contenido en la respuesta".to_string());
     }
+    
+    println!("✅ Respuesta obtenida: {} caracteres", response.text.len());
+    Ok(response.text)
+}
 
-    // Configurar thinking mode
-    let (thinking_config, max_tokens) = if use_thinking {
-        println!("🤖 ask_gemini con thinking activado");
-        (
-            Some(GeminiThinkingConfig {
-                thinking_budget: -1,
-                include_thoughts: true,
-            }),
-            2048 // Más tokens para respuestas completas
-        )
+// Petición cancelable con `cancel_ai_request` mientras viva; al soltarse se quita del registro
+struct ActiveAiRequest {
+    id: String,
+    cancel: Arc<CancelToken>,
+}
+
+impl ActiveAiRequest {
+    fn register(id: &str) -> Self {
+        let cancel = Arc::new(CancelToken::default());
+        ACTIVE_AI_REQUESTS.lock().unwrap()
+            .get_or_insert_with(HashMap::new)
+            .insert(id.to_string(), cancel.clone());
+        ActiveAiRequest { id: id.to_string(), cancel }
+    }
+}
+
+impl Drop for ActiveAiRequest {
+    fn drop(&mut self) {
+        if let Some(requests) = ACTIVE_AI_REQUESTS.lock().unwrap().as_mut() {
+            requests.remove(&self.id);
+        }
+    }
+}
+
+// Variante de `ask_gemini` con streaming. El texto se emite por partes en "ai-stream-chunk",
+// los pensamientos del modelo en "ai-stream-thought" y al terminar "ai-stream-done" (o
+// "ai-stream-error"). Todos los eventos llevan el `requestId` para poder distinguirlos.
+#[tauri::command]
+async fn ask_gemini_stream(
+    app: tauri::AppHandle,
+    request_id: String,
+    prompt: String,
+    api_key: String,
+    model: String,
+    use_thinking: bool,
+    use_project_context: Option<bool>,
+) -> Result<(), String> {
+    let provider = ai_provider(&api_key, &model);
+    // Se registra antes del contexto: armarlo puede sincronizar el índice semántico
+    let active = ActiveAiRequest::register(&request_id);
+    let cancel = active.cancel.clone();
+    let prompt = if use_project_context.unwrap_or(false) {
+        with_project_context(prompt).await
     } else {
-        println!("⚡ ask_gemini modo rápido");
-        (
-            Some(GeminiThinkingConfig {
-                thinking_budget: 0,
-                include_thoughts: false,
-            }),
-            1024
-        )
+        prompt
     };
-
-    let request_body = GeminiRequest {
-        contents: vec![GeminiContent {
-            parts: vec![GeminiPart { text: prompt }],
-        }],
-        generation_config: GeminiGenerationConfig {
-            temperature: 0.3,
-            max_output_tokens: max_tokens,
-            candidate_count: 1,
-            thinking_config,
-        },
+    if cancel.is_cancelled() {
+        let _ = app.emit("ai-stream-done", serde_json::json!({
+            "requestId": request_id,
+            "text": "",
+            "cancelled": true,
+        }));
+        return Ok(());
+    }
+    let request = GenerateRequest {
+        max_output_tokens: if use_thinking { 2048 } else { 1024 },
+        thinking: use_thinking,
+        // Tiempo máximo sin recibir nada, no el total de la respuesta
+        timeout: std::time::Duration::from_secs(30),
+        ..GenerateRequest::prompt(prompt)
     };
-
-    let url = format!(
-        "https://generativelanguage.googleapis.com/v1beta/models/{}:generateContent?key={}",
-        model, api_key
-    );
     
-    println!("🚀 ask_gemini llamando a Gemini API...");
-    println!("   Modelo: {}", model);
-    
-    let client = Client::new();
+    record_request("ask", provider.as_ref());
+    let result = ai_provider::generate_stream(provider.as_ref(), &request, &cancel, |delta| {
+        if !delta.text.is_empty() {
+            let _ = app.emit("ai-stream-chunk", serde_json::json!({ "requestId": request_id, "text": delta.text }));
+        }
+        for thought in &delta.thoughts {
+            let _ = app.emit("ai-stream-thought", serde_json::json!({ "requestId": request_id, "text": thought }));
+        }
+    }).await;
+    drop(active);
     
-    match client
-        .post(&url)
-        .header("Content-Type", "application/json")
-        .json(&request_body)
-        .timeout(std::time::Duration::from_secs(15))
-        .send()
-        .await
-    {
+    match result {
         Ok(response) => {
-            let status = response.status();
-            println!("📡 Status: {}", status);
-            
-            if response.status().is_success() {
-                let response_text = response.text().await.map_err(|e| e.to_string())?;
-                
-                match serde_json::from_str::<serde_json::Value>(&response_text) {
-                    Ok(json) => {
-                        if let Some(candidates) = json["candidates"].as_array() {
-                            if let Some(candidate) = candidates.first() {
-                                if let Some(content) = candidate.get("content") {
-                                    if let Some(parts) = content["parts"].as_array() {
-                                        // Concatenar todas las partes de texto (ignorando thoughts)
-                                        let mut result = String::new();
-                                        for part in parts {
-                                            let is_thought = part["thought"].as_bool().unwrap_or(false);
-                                            if !is_thought {
-                                                if let Some(text) = part["text"].as_str() {
-                                                    result.push_str(text);
-                                                }
-                                            }
-                                        }
-                                        
-                                        if !result.is_empty() {
-                                            println!("✅ Respuesta obtenida: {} caracteres", result.len());
-                                            return Ok(result);
-                                        }
-                                    }
-                                }
-                            }
-                        }
-                        Err("No se encontró contenido en la respuesta".to_string())
-                    }
-                    Err(e) => {
-                        eprintln!("❌ Error parsing JSON: {:?}", e);
-                        Err(format!("Error parsing response: {}", e))
-                    }
-                }
-            } else {
-                let error_text = response.text().await.unwrap_or_default();
-                eprintln!("❌ Gemini API error {}: {}", status, error_text);
-                Err(format!("API error: {}", error_text))
-            }
+            record_tokens("ask", provider.as_ref(), response.usage.as_ref());
+            println!("✅ Streaming terminado: {} caracteres", response.text.len());
+            let _ = app.emit("ai-stream-done", serde_json::json!({
+                "requestId": request_id,
+                "text": response.text,
+                "cancelled": cancel.is_cancelled(),
+            }));
+            Ok(())
         }
         Err(e) => {
-            eprintln!("❌ Error calling Gemini: {:?}", e);
-            Err(format!("Network error: {}", e))
+            let _ = app.emit("ai-stream-error", serde_json::json!({ "requestId": request_id, "error": e }));
+            Err(e)
+        }
+    }
+}
+
+#[tauri::command]
+fn cancel_ai_request(request_id: String) -> Result<(), String> {
+    if let Some(cancel) = ACTIVE_AI_REQUESTS.lock().unwrap().as_ref().and_then(|r| r.get(&request_id)) {
+        cancel.cancel();
+    }
+    Ok(())
+}
+
+// ============================================
+// USO DE IA
+// ============================================
+
+fn with_usage<T>(f: impl FnOnce(&mut UsageMeter) -> Result<T, String>) -> Result<T, String> {
+    let mut lock = USAGE.lock().unwrap();
+    let usage = lock.as_mut().ok_or_else(|| "Medidor de uso no inicializado".to_string())?;
+    f(usage)
+}
+
+// Un error al guardar el consumo no debe romper la respuesta
+fn record_usage(feature: &str, provider: &dyn AiProvider, totals: &UsageTotals) {
+    let model = format!("{}:{}", provider.name(), provider.model());
+    if let Err(e) = with_usage(|usage| usage.record(&model, feature, totals)) {
+        eprintln!("❌ Error registrando uso de IA: {}", e);
+    }
+}
+
+// Las peticiones se cuentan al mandarlas (las canceladas o fallidas también se facturan)
+// y los tokens se suman cuando llega la respuesta
+fn record_request(feature: &str, provider: &dyn AiProvider) {
+    record_usage(feature, provider, &UsageTotals::request(None));
+}
+
+fn record_tokens(feature: &str, provider: &dyn AiProvider, usage: Option<&TokenUsage>) {
+    record_usage(feature, provider, &UsageTotals::tokens(usage));
+}
+
+// Embeddings del índice semántico y de las consultas. La API no informa el uso:
+// los tokens de entrada se estiman
+async fn embed(provider: &dyn AiProvider, texts: &[String]) -> Result<Vec<Vec<f32>>, String> {
+    let totals = UsageTotals {
+        requests: 1,
+        prompt_tokens: texts.iter().map(|t| ai_context::estimate_tokens(t) as u64).sum(),
+        ..Default::default()
+    };
+    record_usage("embedding", provider, &totals);
+    ai_provider::embed(provider, texts).await
+}
+
+// Con algún límite superado las sugerencias inline se desactivan hasta el día (o mes) siguiente
+fn usage_budget_exceeded() -> bool {
+    with_usage(|usage| Ok(!usage.exceeded().is_empty())).unwrap_or(false)
+}
+
+#[tauri::command]
+fn get_ai_usage() -> Result<UsageSummary, String> {
+    with_usage(|usage| Ok(usage.summary()))
+}
+
+#[tauri::command]
+fn set_ai_budgets(budgets: UsageBudgets) -> Result<UsageSummary, String> {
+    with_usage(|usage| {
+        usage.set_budgets(budgets)?;
+        Ok(usage.summary())
+    })
+}
+
+#[tauri::command]
+fn clear_ai_usage() -> Result<UsageSummary, String> {
+    with_usage(|usage| {
+        usage.clear()?;
+        Ok(usage.summary())
+    })
+}
+
+// ============================================
+// CONVERSACIONES
+// ============================================
+
+fn with_chats<T>(f: impl FnOnce(&ChatManager) -> Result<T, String>) -> Result<T, String> {
+    let lock = CHATS.lock().unwrap();
+    let chats = lock.as_ref().ok_or_else(|| "Chats no inicializados".to_string())?;
+    f(chats)
+}
+
+// Conversaciones con un envío en curso: un segundo envío partiría de un historial viejo
+static CHATS_SENDING: Mutex<Option<HashSet<String>>> = Mutex::new(None);
+
+struct ChatSendGuard(String);
+
+impl ChatSendGuard {
+    fn acquire(session_id: &str) -> Result<Self, String> {
+        let mut sending = CHATS_SENDING.lock().unwrap();
+        if !sending.get_or_insert_with(HashSet::new).insert(session_id.to_string()) {
+            return Err("La conversación ya está esperando una respuesta".to_string());
+        }
+        Ok(ChatSendGuard(session_id.to_string()))
+    }
+}
+
+impl Drop for ChatSendGuard {
+    fn drop(&mut self) {
+        if let Some(sending) = CHATS_SENDING.lock().unwrap().as_mut() {
+            sending.remove(&self.0);
         }
     }
 }
 
-// Guardar configuración de Gemini
 #[tauri::command]
-fn save_gemini_config(api_key: String, model: String, app_data_dir: String, agent_mode: Option<bool>, inline_suggestions_enabled: Option<bool>) -> Result<(), String> {
-    // Crear el directorio si no existe
-    let dir_path = Path::new(&app_data_dir);
-    if !dir_path.exists() {
-        fs::create_dir_all(dir_path).map_err(|e| format!("Error creando directorio: {}", e))?;
+fn create_chat_session(title: Option<String>) -> Result<ChatSession, String> {
+    with_chats(|chats| chats.create(title))
+}
+
+#[tauri::command]
+fn list_chat_sessions() -> Result<Vec<ChatSessionInfo>, String> {
+    with_chats(|chats| chats.list())
+}
+
+#[tauri::command]
+fn get_chat_session(session_id: String) -> Result<ChatSession, String> {
+    with_chats(|chats| chats.load(&session_id))
+}
+
+#[tauri::command]
+fn rename_chat_session(session_id: String, title: String) -> Result<ChatSessionInfo, String> {
+    with_chats(|chats| chats.rename(&session_id, &title))
+}
+
+#[tauri::command]
+fn delete_chat_session(session_id: String) -> Result<(), String> {
+    with_chats(|chats| chats.delete(&session_id))
+}
+
+// Markdown por defecto; el frontend decide dónde guardarlo
+#[tauri::command]
+fn export_chat_session(session_id: String, format: Option<String>) -> Result<String, String> {
+    with_chats(|chats| chats.export(&session_id, format.as_deref().unwrap_or("markdown")))
+}
+
+/// Envía un mensaje dentro de una conversación: el proveedor recibe los turnos anteriores
+/// (o su resumen, si ya no caben) y los adjuntos. Devuelve la conversación actualizada;
+/// si la petición falla no se guarda nada.
+#[tauri::command]
+async fn send_chat_message(
+    session_id: String,
+    text: String,
+    attachments: Option<Vec<ChatAttachment>>,
+    api_key: String,
+    model: String,
+    use_thinking: bool,
+    use_project_context: Option<bool>,
+) -> Result<ChatSession, String> {
+    let provider = ai_provider(&api_key, &model);
+    let _sending = ChatSendGuard::acquire(&session_id)?;
+    let mut session = with_chats(|chats| chats.load(&session_id))?;
+    
+    // Adjuntos sin contenido: se leen del proyecto (la selección, si trae rango)
+    let mut resolved = Vec::new();
+    for mut attachment in attachments.unwrap_or_default() {
+        if attachment.content.is_empty() {
+            let path = sandboxed(&attachment.path)?;
+            let file = file_io::read_versioned(&path)?;
+            if file.binary {
+                return Err(format!("No se puede adjuntar un archivo binario: {}", attachment.path));
+            }
+            attachment.content = chat::select_lines(&file.content, attachment.start_line, attachment.end_line);
+        }
+        let content = std::mem::take(&mut attachment.content);
+        attachment.set_content(&content);
+        resolved.push(attachment);
     }
+    let user_message = ChatMessage::new("user", text.clone(), resolved);
+    session.push(user_message.clone());
+    let mut new_summary = None;
     
-    let config_path = dir_path.join("gemini_config.json");
+    // Si la ventana se llena, lo antiguo se reemplaza por un resumen
+    if let Some(until) = session.pending_summary(chat::CONTEXT_BUDGET) {
+        let request = GenerateRequest {
+            temperature: 0.2,
+            max_output_tokens: 1024,
+            timeout: std::time::Duration::from_secs(60),
+            ..GenerateRequest::prompt(session.summary_prompt(until))
+        };
+        record_request("chat", provider.as_ref());
+        let summary = ai_provider::generate(provider.as_ref(), &request).await?;
+        record_tokens("chat", provider.as_ref(), summary.usage.as_ref());
+        println!("🗜️ Conversación resumida: {} mensajes", until - session.summarized_until);
+        session.set_summary(summary.text.clone(), until);
+        new_summary = Some((summary.text, until));
+    }
     
-    let config = serde_json::json!({
-        "api_key": api_key,
-        "model": model,
-        "enabled": true,
-        "agent_mode": agent_mode.unwrap_or(false),
-        "inline_suggestions_enabled": inline_suggestions_enabled.unwrap_or(false)
-    });
+    let last_user_text = if use_project_context.unwrap_or(false) {
+        Some(with_project_context(text).await)
+    } else {
+        None
+    };
+    let request = GenerateRequest {
+        messages: session.ai_messages(last_user_text),
+        max_output_tokens: if use_thinking { 2048 } else { 1024 },
+        thinking: use_thinking,
+        timeout: std::time::Duration::from_secs(60),
+        ..GenerateRequest::prompt(String::new())
+    };
+    record_request("chat", provider.as_ref());
+    let response = ai_provider::generate(provider.as_ref(), &request).await?;
+    record_tokens("chat", provider.as_ref(), response.usage.as_ref());
+    if response.text.is_empty() {
+        return Err("No se encontró contenido en la respuesta".to_string());
+    }
+    
+    // Se vuelve a leer al guardar: un rename durante la espera no se pierde
+    let reply = ChatMessage::new("assistant", response.text, Vec::new());
+    with_chats(|chats| chats.append(&session_id, vec![user_message, reply], new_summary))
+}
+
+// ============================================
+// AGENTE
+// ============================================
+
+// Ejecuciones terminadas a la espera de que el usuario aplique o descarte sus ediciones
+static AGENT_RUNS: Mutex<Option<HashMap<String, AgentRun>>> = Mutex::new(None);
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct AgentApplyResult {
+    backup_id: String,
+    result: WorkspaceEditResult,
+}
+
+// Resumen corto de los argumentos para el registro de actividad (sin el contenido de los archivos)
+fn agent_args_summary(args: &serde_json::Value) -> String {
+    let Some(object) = args.as_object() else { return String::new() };
+    object.iter()
+        .filter(|(key, _)| !matches!(key.as_str(), "content" | "replacements"))
+        .map(|(key, value)| match value.as_str() {
+            Some(text) => format!("{}: {}", key, text.chars().take(80).collect::<String>()),
+            None => format!("{}: {}", key, value),
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Ejecuta el agente sobre el proyecto abierto. Cada paso se emite en "agent-step" y se
+/// registra en la actividad; las ediciones quedan propuestas hasta `apply_agent_edits`.
+/// Se cancela con `cancel_ai_request(run_id)`.
+#[tauri::command]
+async fn run_agent(
+    app: tauri::AppHandle,
+    run_id: String,
+    task: String,
+    api_key: String,
+    model: String,
+    current_file: Option<String>,
+) -> Result<AgentRun, String> {
+    let provider = ai_provider(&api_key, &model);
+    if provider.requires_api_key() && !provider.has_api_key() {
+        return Err("No hay API key configurada".to_string());
+    }
+    let active = ActiveAiRequest::register(&run_id);
+    let cancel = active.cancel.clone();
+    
+    let root = SANDBOX.lock().unwrap().as_ref()
+        .map(|s| s.root().to_path_buf())
+        .ok_or_else(|| FsError::NoProjectOpen.to_string())?;
+    let mut tools = agent::AgentTools::new(Sandbox::new(&root)?)?;
+    let thinking = AI_CONFIG.lock().unwrap().as_ref().map(|c| c.agent_mode).unwrap_or(false);
+    
+    log_activity("agent", "Agente iniciado", "", Some(task.clone()));
+    let result = agent::run(
+        provider.as_ref(), &mut tools, &run_id, &task, current_file.as_deref(), thinking, &cancel,
+        &|totals: &UsageTotals| record_usage("agent", provider.as_ref(), totals),
+        |step| {
+            let _ = app.emit("agent-step", serde_json::json!({ "runId": run_id, "step": step }));
+            let action = if step.error {
+                format!("Agente: {} (error)", step.tool)
+            } else {
+                format!("Agente: {}", step.tool)
+            };
+            log_activity(
+                "agent",
+                &action,
+                &step.target,
+                Some(agent_args_summary(&step.args)),
+            );
+        },
+    ).await;
+    drop(active);
+    
+    let run = result?;
+    println!("🤖 Agente terminado ({}): {} pasos, {} ediciones", run.status, run.steps.len(), run.edits.len());
+    AGENT_RUNS.lock().unwrap()
+        .get_or_insert_with(HashMap::new)
+        .insert(run_id, run.clone());
+    Ok(run)
+}
+
+/// Aplica las ediciones propuestas (todas, o solo `paths`) en una sola transacción,
+/// después de crear un backup automático del proyecto.
+#[tauri::command]
+fn apply_agent_edits(run_id: String, paths: Option<Vec<String>>) -> Result<AgentApplyResult, String> {
+    let run = AGENT_RUNS.lock().unwrap().as_ref()
+        .and_then(|runs| runs.get(&run_id).cloned())
+        .ok_or_else(|| format!("No existe la ejecución del agente: {}", run_id))?;
+    let operations = run.operations(paths.as_deref());
+    if operations.is_empty() {
+        return Err("No hay ediciones para aplicar".to_string());
+    }
     
-    fs::write(&config_path, serde_json::to_string_pretty(&config).unwrap())
-        .map_err(|e| format!("Error guardando archivo: {}", e))?;
+    let root = SANDBOX.lock().unwrap().as_ref()
+        .map(|s| s.root().to_path_buf())
+        .ok_or_else(|| FsError::NoProjectOpen.to_string())?;
+    let backup = BACKUP_MANAGER.lock().unwrap().as_mut()
+        .ok_or_else(|| "Backup manager no inicializado".to_string())?
+        .create_backup(
+            &root,
+            Some("Antes de aplicar cambios del agente".to_string()),
+            Some(run.task.clone()),
+            "auto",
+        )?;
+    
+    let result = apply_workspace_edit(operations)
+        .map_err(|e| format!("Operación {}: {}", e.operation + 1, e.error))?;
+    
+    log_activity(
+        "agent",
+        "Cambios del agente aplicados",
+        result.changed_paths.first().map(|p| p.as_str()).unwrap_or(""),
+        Some(format!("{} archivos, backup {}", result.changed_paths.len(), backup.id)),
+    );
+    if let Some(runs) = AGENT_RUNS.lock().unwrap().as_mut() {
+        runs.remove(&run_id);
+    }
     
+    Ok(AgentApplyResult { backup_id: backup.id, result })
+}
+
+#[tauri::command]
+fn discard_agent_run(run_id: String) -> Result<(), String> {
+    if let Some(runs) = AGENT_RUNS.lock().unwrap().as_mut() {
+        runs.remove(&run_id);
+    }
     Ok(())
 }
 
-// Leer configuración de Gemini
+/// Genera un comando slash desde una descripción en lenguaje natural, con los modelos de
+/// Prisma y un comando de ejemplo como contexto, y lo valida con tsc. No escribe nada.
 #[tauri::command]
-fn load_gemini_config(app_data_dir: String) -> Result<String, String> {
-    let config_path = Path::new(&app_data_dir).join("gemini_config.json");
-    
-    if !config_path.exists() {
+async fn generate_command(
+    description: String,
+    category: Option<String>,
+    api_key: String,
+    model: String,
+) -> Result<GeneratedCommand, String> {
+    let provider = ai_provider(&api_key, &model);
+    if provider.requires_api_key() && !provider.has_api_key() {
         return Err("No hay API key configurada".to_string());
     }
+    let root = SANDBOX.lock().unwrap().as_ref()
+        .map(|s| s.root().to_path_buf())
+        .ok_or_else(|| FsError::NoProjectOpen.to_string())?;
+    let thinking = AI_CONFIG.lock().unwrap().as_ref().map(|c| c.agent_mode).unwrap_or(false);
+    
+    let record = |totals: &UsageTotals| record_usage("command", provider.as_ref(), totals);
+    let generated = command_gen::generate(provider.as_ref(), &root, &description, category.as_deref(), thinking, &record).await?;
+    println!(
+        "🤖 Comando generado: {} ({} intentos, {} errores de tipos)",
+        generated.name, generated.attempts, generated.type_check.errors.len()
+    );
+    Ok(generated)
+}
+
+/// Escribe un comando generado (después de previsualizarlo). Nunca pisa un archivo existente.
+#[tauri::command]
+fn create_generated_command(relative_path: String, content: String) -> Result<String, String> {
+    let path = workspace_path(&relative_path)?;
+    if path.exists() {
+        return Err(FsError::AlreadyExists(relative_path).into());
+    }
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| FsError::from_io(e, parent))?;
+    }
+    file_io::write_atomic(&path, content.as_bytes())?;
+    
+    log_activity("create", "Comando generado con IA", &path.to_string_lossy(), None);
+    Ok(path.to_string_lossy().to_string())
+}
+
+// Guardar configuración de IA. La API key se guarda cifrada aparte; vacía = conservar la actual,
+// salvo que cambie el proveedor o la URL: la key guardada solo se manda a donde se ingresó.
+#[tauri::command]
+fn save_gemini_config(
+    api_key: String,
+    model: String,
+    app_data_dir: String,
+    agent_mode: Option<bool>,
+    inline_suggestions_enabled: Option<bool>,
+    provider: Option<ProviderKind>,
+    base_url: Option<String>,
+) -> Result<ApiKeyStatus, String> {
+    let app_dir = Path::new(&app_data_dir);
+    let previous = AI_CONFIG.lock().unwrap().clone().unwrap_or_default();
+    
+    let mut config = AiConfig {
+        provider: provider.unwrap_or_default(),
+        base_url: base_url.filter(|url| !url.trim().is_empty()),
+        api_key: String::new(),
+        model,
+        enabled: true,
+        agent_mode: agent_mode.unwrap_or(false),
+        inline_suggestions_enabled: inline_suggestions_enabled.unwrap_or(false),
+        // No se edita desde este formulario: se conserva el que hubiera
+        embedding_model: previous.embedding_model.clone(),
+    };
+    
+    if !api_key.trim().is_empty() {
+        let storage = secrets::store_api_key(app_dir, api_key.trim())?;
+        println!("🔐 API key guardada ({})", storage);
+    } else if config.provider != previous.provider || config.endpoint() != previous.endpoint() {
+        secrets::delete_api_key(app_dir)?;
+        println!("🔐 Proveedor o URL cambiados: hay que volver a ingresar la API key");
+    }
+    config.api_key = secrets::load_api_key(app_dir).unwrap_or_default();
+    
+    config.save(app_dir)?;
+    *AI_CONFIG.lock().unwrap() = Some(config);
+    Ok(secrets::status(app_dir))
+}
+
+// Leer configuración de IA. Nunca incluye la API key: solo si está configurada y sus últimos 4 caracteres.
+#[tauri::command]
+fn load_gemini_config(app_data_dir: String) -> Result<String, String> {
+    let app_dir = Path::new(&app_data_dir);
+    let config = AiConfig::load(app_dir)
+        .ok_or_else(|| "No hay API key configurada".to_string())?;
     
-    let content = fs::read_to_string(&config_path).map_err(|e| e.to_string())?;
-    Ok(content)
+    let status = secrets::status(app_dir);
+    let mut json = serde_json::to_value(&config).map_err(|e| e.to_string())?;
+    json["api_key_configured"] = serde_json::json!(status.configured);
+    json["api_key_last4"] = serde_json::json!(status.last4);
+    json["api_key_storage"] = serde_json::json!(status.storage);
+    
+    serde_json::to_string(&json).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn get_api_key_status(app_data_dir: String) -> ApiKeyStatus {
+    secrets::status(Path::new(&app_data_dir))
+}
+
+#[tauri::command]
+fn delete_gemini_api_key(app_data_dir: String) -> Result<(), String> {
+    secrets::delete_api_key(Path::new(&app_data_dir))?;
+    if let Some(config) = AI_CONFIG.lock().unwrap().as_mut() {
+        config.api_key.clear();
+    }
+    Ok(())
 }
 
 // ============================================
 // ACTIVITY LOG COMMANDS
 // ============================================
 
+// Registra una acción del backend en el activity log (si está inicializado)
+fn log_activity(entry_type: &str, action: &str, file: &str, details: Option<String>) {
+    let mut log_lock = ACTIVITY_LOG.lock().unwrap();
+    
+    if let Some(log) = log_lock.as_mut() {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_millis() as u64;
+        
+        let entry = LogEntry {
+            id: uuid::Uuid::new_v4().to_string(),
+            entry_type: entry_type.to_string(),
+            action: action.to_string(),
+            file: file.to_string(),
+            timestamp,
+            lines: None,
+            details,
+            user: None,
+            diff: None,
+        };
+        
+        if let Err(e) = log.add_entry(entry) {
+            eprintln!("❌ Error guardando activity log: {}", e);
+        }
+    }
+}
+
 #[tauri::command]
 fn save_activity_log(entry: LogEntry) -> Result<(), String> {
     let mut log_lock = ACTIVITY_LOG.lock().unwrap();
@@ -1278,6 +2536,147 @@ fn compare_backup(backup_id: String) -> Result<(String, String), String> {
     }
 }
 
+// ============================================
+// LOCAL HISTORY COMMANDS
+// ============================================
+
+fn with_local_history<T>(f: impl FnOnce(&mut LocalHistory) -> Result<T, String>) -> Result<T, String> {
+    let mut history_lock = LOCAL_HISTORY.lock().unwrap();
+    let history = history_lock.as_mut().ok_or("Historial local no inicializado")?;
+    f(history)
+}
+
+/// Versiones guardadas del archivo, de la más nueva a la más antigua
+#[tauri::command]
+fn get_file_history(file_path: String) -> Result<Vec<HistoryEntry>, String> {
+    let path = sandboxed(&file_path)?;
+    with_local_history(|history| history.entries(&path))
+}
+
+/// Diff entre dos versiones; sin `to_id` se compara con el archivo en disco
+#[tauri::command]
+fn diff_file_history(file_path: String, from_id: String, to_id: Option<String>) -> Result<HistoryDiff, String> {
+    let path = sandboxed(&file_path)?;
+    
+    with_local_history(|history| {
+        let old = history.content(&path, &from_id)?;
+        let new = match &to_id {
+            Some(id) => history.content(&path, id)?,
+            None => fs::read(&path).unwrap_or_default(),
+        };
+        Ok(local_history::diff_versions(&old, &new))
+    })
+}
+
+/// Restaura una versión. El contenido actual se registra antes, así la restauración se puede deshacer.
+#[tauri::command]
+fn restore_file_history(file_path: String, entry_id: String) -> Result<FileVersion, String> {
+    let path = sandboxed(&file_path)?;
+    
+    let version = with_local_history(|history| {
+        let bytes = history.content(&path, &entry_id)?;
+        if let Ok(current) = fs::read(&path) {
+            history.record(&path, &current, "external")?;
+        }
+        let version = file_io::write_atomic(&path, &bytes)?;
+        history.record(&path, &bytes, "restore")?;
+        Ok(version)
+    })?;
+    
+    log_activity("save", "Versión restaurada del historial local", &file_path, None);
+    Ok(version)
+}
+
+#[tauri::command]
+fn clear_file_history(file_path: String) -> Result<(), String> {
+    let path = sandboxed(&file_path)?;
+    with_local_history(|history| history.clear(&path))
+}
+
+#[tauri::command]
+fn get_history_settings() -> Result<HistorySettings, String> {
+    with_local_history(|history| Ok(history.settings().clone()))
+}
+
+#[tauri::command]
+fn set_history_settings(settings: HistorySettings) -> Result<(), String> {
+    with_local_history(|history| history.set_settings(settings))
+}
+
+// ============================================
+// TRASH COMMANDS
+// ============================================
+
+#[tauri::command]
+fn get_trash_entries() -> Result<Vec<TrashEntry>, String> {
+    let mut trash_lock = TRASH.lock().unwrap();
+    
+    if let Some(trash) = trash_lock.as_mut() {
+        trash.purge_expired()?;
+        Ok(trash.get_entries().clone())
+    } else {
+        Ok(Vec::new())
+    }
+}
+
+#[tauri::command]
+fn restore_trash_entry(entry_id: String) -> Result<TrashEntry, String> {
+    let mut trash_lock = TRASH.lock().unwrap();
+    
+    if let Some(trash) = trash_lock.as_mut() {
+        // Solo dentro del proyecto abierto
+        trash.restore(&entry_id, |original| Ok(workspace_path(original)?))
+    } else {
+        Err("Papelera no inicializada".to_string())
+    }
+}
+
+#[tauri::command]
+fn purge_trash_entry(entry_id: String) -> Result<(), String> {
+    let mut trash_lock = TRASH.lock().unwrap();
+    
+    if let Some(trash) = trash_lock.as_mut() {
+        trash.purge(&entry_id)
+    } else {
+        Err("Papelera no inicializada".to_string())
+    }
+}
+
+#[tauri::command]
+fn empty_trash() -> Result<(), String> {
+    let mut trash_lock = TRASH.lock().unwrap();
+    
+    if let Some(trash) = trash_lock.as_mut() {
+        trash.purge_all()
+    } else {
+        Err("Papelera no inicializada".to_string())
+    }
+}
+
+#[tauri::command]
+fn get_trash_retention() -> Result<u64, String> {
+    let trash_lock = TRASH.lock().unwrap();
+    
+    if let Some(trash) = trash_lock.as_ref() {
+        Ok(trash.retention_days())
+    } else {
+        Err("Papelera no inicializada".to_string())
+    }
+}
+
+/// Cambia los días que se conservan los elementos (0 = no purgar nunca).
+/// Devuelve cuántos elementos se purgaron con la nueva retención.
+#[tauri::command]
+fn set_trash_retention(days: u64) -> Result<usize, String> {
+    let mut trash_lock = TRASH.lock().unwrap();
+    
+    if let Some(trash) = trash_lock.as_mut() {
+        trash.set_retention_days(days)
+    } else {
+        Err("Papelera no inicializada".to_string())
+    }
+}
+
 // ============================================
 // DIAGNOSTICS COMMANDS
 // ============================================
@@ -1323,9 +2722,15 @@ fn apply_quick_fix(error: DiagnosticError) -> Result<(), String> {
 }
 
 // Inicializar managers al inicio
+// El directorio sale del AppHandle: el webview no puede elegir dónde se guardan los datos
 #[tauri::command]
-fn init_managers(app_data_dir: String) -> Result<(), String> {
-    let app_dir = Path::new(&app_data_dir);
+fn init_managers(app: tauri::AppHandle) -> Result<(), String> {
+    let app_dir = app.path().app_data_dir().map_err(|e| e.to_string())?;
+    fs::create_dir_all(&app_dir).map_err(|e| e.to_string())?;
+    let app_dir = app_dir.as_path();
+    
+    // Proyectos que el usuario ya confirmó
+    *TRUSTED_PROJECTS.lock().unwrap() = Some(TrustedProjects::new(app_dir));
     
     // Inicializar Activity Log
     let activity_log = ActivityLog::new(app_dir)?;
@@ -1335,10 +2740,36 @@ fn init_managers(app_data_dir: String) -> Result<(), String> {
     let backup_manager = BackupManager::new(app_dir)?;
     *BACKUP_MANAGER.lock().unwrap() = Some(backup_manager);
     
+    // Límites para archivos grandes
+    *FILE_LIMITS.lock().unwrap() = Some(FileLimits::load(app_dir));
+    
+    // Inicializar Papelera (purga lo que ya expiró)
+    let trash = TrashManager::new(app_dir)?;
+    *TRASH.lock().unwrap() = Some(trash);
+    
+    // Proveedor de IA guardado
+    *AI_CONFIG.lock().unwrap() = AiConfig::load(app_dir);
+    
+    // Historial local por archivo
+    let local_history = LocalHistory::new(app_dir)?;
+    *LOCAL_HISTORY.lock().unwrap() = Some(local_history);
+    
+    // Índice semántico (se carga por proyecto al usarlo)
+    let semantic_index = SemanticIndex::new(app_dir)?;
+    *SEMANTIC_INDEX.lock().unwrap() = Some(semantic_index);
+    
+    // Conversaciones con la IA
+    let chats = ChatManager::new(app_dir)?;
+    *CHATS.lock().unwrap() = Some(chats);
+    
+    // Consumo de la IA y límites
+    *USAGE.lock().unwrap() = Some(UsageMeter::new(app_dir));
+    
     // Inicializar Diagnostics
     let diagnostics = DiagnosticsManager::new();
     *DIAGNOSTICS.lock().unwrap() = Some(diagnostics);
     
+    *APP_DIR.lock().unwrap() = Some(app_dir.to_path_buf());
     Ok(())
 }
 
@@ -1350,12 +2781,22 @@ pub fn run() {
         .invoke_handler(tauri::generate_handler![
             greet,
             validate_project_path,
+            pick_project_folder,
+            open_project,
+            allow_external_path,
             get_project_root,
             scan_commands,
             scan_events,
+            scan_event_bindings,
+            scan_components,
             get_project_stats,
             read_file_content,
             write_file_content,
+            probe_file,
+            read_file_lines,
+            read_file_chunk,
+            get_file_limits,
+            set_file_limits,
             scan_all_files,
             init_discord_rpc,
             update_discord_rpc,
@@ -1365,17 +2806,61 @@ pub fn run() {
             delete_file,
             delete_folder,
             rename_file,
+            copy_file,
+            copy_folder,
+            validate_workspace_edit,
+            apply_workspace_edit,
             prisma_db_push,
             prisma_db_pull,
             read_env_file,
             write_env_file,
             scan_env_variables,
             scan_env_variables_with_locations,
+            get_project_layout,
+            save_project_layout,
+            get_import_graph,
+            get_module_dependents,
+            get_module_dependencies,
+            preview_rename_module,
+            rename_module,
+            list_templates,
+            preview_scaffold,
+            scaffold,
+            fuzzy_search_files,
+            search_project,
+            cancel_search,
             get_package_scripts,
             get_gemini_completion,
+            cancel_completion,
+            get_completion_context,
+            get_completion_stats,
+            clear_completion_cache,
+            update_semantic_index,
+            semantic_search,
+            get_semantic_index_status,
+            clear_semantic_index,
             ask_gemini,
+            ask_gemini_stream,
+            cancel_ai_request,
+            create_chat_session,
+            list_chat_sessions,
+            get_chat_session,
+            rename_chat_session,
+            delete_chat_session,
+            export_chat_session,
+            send_chat_message,
+            get_ai_usage,
+            set_ai_budgets,
+            clear_ai_usage,
+            run_agent,
+            apply_agent_edits,
+            discard_agent_run,
+            generate_command,
+            create_generated_command,
             save_gemini_config,
             load_gemini_config,
+            get_api_key_status,
+            delete_gemini_api_key,
             // Nuevos comandos
             init_managers,
             save_activity_log,
@@ -1386,6 +2871,18 @@ pub fn run() {
             restore_backup,
             delete_backup,
             compare_backup,
+            get_file_history,
+            diff_file_history,
+            restore_file_history,
+            clear_file_history,
+            get_history_settings,
+            set_history_settings,
+            get_trash_entries,
+            restore_trash_entry,
+            purge_trash_entry,
+            empty_trash,
+            get_trash_retention,
+            set_trash_retention,
             get_diagnostics,
             analyze_file_diagnostics,
             clear_file_diagnostics,
@@ -0,0 +1,345 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::file_io;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HistoryEntry {
+    pub id: String,
+    pub hash: String,
+    pub timestamp: u64,
+    pub size: u64,
+    // "save", "external" (cambio hecho fuera del editor) o "restore"
+    pub source: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct HistorySettings {
+    pub max_entries: usize,
+    // 0 = sin límite de antigüedad
+    pub max_age_days: u64,
+    // Tamaño total de las versiones guardadas de un archivo
+    pub max_size_per_file: u64,
+    // Los archivos más grandes no se guardan en el historial
+    pub max_file_size: u64,
+}
+
+impl Default for HistorySettings {
+    fn default() -> Self {
+        HistorySettings {
+            max_entries: 100,
+            max_age_days: 30,
+            max_size_per_file: 20 * 1024 * 1024,
+            max_file_size: 2 * 1024 * 1024,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiffLine {
+    // "equal", "added" o "removed"
+    pub kind: String,
+    pub old_line: Option<usize>,
+    pub new_line: Option<usize>,
+    pub text: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HistoryDiff {
+    pub old_content: String,
+    pub new_content: String,
+    pub lines: Vec<DiffLine>,
+    pub added: usize,
+    pub removed: usize,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct FileHistory {
+    path: String,
+    entries: Vec<HistoryEntry>,
+}
+
+/// Historial local por archivo: cada guardado se registra en
+/// `<app_data>/history/<clave>/`, con un `index.json` y una copia por contenido distinto
+/// (nombrada por su hash, así dos versiones iguales comparten copia).
+pub struct LocalHistory {
+    history_dir: PathBuf,
+    settings: HistorySettings,
+}
+
+impl LocalHistory {
+    pub fn new(app_dir: &Path) -> Result<Self, String> {
+        let history_dir = app_dir.join("history");
+        fs::create_dir_all(&history_dir)
+            .map_err(|e| format!("Failed to create history directory: {}", e))?;
+
+        let settings = fs::read_to_string(history_dir.join("settings.json"))
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default();
+
+        Ok(LocalHistory { history_dir, settings })
+    }
+
+    pub fn settings(&self) -> &HistorySettings {
+        &self.settings
+    }
+
+    pub fn set_settings(&mut self, settings: HistorySettings) -> Result<(), String> {
+        let json = serde_json::to_string_pretty(&settings)
+            .map_err(|e| format!("Failed to serialize history settings: {}", e))?;
+        fs::write(self.history_dir.join("settings.json"), json)
+            .map_err(|e| format!("Failed to write history settings: {}", e))?;
+        self.settings = settings;
+        Ok(())
+    }
+
+    /// Registra una versión de `path`. No hace nada si coincide con la última
+    /// registrada o si supera `max_file_size`.
+    pub fn record(&self, path: &Path, bytes: &[u8], source: &str) -> Result<Option<HistoryEntry>, String> {
+        if bytes.len() as u64 > self.settings.max_file_size {
+            return Ok(None);
+        }
+
+        let dir = self.file_dir(path);
+        let mut history = self.load(path)?;
+        let hash = file_io::hash_bytes(bytes);
+        if history.entries.last().map(|e| e.hash == hash).unwrap_or(false) {
+            return Ok(None);
+        }
+
+        fs::create_dir_all(&dir)
+            .map_err(|e| format!("Failed to create history directory: {}", e))?;
+        let blob = dir.join(&hash);
+        if !blob.exists() {
+            fs::write(&blob, bytes)
+                .map_err(|e| format!("Failed to write history version: {}", e))?;
+        }
+
+        let entry = HistoryEntry {
+            id: format!("v_{}", uuid::Uuid::new_v4()),
+            hash,
+            timestamp: now_millis(),
+            size: bytes.len() as u64,
+            source: source.to_string(),
+        };
+        history.path = path.to_string_lossy().to_string();
+        history.entries.push(entry.clone());
+
+        self.prune(&mut history);
+        self.save(path, &history)?;
+        Ok(Some(entry))
+    }
+
+    /// Versiones de `path`, de la más nueva a la más antigua.
+    pub fn entries(&self, path: &Path) -> Result<Vec<HistoryEntry>, String> {
+        let mut entries = self.load(path)?.entries;
+        entries.reverse();
+        Ok(entries)
+    }
+
+    pub fn content(&self, path: &Path, entry_id: &str) -> Result<Vec<u8>, String> {
+        let history = self.load(path)?;
+        let entry = history.entries.iter()
+            .find(|e| e.id == entry_id)
+            .ok_or_else(|| format!("Version not found: {}", entry_id))?;
+
+        fs::read(self.file_dir(path).join(&entry.hash))
+            .map_err(|e| format!("Failed to read history version: {}", e))
+    }
+
+    /// Borra todo el historial de `path`.
+    pub fn clear(&self, path: &Path) -> Result<(), String> {
+        let dir = self.file_dir(path);
+        if dir.exists() {
+            fs::remove_dir_all(&dir)
+                .map_err(|e| format!("Failed to delete history: {}", e))?;
+        }
+        Ok(())
+    }
+
+    // Aplica los límites de antigüedad, cantidad y tamaño. La versión más nueva se conserva siempre.
+    fn prune(&self, history: &mut FileHistory) {
+        let now = now_millis();
+        let max_age_ms = self.settings.max_age_days * 24 * 60 * 60 * 1000;
+        let newest = history.entries.len().saturating_sub(1);
+
+        let mut index = 0;
+        history.entries.retain(|entry| {
+            let keep = index == newest
+                || max_age_ms == 0
+                || now.saturating_sub(entry.timestamp) <= max_age_ms;
+            index += 1;
+            keep
+        });
+
+        let max_entries = self.settings.max_entries.max(1);
+        if history.entries.len() > max_entries {
+            history.entries.drain(..history.entries.len() - max_entries);
+        }
+
+        while history.entries.len() > 1 && unique_size(&history.entries) > self.settings.max_size_per_file {
+            history.entries.remove(0);
+        }
+
+        // Copias que ya no usa ninguna versión
+        let used: HashSet<&str> = history.entries.iter().map(|e| e.hash.as_str()).collect();
+        let dir = self.file_dir(Path::new(&history.path));
+        if let Ok(files) = fs::read_dir(&dir) {
+            for file in files.flatten() {
+                let name = file.file_name().to_string_lossy().to_string();
+                if name != "index.json" && !used.contains(name.as_str()) {
+                    let _ = fs::remove_file(file.path());
+                }
+            }
+        }
+    }
+
+    fn file_dir(&self, path: &Path) -> PathBuf {
+        let key = file_io::hash_bytes(path.to_string_lossy().as_bytes());
+        self.history_dir.join(&key[..16])
+    }
+
+    fn load(&self, path: &Path) -> Result<FileHistory, String> {
+        let index_file = self.file_dir(path).join("index.json");
+        if !index_file.exists() {
+            return Ok(FileHistory::default());
+        }
+
+        let content = fs::read_to_string(&index_file)
+            .map_err(|e| format!("Failed to read history index: {}", e))?;
+        serde_json::from_str(&content)
+            .map_err(|e| format!("Failed to parse history index: {}", e))
+    }
+
+    fn save(&self, path: &Path, history: &FileHistory) -> Result<(), String> {
+        let json = serde_json::to_string_pretty(history)
+            .map_err(|e| format!("Failed to serialize history index: {}", e))?;
+        fs::write(self.file_dir(path).join("index.json"), json)
+            .map_err(|e| format!("Failed to write history index: {}", e))
+    }
+}
+
+fn unique_size(entries: &[HistoryEntry]) -> u64 {
+    let mut seen = HashSet::new();
+    entries.iter()
+        .filter(|e| seen.insert(e.hash.as_str()))
+        .map(|e| e.size)
+        .sum()
+}
+
+fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_millis() as u64
+}
+
+// Por encima de esto (líneas distintas viejas × nuevas) no se busca el diff mínimo
+const MAX_DIFF_CELLS: usize = 4_000_000;
+
+/// Diff por líneas entre dos versiones (LCS, recortando antes el prefijo y sufijo comunes).
+/// Si el bloque que cambia es muy grande se muestra como borrado + agregado completo.
+pub fn diff_versions(old_bytes: &[u8], new_bytes: &[u8]) -> HistoryDiff {
+    let to_text = |bytes: &[u8]| {
+        file_io::decode(bytes)
+            .map(|(text, _)| text.replace("\r\n", "\n"))
+            .unwrap_or_default()
+    };
+    let old_content = to_text(old_bytes);
+    let new_content = to_text(new_bytes);
+
+    let old: Vec<&str> = old_content.lines().collect();
+    let new: Vec<&str> = new_content.lines().collect();
+
+    let prefix = old.iter().zip(&new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..].iter().rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let old_mid = &old[prefix..old.len() - suffix];
+    let new_mid = &new[prefix..new.len() - suffix];
+
+    let mut lines = Vec::new();
+    let equal = |lines: &mut Vec<DiffLine>, o: usize, n: usize, text: &str| lines.push(DiffLine {
+        kind: "equal".to_string(),
+        old_line: Some(o + 1),
+        new_line: Some(n + 1),
+        text: text.to_string(),
+    });
+    let removed = |lines: &mut Vec<DiffLine>, o: usize, text: &str| lines.push(DiffLine {
+        kind: "removed".to_string(),
+        old_line: Some(o + 1),
+        new_line: None,
+        text: text.to_string(),
+    });
+    let added = |lines: &mut Vec<DiffLine>, n: usize, text: &str| lines.push(DiffLine {
+        kind: "added".to_string(),
+        old_line: None,
+        new_line: Some(n + 1),
+        text: text.to_string(),
+    });
+
+    for i in 0..prefix {
+        equal(&mut lines, i, i, old[i]);
+    }
+
+    if old_mid.len() * new_mid.len() <= MAX_DIFF_CELLS {
+        // lcs[i][j] = LCS de old_mid[i..] y new_mid[j..]
+        let width = new_mid.len() + 1;
+        let mut lcs = vec![0u32; (old_mid.len() + 1) * width];
+        for i in (0..old_mid.len()).rev() {
+            for j in (0..new_mid.len()).rev() {
+                lcs[i * width + j] = if old_mid[i] == new_mid[j] {
+                    lcs[(i + 1) * width + j + 1] + 1
+                } else {
+                    lcs[(i + 1) * width + j].max(lcs[i * width + j + 1])
+                };
+            }
+        }
+
+        let (mut i, mut j) = (0, 0);
+        while i < old_mid.len() || j < new_mid.len() {
+            if i < old_mid.len() && j < new_mid.len() && old_mid[i] == new_mid[j] {
+                equal(&mut lines, prefix + i, prefix + j, old_mid[i]);
+                i += 1;
+                j += 1;
+            } else if i < old_mid.len() && (j == new_mid.len() || lcs[(i + 1) * width + j] >= lcs[i * width + j + 1]) {
+                removed(&mut lines, prefix + i, old_mid[i]);
+                i += 1;
+            } else {
+                added(&mut lines, prefix + j, new_mid[j]);
+                j += 1;
+            }
+        }
+    } else {
+        for (i, text) in old_mid.iter().enumerate() {
+            removed(&mut lines, prefix + i, text);
+        }
+        for (j, text) in new_mid.iter().enumerate() {
+            added(&mut lines, prefix + j, text);
+        }
+    }
+
+    for k in 0..suffix {
+        let o = old.len() - suffix + k;
+        let n = new.len() - suffix + k;
+        equal(&mut lines, o, n, old[o]);
+    }
+
+    HistoryDiff {
+        added: lines.iter().filter(|l| l.kind == "added").count(),
+        removed: lines.iter().filter(|l| l.kind == "removed").count(),
+        old_content,
+        new_content,
+        lines,
+    }
+}
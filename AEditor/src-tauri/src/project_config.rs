@@ -0,0 +1,127 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+pub const CONFIG_FILE: &str = "aeditor.json";
+
+/// Estructura de carpetas del bot. Se lee de `aeditor.json` en la raíz del proyecto;
+/// los campos que falten usan la estructura de Amayo (`src/commands`, `src/events`...).
+/// Todas las rutas son relativas a la raíz del proyecto.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ProjectLayout {
+    pub src_dir: String,
+    pub commands_dir: String,
+    pub events_dir: String,
+    pub components_dir: String,
+    pub prisma_schema: String,
+    pub env_file: String,
+}
+
+impl Default for ProjectLayout {
+    fn default() -> Self {
+        ProjectLayout {
+            src_dir: "src".to_string(),
+            commands_dir: "src/commands".to_string(),
+            events_dir: "src/events".to_string(),
+            components_dir: "src/components".to_string(),
+            prisma_schema: "prisma/schema.prisma".to_string(),
+            env_file: ".env".to_string(),
+        }
+    }
+}
+
+impl ProjectLayout {
+    pub fn load(project_root: &Path) -> Result<Self, String> {
+        let config_path = project_root.join(CONFIG_FILE);
+
+        if !config_path.exists() {
+            return Ok(ProjectLayout::default());
+        }
+
+        let content = fs::read_to_string(&config_path)
+            .map_err(|e| format!("Failed to read {}: {}", CONFIG_FILE, e))?;
+
+        let layout: ProjectLayout = serde_json::from_str(&content)
+            .map_err(|e| format!("Invalid {}: {}", CONFIG_FILE, e))?;
+        layout.validate()?;
+        Ok(layout)
+    }
+
+    /// Todas las entradas deben ser relativas y quedarse dentro de la raíz del proyecto
+    /// (`aeditor.json` viene del repo del bot y no es de confianza).
+    pub fn validate(&self) -> Result<(), String> {
+        let entries = [
+            ("srcDir", &self.src_dir),
+            ("commandsDir", &self.commands_dir),
+            ("eventsDir", &self.events_dir),
+            ("componentsDir", &self.components_dir),
+            ("prismaSchema", &self.prisma_schema),
+            ("envFile", &self.env_file),
+        ];
+        for (name, value) in entries {
+            if !stays_inside(Path::new(value)) {
+                return Err(format!("Invalid {}: {} debe ser una ruta relativa dentro del proyecto ({})", CONFIG_FILE, name, value));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn save(&self, project_root: &Path) -> Result<(), String> {
+        self.validate()?;
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|e| format!("Failed to serialize {}: {}", CONFIG_FILE, e))?;
+
+        fs::write(project_root.join(CONFIG_FILE), json)
+            .map_err(|e| format!("Failed to write {}: {}", CONFIG_FILE, e))
+    }
+
+    pub fn src_path(&self, project_root: &Path) -> PathBuf {
+        project_root.join(&self.src_dir)
+    }
+
+    pub fn commands_path(&self, project_root: &Path) -> PathBuf {
+        project_root.join(&self.commands_dir)
+    }
+
+    pub fn events_path(&self, project_root: &Path) -> PathBuf {
+        project_root.join(&self.events_dir)
+    }
+
+    pub fn components_path(&self, project_root: &Path) -> PathBuf {
+        project_root.join(&self.components_dir)
+    }
+
+    pub fn prisma_schema_path(&self, project_root: &Path) -> PathBuf {
+        project_root.join(&self.prisma_schema)
+    }
+
+    /// Carpeta que contiene el schema de Prisma (normalmente `prisma/`)
+    pub fn prisma_path(&self, project_root: &Path) -> PathBuf {
+        self.prisma_schema_path(project_root)
+            .parent()
+            .map(|p| p.to_path_buf())
+            .unwrap_or_else(|| project_root.to_path_buf())
+    }
+
+    pub fn env_path(&self, project_root: &Path) -> PathBuf {
+        project_root.join(&self.env_file)
+    }
+}
+
+// Ruta relativa que, normalizada, no sale de la carpeta en la que se une
+fn stays_inside(path: &Path) -> bool {
+    let mut depth = 0usize;
+    for component in path.components() {
+        match component {
+            Component::Prefix(_) | Component::RootDir => return false,
+            Component::CurDir => {}
+            Component::ParentDir => match depth.checked_sub(1) {
+                Some(d) => depth = d,
+                None => return false,
+            },
+            Component::Normal(_) => depth += 1,
+        }
+    }
+    true
+}
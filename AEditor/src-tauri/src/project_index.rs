@@ -0,0 +1,222 @@
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::UNIX_EPOCH;
+
+use crate::project_config::ProjectLayout;
+
+const SOURCE_EXTENSIONS: &[&str] = &["ts", "tsx", "js", "mjs", "cjs"];
+
+// Única en todo el proceso: un índice recreado nunca repite la generación de otro
+static NEXT_GENERATION: AtomicU64 = AtomicU64::new(1);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportRef {
+    pub specifier: String,
+    pub line: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IndexedFile {
+    pub relative_path: String,
+    pub modified: u64,
+    pub size: u64,
+    pub imports: Vec<ImportRef>,
+}
+
+/// Índice en memoria de los archivos fuente del proyecto abierto.
+/// `refresh` solo vuelve a parsear los archivos cuya fecha de modificación o tamaño cambió.
+pub struct ProjectIndex {
+    root: PathBuf,
+    layout: ProjectLayout,
+    files: HashMap<String, IndexedFile>,
+    // Cambia cada vez que `refresh` encuentra cambios
+    generation: u64,
+    import_re: Regex,
+    call_re: Regex,
+}
+
+impl ProjectIndex {
+    pub fn new(root: &Path) -> Result<Self, String> {
+        Ok(ProjectIndex {
+            root: root.to_path_buf(),
+            layout: ProjectLayout::load(root)?,
+            files: HashMap::new(),
+            generation: 0,
+            // import x from "y" / import "y" / export { x } from "y"
+            import_re: Regex::new(
+                r#"(?m)^\s*(?:import|export)\b(?:[^;'"]*?\bfrom)?\s*["']([^"']+)["']"#
+            ).unwrap(),
+            // require("y") / import("y")
+            call_re: Regex::new(r#"\b(?:require|import)\(\s*["']([^"']+)["']\s*\)"#).unwrap(),
+        })
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    pub fn layout(&self) -> &ProjectLayout {
+        &self.layout
+    }
+
+    pub fn files(&self) -> &HashMap<String, IndexedFile> {
+        &self.files
+    }
+
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    /// Sincroniza el índice con el disco. Devuelve cuántos archivos se (re)parsearon.
+    pub fn refresh(&mut self) -> Result<usize, String> {
+        self.layout = ProjectLayout::load(&self.root)?;
+
+        let src_dir = self.layout.src_path(&self.root);
+        let mut paths = Vec::new();
+        if src_dir.exists() {
+            collect_files(&src_dir, &mut paths)?;
+        }
+
+        let mut seen = HashSet::with_capacity(paths.len());
+        let mut updated = 0;
+
+        for path in paths {
+            let metadata = match fs::metadata(&path) {
+                Ok(m) => m,
+                Err(_) => continue,
+            };
+            let modified = metadata.modified()
+                .ok()
+                .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+                .map(|d| d.as_millis() as u64)
+                .unwrap_or(0);
+            let size = metadata.len();
+
+            let relative_path = self.relative(&path);
+            seen.insert(relative_path.clone());
+
+            let unchanged = self.files.get(&relative_path)
+                .map(|f| f.modified == modified && f.size == size)
+                .unwrap_or(false);
+            if unchanged {
+                continue;
+            }
+
+            let content = fs::read_to_string(&path).unwrap_or_default();
+            let imports = self.parse_imports(&content);
+
+            self.files.insert(relative_path.clone(), IndexedFile {
+                relative_path,
+                modified,
+                size,
+                imports,
+            });
+            updated += 1;
+        }
+
+        // Quitar del índice los archivos borrados
+        let before = self.files.len();
+        self.files.retain(|path, _| seen.contains(path));
+        updated += before - self.files.len();
+
+        if updated > 0 {
+            self.generation = NEXT_GENERATION.fetch_add(1, Ordering::Relaxed);
+        }
+        Ok(updated)
+    }
+
+    fn parse_imports(&self, content: &str) -> Vec<ImportRef> {
+        let mut imports = Vec::new();
+
+        for re in [&self.import_re, &self.call_re] {
+            for cap in re.captures_iter(content) {
+                let m = cap.get(1).unwrap();
+                imports.push(ImportRef {
+                    specifier: m.as_str().to_string(),
+                    line: content[..m.start()].lines().count().max(1),
+                });
+            }
+        }
+
+        imports.sort_by_key(|i| i.line);
+        imports
+    }
+
+    /// Ruta relativa a la raíz del proyecto, siempre con `/` como separador.
+    pub fn relative(&self, path: &Path) -> String {
+        path.strip_prefix(&self.root)
+            .unwrap_or(path)
+            .to_string_lossy()
+            .replace('\\', "/")
+    }
+
+    /// Resuelve un import relativo al archivo que lo contiene, igual que TypeScript con
+    /// `moduleResolution: NodeNext` (`./x.js` puede apuntar a `./x.ts`).
+    /// Devuelve `None` para paquetes externos o imports que no existen en el índice.
+    pub fn resolve(&self, from: &str, specifier: &str) -> Option<String> {
+        if !specifier.starts_with('.') {
+            return None;
+        }
+
+        let base = Path::new(from).parent().unwrap_or(Path::new(""));
+        let joined = normalize(&base.join(specifier));
+
+        let stem = ["js", "mjs", "cjs"].iter()
+            .find_map(|ext| joined.strip_suffix(&format!(".{}", ext)))
+            .unwrap_or(&joined)
+            .to_string();
+
+        let mut candidates = vec![joined.clone()];
+        for candidate in [&joined, &stem] {
+            for ext in SOURCE_EXTENSIONS {
+                candidates.push(format!("{}.{}", candidate, ext));
+                candidates.push(format!("{}/index.{}", candidate, ext));
+            }
+        }
+
+        candidates.into_iter().find(|c| self.files.contains_key(c))
+    }
+}
+
+fn normalize(path: &Path) -> String {
+    let mut parts: Vec<String> = Vec::new();
+    for component in path.components() {
+        match component.as_os_str().to_str().unwrap_or("") {
+            "." | "" => {}
+            ".." => {
+                parts.pop();
+            }
+            part => parts.push(part.to_string()),
+        }
+    }
+    parts.join("/")
+}
+
+fn collect_files(dir: &Path, files: &mut Vec<PathBuf>) -> Result<(), String> {
+    for entry in fs::read_dir(dir).map_err(|e| e.to_string())? {
+        let entry = entry.map_err(|e| e.to_string())?;
+        let path = entry.path();
+
+        let name = path.file_name().and_then(|s| s.to_str()).unwrap_or("");
+        if name.starts_with('.') || name == "node_modules" {
+            continue;
+        }
+
+        if path.is_dir() {
+            collect_files(&path, files)?;
+        } else if !name.ends_with(".d.ts") {
+            let ext = path.extension().and_then(|s| s.to_str()).unwrap_or("");
+            if SOURCE_EXTENSIONS.contains(&ext) {
+                files.push(path);
+            }
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,270 @@
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::path::Path;
+
+use crate::file_io;
+use crate::project_index::ProjectIndex;
+use crate::workspace_edit::{EditOperation, TextEdit};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReferenceChange {
+    pub file: String,
+    pub line: usize,
+    pub before: String,
+    pub after: String,
+}
+
+/// Resultado de planificar un renombre: los cambios línea a línea para mostrar
+/// y las operaciones listas para `apply_workspace_edit`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RenamePreview {
+    pub old_path: String,
+    pub new_path: String,
+    pub old_name: Option<String>,
+    pub new_name: Option<String>,
+    pub changes: Vec<ReferenceChange>,
+    pub operations: Vec<EditOperation>,
+}
+
+// Cambios de una línea: (línea desde 1, reemplazos, texto antes, texto después)
+type LineEdits = (usize, Vec<TextEdit>, String, String);
+
+/// Planifica mover `old_rel` a `new_rel` (rutas relativas a la raíz) actualizando
+/// los imports que lo referencian, los imports relativos del propio archivo y,
+/// si se pasa `new_name`, el campo `name` del comando.
+pub fn plan_rename(
+    index: &ProjectIndex,
+    old_rel: &str,
+    new_rel: &str,
+    new_name: Option<&str>,
+) -> Result<RenamePreview, String> {
+    if !index.files().contains_key(old_rel) {
+        return Err(format!("El módulo no está en el índice del proyecto: {}", old_rel));
+    }
+    if old_rel == new_rel {
+        return Err("La ruta nueva es igual a la actual".to_string());
+    }
+    if index.root().join(new_rel).exists() {
+        return Err(format!("Ya existe: {}", new_rel));
+    }
+
+    // Ediciones agrupadas por archivo (ruta relativa *después* del renombre), por línea
+    let mut edits: BTreeMap<String, Vec<LineEdits>> = BTreeMap::new();
+    let mut old_name = None;
+
+    for (file, indexed) in index.files() {
+        let is_moved = file == old_rel;
+        let target_file = if is_moved { new_rel } else { file.as_str() };
+        let mut lines: Option<Vec<String>> = None;
+        // Una línea con dos imports del mismo módulo se reescribe entera la primera vez
+        let mut rewritten: HashSet<(usize, &str)> = HashSet::new();
+
+        for import in &indexed.imports {
+            let Some(resolved) = index.resolve(file, &import.specifier) else {
+                continue;
+            };
+
+            // Desde otro archivo: solo los imports del módulo movido.
+            // Desde el propio archivo: todos sus imports relativos cambian de base.
+            let new_specifier = if resolved == old_rel && !is_moved {
+                rewrite_specifier(&import.specifier, file, new_rel)
+            } else if is_moved && resolved != old_rel {
+                rewrite_specifier(&import.specifier, new_rel, &resolved)
+            } else {
+                continue;
+            };
+            if new_specifier == import.specifier || !rewritten.insert((import.line, import.specifier.as_str())) {
+                continue;
+            }
+
+            if lines.is_none() {
+                lines = Some(read_lines(&index.root().join(file))?);
+            }
+            let line_text = lines.as_ref()
+                .and_then(|l| l.get(import.line - 1))
+                .cloned()
+                .unwrap_or_default();
+            let columns = find_specifiers(&line_text, &import.specifier);
+            if columns.is_empty() {
+                continue;
+            }
+            let mut after = line_text.clone();
+            for column in columns.iter().rev() {
+                after.replace_range(*column..*column + import.specifier.len(), &new_specifier);
+            }
+
+            let line_edits = columns.iter()
+                .map(|column| {
+                    let start_column = line_text[..*column].encode_utf16().count() + 1;
+                    TextEdit {
+                        start_line: import.line,
+                        start_column,
+                        end_line: import.line,
+                        end_column: start_column + import.specifier.encode_utf16().count(),
+                        new_text: new_specifier.clone(),
+                    }
+                })
+                .collect();
+            edits.entry(target_file.to_string())
+                .or_default()
+                .push((import.line, line_edits, line_text, after));
+        }
+    }
+
+    if let Some(new_name) = new_name {
+        let lines = read_lines(&index.root().join(old_rel))?;
+        let (line, column, current) = find_command_name(&lines)
+            .ok_or_else(|| format!("No se encontró el campo `name` del comando en {}", old_rel))?;
+        old_name = Some(current.clone());
+
+        if current != new_name {
+            let line_text = lines[line - 1].clone();
+            let after = format!("{}{}{}", &line_text[..column], new_name, &line_text[column + current.len()..]);
+            let start_column = line_text[..column].encode_utf16().count() + 1;
+            edits.entry(new_rel.to_string()).or_default().push((
+                line,
+                vec![TextEdit {
+                    start_line: line,
+                    start_column,
+                    end_line: line,
+                    end_column: start_column + current.encode_utf16().count(),
+                    new_text: new_name.to_string(),
+                }],
+                line_text,
+                after,
+            ));
+        }
+    }
+
+    let absolute = |rel: &str| index.root().join(rel).to_string_lossy().to_string();
+    let mut operations = vec![EditOperation::Rename {
+        from: absolute(old_rel),
+        to: absolute(new_rel),
+        overwrite: false,
+    }];
+    let mut changes = Vec::new();
+
+    for (file, mut file_edits) in edits {
+        file_edits.sort_by_key(|(line, line_edits, _, _)| (*line, line_edits[0].start_column));
+
+        // La versión se toma del archivo en su ubicación actual
+        let on_disk = if file == new_rel { old_rel } else { file.as_str() };
+        let expected_version = file_io::current_version(&index.root().join(on_disk))
+            .map_err(|e| e.to_string())?;
+
+        for (line, _, before, after) in &file_edits {
+            changes.push(ReferenceChange {
+                file: file.clone(),
+                line: *line,
+                before: before.clone(),
+                after: after.clone(),
+            });
+        }
+
+        operations.push(EditOperation::Edit {
+            path: absolute(&file),
+            edits: file_edits.into_iter().flat_map(|(_, line_edits, _, _)| line_edits).collect(),
+            expected_version,
+        });
+    }
+
+    Ok(RenamePreview {
+        old_path: old_rel.to_string(),
+        new_path: new_rel.to_string(),
+        old_name,
+        new_name: new_name.map(|n| n.to_string()),
+        changes,
+        operations,
+    })
+}
+
+fn read_lines(path: &Path) -> Result<Vec<String>, String> {
+    let file = file_io::read_versioned(path).map_err(|e| e.to_string())?;
+    Ok(file.content.lines().map(|l| l.to_string()).collect())
+}
+
+// Posiciones en bytes de cada aparición del specifier entre comillas dentro de la línea
+fn find_specifiers(line: &str, specifier: &str) -> Vec<usize> {
+    let mut columns: Vec<usize> = ['"', '\'', '`'].iter()
+        .flat_map(|q| {
+            let quoted = format!("{}{}{}", q, specifier, q);
+            line.match_indices(&quoted).map(|(i, _)| i + 1).collect::<Vec<_>>()
+        })
+        .collect();
+    columns.sort_unstable();
+    columns
+}
+
+/// Calcula el nuevo specifier desde `from_file` hasta `target`, manteniendo el estilo
+/// del original: extensión `.js` (NodeNext), sin extensión o import de carpeta (`./x` → `x/index.ts`).
+fn rewrite_specifier(original: &str, from_file: &str, target: &str) -> String {
+    let target_path = Path::new(target);
+    let target_ext = target_path.extension().and_then(|e| e.to_str()).unwrap_or("");
+    let target_stem = target.strip_suffix(&format!(".{}", target_ext)).unwrap_or(target);
+
+    let original_ext = Path::new(original).extension().and_then(|e| e.to_str()).unwrap_or("");
+    let original_file = original.rsplit('/').next().unwrap_or(original);
+
+    let destination = if target_stem.ends_with("/index") && !original_file.starts_with("index") {
+        // Import de carpeta: mantenerlo como carpeta
+        target_stem.trim_end_matches("/index").to_string()
+    } else {
+        match original_ext {
+            "js" | "mjs" | "cjs" => {
+                let js_ext = match target_ext {
+                    "ts" | "tsx" => "js",
+                    "mts" => "mjs",
+                    "cts" => "cjs",
+                    other => other,
+                };
+                format!("{}.{}", target_stem, js_ext)
+            }
+            "ts" | "tsx" => target.to_string(),
+            _ => target_stem.to_string(),
+        }
+    };
+
+    relative_specifier(from_file, &destination)
+}
+
+/// Specifier relativo (`./x`, `../x`) desde el archivo `from_file` hasta `target`.
+pub fn relative_specifier(from_file: &str, target: &str) -> String {
+    let from_dir: Vec<&str> = from_file.split('/').filter(|p| !p.is_empty()).collect();
+    let from_dir = &from_dir[..from_dir.len().saturating_sub(1)];
+    let target: Vec<&str> = target.split('/').filter(|p| !p.is_empty()).collect();
+
+    let common = from_dir.iter()
+        .zip(target.iter())
+        .take_while(|(a, b)| a == b)
+        .count();
+
+    let mut parts: Vec<&str> = vec![".."; from_dir.len() - common];
+    parts.extend(&target[common..]);
+    let joined = parts.join("/");
+
+    if joined.starts_with("..") {
+        joined
+    } else {
+        format!("./{}", joined)
+    }
+}
+
+/// Busca `name: "x"` dentro del objeto exportado del comando.
+/// Devuelve (línea desde 1, offset en bytes del valor, valor).
+pub fn find_command_name(lines: &[String]) -> Option<(usize, usize, String)> {
+    let export_re = Regex::new(r"\bexport\s+(?:const\s+\w+|default)\b").unwrap();
+    let name_re = Regex::new(r#"^\s*name\s*:\s*["'`]([^"'`]+)["'`]"#).unwrap();
+
+    let start = lines.iter().position(|l| export_re.is_match(l))?;
+    lines.iter()
+        .enumerate()
+        .skip(start)
+        .find_map(|(i, line)| {
+            let cap = name_re.captures(line)?;
+            let m = cap.get(1)?;
+            Some((i + 1, m.start(), m.as_str().to_string()))
+        })
+}
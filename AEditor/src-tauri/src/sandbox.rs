@@ -0,0 +1,307 @@
+use serde::ser::SerializeStruct;
+use serde::{Serialize, Serializer};
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+
+/// Error tipado de las operaciones de archivos. Se envía al frontend como
+/// `{ kind, message, path }` para poder distinguir, por ejemplo, un acceso fuera del proyecto.
+#[derive(Debug, Clone)]
+pub enum FsError {
+    NoProjectOpen,
+    OutsideProject(String),
+    NotFound(String),
+    NotAFile(String),
+    NotADirectory(String),
+    AlreadyExists(String),
+    // El archivo cambió en disco desde que el editor lo leyó
+    Conflict(String),
+    // Edición de texto con un rango inválido: (ruta, motivo)
+    InvalidEdit(String, String),
+    // El texto no se puede guardar con la codificación del archivo: (ruta, motivo)
+    Encoding(String, String),
+    // Supera el límite para abrirlo en el editor; hay que usar el visor de archivos grandes
+    TooLarge(String),
+    PermissionDenied(String),
+    // El usuario no lo confirmó o la ruta es demasiado amplia (raíz del sistema, home)
+    NotAllowed(String),
+    Io(String, String),
+}
+
+impl FsError {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            FsError::NoProjectOpen => "noProjectOpen",
+            FsError::OutsideProject(_) => "outsideProject",
+            FsError::NotFound(_) => "notFound",
+            FsError::NotAFile(_) => "notAFile",
+            FsError::NotADirectory(_) => "notADirectory",
+            FsError::AlreadyExists(_) => "alreadyExists",
+            FsError::Conflict(_) => "conflict",
+            FsError::InvalidEdit(_, _) => "invalidEdit",
+            FsError::Encoding(_, _) => "encoding",
+            FsError::TooLarge(_) => "tooLarge",
+            FsError::PermissionDenied(_) => "permissionDenied",
+            FsError::NotAllowed(_) => "notAllowed",
+            FsError::Io(_, _) => "io",
+        }
+    }
+
+    pub fn path(&self) -> Option<&str> {
+        match self {
+            FsError::NoProjectOpen => None,
+            FsError::OutsideProject(p)
+            | FsError::NotFound(p)
+            | FsError::NotAFile(p)
+            | FsError::NotADirectory(p)
+            | FsError::AlreadyExists(p)
+            | FsError::Conflict(p)
+            | FsError::TooLarge(p)
+            | FsError::PermissionDenied(p)
+            | FsError::NotAllowed(p)
+            | FsError::InvalidEdit(p, _)
+            | FsError::Encoding(p, _)
+            | FsError::Io(p, _) => Some(p),
+        }
+    }
+
+    pub fn from_io(err: io::Error, path: &Path) -> Self {
+        let path = path.display().to_string();
+        match err.kind() {
+            io::ErrorKind::NotFound => FsError::NotFound(path),
+            io::ErrorKind::PermissionDenied => FsError::PermissionDenied(path),
+            io::ErrorKind::AlreadyExists => FsError::AlreadyExists(path),
+            _ => FsError::Io(path, err.to_string()),
+        }
+    }
+}
+
+impl fmt::Display for FsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FsError::NoProjectOpen => write!(f, "No hay ningún proyecto abierto"),
+            FsError::OutsideProject(p) => write!(f, "La ruta está fuera del proyecto: {}", p),
+            FsError::NotFound(p) => write!(f, "No existe: {}", p),
+            FsError::NotAFile(p) => write!(f, "La ruta no es un archivo: {}", p),
+            FsError::NotADirectory(p) => write!(f, "La ruta no es un directorio: {}", p),
+            FsError::AlreadyExists(p) => write!(f, "Ya existe: {}", p),
+            FsError::Conflict(p) => write!(
+                f,
+                "El archivo cambió en disco desde que se abrió: {}",
+                p
+            ),
+            FsError::InvalidEdit(p, e) => write!(f, "Edición inválida en {}: {}", p, e),
+            FsError::Encoding(p, e) => write!(f, "No se puede guardar {}: {}", p, e),
+            FsError::TooLarge(p) => write!(f, "El archivo es demasiado grande para el editor: {}", p),
+            FsError::PermissionDenied(p) => write!(
+                f,
+                "Permiso denegado: {}. Ejecuta el editor como administrador.",
+                p
+            ),
+            FsError::NotAllowed(p) => write!(f, "Acceso no permitido: {}", p),
+            FsError::Io(p, e) => write!(f, "Error de E/S en {}: {}", p, e),
+        }
+    }
+}
+
+impl Serialize for FsError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("FsError", 3)?;
+        state.serialize_field("kind", self.kind())?;
+        state.serialize_field("message", &self.to_string())?;
+        state.serialize_field("path", &self.path())?;
+        state.end()
+    }
+}
+
+impl From<FsError> for String {
+    fn from(err: FsError) -> Self {
+        err.to_string()
+    }
+}
+
+/// Limita las operaciones de archivos a la raíz del proyecto abierto
+/// y a las rutas externas que el usuario haya permitido explícitamente.
+pub struct Sandbox {
+    root: PathBuf,
+    allowed_external: Vec<PathBuf>,
+}
+
+impl Sandbox {
+    pub fn new(project_root: &Path) -> Result<Self, FsError> {
+        let root = project_root.canonicalize()
+            .map_err(|e| FsError::from_io(e, project_root))?;
+
+        if !root.is_dir() {
+            return Err(FsError::NotADirectory(root.display().to_string()));
+        }
+        if is_too_broad(&root) {
+            return Err(FsError::NotAllowed(root.display().to_string()));
+        }
+
+        Ok(Sandbox {
+            root,
+            allowed_external: Vec::new(),
+        })
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    pub fn allow_external(&mut self, path: &Path) -> Result<PathBuf, FsError> {
+        let resolved = external_target(path)?;
+        if !self.allowed_external.contains(&resolved) {
+            self.allowed_external.push(resolved.clone());
+        }
+        Ok(resolved)
+    }
+
+    // `root` está canonicalizada: las rutas se comparan también canonicalizadas
+    // (prefijo `\\?\` en Windows, raíz enlazada por symlink, componentes `.`)
+    pub fn is_external(&self, path: &Path) -> bool {
+        real_path(&lexical_normalize(path))
+            .map(|real| !real.starts_with(&self.root))
+            .unwrap_or(true)
+    }
+
+    /// Si `path` es la raíz del proyecto (para no dejar borrarla ni moverla).
+    pub fn is_root(&self, path: &Path) -> bool {
+        real_path(&lexical_normalize(path))
+            .map(|real| real == self.root)
+            .unwrap_or(false)
+    }
+
+    /// Resuelve `path` (absoluta o relativa a la raíz del proyecto) y verifica que,
+    /// después de seguir enlaces simbólicos, siga dentro del proyecto o de una ruta permitida.
+    /// Devuelve la ruta sin resolver los symlinks, para que borrar o renombrar un
+    /// enlace actúe sobre el enlace y no sobre su destino.
+    pub fn resolve(&self, path: &str) -> Result<PathBuf, FsError> {
+        let requested = Path::new(path);
+        let joined = if requested.is_absolute() {
+            requested.to_path_buf()
+        } else {
+            self.root.join(requested)
+        };
+        let normalized = lexical_normalize(&joined);
+
+        // La carpeta padre, con symlinks resueltos, debe estar permitida
+        let parent = normalized.parent().unwrap_or(&normalized);
+        let real_parent = real_path(parent)?;
+        let candidate = match normalized.file_name() {
+            Some(name) => real_parent.join(name),
+            None => real_parent,
+        };
+        if !self.is_allowed(&candidate) {
+            return Err(FsError::OutsideProject(path.to_string()));
+        }
+
+        // Si la ruta ya existe y es un symlink, su destino también debe estar permitido
+        if fs::symlink_metadata(&normalized).map(|m| m.file_type().is_symlink()).unwrap_or(false) {
+            let target = normalized.canonicalize()
+                .map_err(|e| FsError::from_io(e, &normalized))?;
+            if !self.is_allowed(&target) {
+                return Err(FsError::OutsideProject(path.to_string()));
+            }
+        }
+
+        Ok(normalized)
+    }
+
+    fn is_allowed(&self, real: &Path) -> bool {
+        real.starts_with(&self.root) || self.allowed_external.iter().any(|p| real.starts_with(p))
+    }
+}
+
+/// Ruta real que se permitiría con `allow_external`, para confirmarla antes con el usuario.
+/// Nunca se permite la raíz del sistema de archivos ni el home (ni una carpeta que lo contenga).
+pub fn external_target(path: &Path) -> Result<PathBuf, FsError> {
+    let resolved = real_path(&lexical_normalize(path))?;
+    if is_too_broad(&resolved) {
+        return Err(FsError::NotAllowed(resolved.display().to_string()));
+    }
+    Ok(resolved)
+}
+
+fn is_too_broad(real: &Path) -> bool {
+    if real.parent().is_none() {
+        return true;
+    }
+    std::env::var_os("HOME")
+        .or_else(|| std::env::var_os("USERPROFILE"))
+        .and_then(|home| Path::new(&home).canonicalize().ok())
+        .map(|home| home.starts_with(real))
+        .unwrap_or(false)
+}
+
+/// Raíces de proyecto que el usuario eligió o confirmó con un diálogo nativo,
+/// en `<app_data>/trusted_projects.json`. Abrir cualquier otra pide confirmación.
+pub struct TrustedProjects {
+    file: PathBuf,
+    roots: Vec<PathBuf>,
+}
+
+impl TrustedProjects {
+    pub fn new(app_dir: &Path) -> Self {
+        let file = app_dir.join("trusted_projects.json");
+        let roots = fs::read_to_string(&file)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default();
+        TrustedProjects { file, roots }
+    }
+
+    /// `root` debe estar canonicalizada (como `Sandbox::root`).
+    pub fn contains(&self, root: &Path) -> bool {
+        self.roots.iter().any(|r| r == root)
+    }
+
+    pub fn trust(&mut self, root: &Path) -> Result<(), String> {
+        if self.contains(root) {
+            return Ok(());
+        }
+        self.roots.push(root.to_path_buf());
+        let json = serde_json::to_string_pretty(&self.roots).map_err(|e| e.to_string())?;
+        fs::write(&self.file, json).map_err(|e| e.to_string())
+    }
+}
+
+/// Elimina `.` y `..` sin tocar el disco.
+fn lexical_normalize(path: &Path) -> PathBuf {
+    let mut result = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                result.pop();
+            }
+            other => result.push(other.as_os_str()),
+        }
+    }
+    result
+}
+
+/// Canonicaliza el ancestro existente más profundo y le agrega el resto de la ruta,
+/// para poder validar rutas que todavía no existen (crear archivo, destino de un rename).
+fn real_path(path: &Path) -> Result<PathBuf, FsError> {
+    let mut existing = path.to_path_buf();
+    let mut rest = Vec::new();
+
+    while !existing.exists() {
+        match (existing.file_name(), existing.parent()) {
+            (Some(name), Some(parent)) => {
+                rest.push(name.to_os_string());
+                existing = parent.to_path_buf();
+            }
+            _ => return Err(FsError::NotFound(path.display().to_string())),
+        }
+    }
+
+    let mut real = existing.canonicalize()
+        .map_err(|e| FsError::from_io(e, &existing))?;
+    for name in rest.into_iter().rev() {
+        real.push(name);
+    }
+    Ok(real)
+}
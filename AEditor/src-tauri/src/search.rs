@@ -0,0 +1,290 @@
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::ignore_rules::IgnoreRules;
+
+// Archivos más grandes que esto no se buscan por contenido
+const MAX_SEARCH_FILE_SIZE: u64 = 2 * 1024 * 1024;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FileMatch {
+    pub name: String,
+    pub path: String,
+    pub relative_path: String,
+    pub score: i64,
+    // Posiciones (en caracteres) de la ruta relativa que coinciden con la búsqueda
+    pub positions: Vec<usize>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct SearchOptions {
+    pub regex: bool,
+    pub case_sensitive: bool,
+    pub whole_word: bool,
+    pub context_lines: usize,
+    pub max_results: usize,
+}
+
+impl Default for SearchOptions {
+    fn default() -> Self {
+        SearchOptions {
+            regex: false,
+            case_sensitive: false,
+            whole_word: false,
+            context_lines: 2,
+            max_results: 2000,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TextMatch {
+    pub line: usize,
+    pub column: usize,
+    pub length: usize,
+    pub text: String,
+    pub before: Vec<String>,
+    pub after: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FileSearchResult {
+    pub search_id: String,
+    pub path: String,
+    pub relative_path: String,
+    pub matches: Vec<TextMatch>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchSummary {
+    pub search_id: String,
+    pub files_searched: usize,
+    pub files_matched: usize,
+    pub total_matches: usize,
+    pub truncated: bool,
+    pub cancelled: bool,
+}
+
+/// Lista los archivos del proyecto respetando `.gitignore`, con rutas relativas.
+pub fn project_files(project_root: &Path) -> Result<Vec<(PathBuf, String)>, String> {
+    let rules = IgnoreRules::load(project_root);
+    let mut files = Vec::new();
+    rules.collect_files(project_root, project_root, &mut files)?;
+
+    Ok(files.into_iter()
+        .map(|path| {
+            let relative = path.strip_prefix(project_root)
+                .unwrap_or(&path)
+                .to_string_lossy()
+                .replace('\\', "/");
+            (path, relative)
+        })
+        .collect())
+}
+
+/// Búsqueda difusa por nombre de archivo, ordenada por relevancia.
+pub fn fuzzy_find(project_root: &Path, query: &str, limit: usize) -> Result<Vec<FileMatch>, String> {
+    let mut matches: Vec<FileMatch> = project_files(project_root)?
+        .into_iter()
+        .filter_map(|(path, relative)| {
+            let (score, positions) = fuzzy_score(query, &relative)?;
+            Some(FileMatch {
+                name: path.file_name()
+                    .and_then(|n| n.to_str())
+                    .unwrap_or("")
+                    .to_string(),
+                path: path.to_string_lossy().to_string(),
+                relative_path: relative,
+                score,
+                positions,
+            })
+        })
+        .collect();
+
+    matches.sort_by(|a, b| {
+        b.score.cmp(&a.score)
+            .then(a.relative_path.len().cmp(&b.relative_path.len()))
+    });
+    matches.truncate(limit);
+
+    Ok(matches)
+}
+
+/// Puntúa `query` como subsecuencia de `candidate` (sin distinguir mayúsculas).
+/// Premia caracteres consecutivos, inicios de palabra y coincidencias en el nombre del archivo.
+/// Devuelve `None` si no todos los caracteres de la búsqueda aparecen en orden.
+pub fn fuzzy_score(query: &str, candidate: &str) -> Option<(i64, Vec<usize>)> {
+    let query: Vec<char> = query.chars()
+        .filter(|c| !c.is_whitespace())
+        .flat_map(|c| c.to_lowercase())
+        .collect();
+    if query.is_empty() {
+        return Some((0, Vec::new()));
+    }
+
+    let chars: Vec<char> = candidate.chars().collect();
+    let lower: Vec<char> = chars.iter()
+        .map(|c| c.to_lowercase().next().unwrap_or(*c))
+        .collect();
+    let name_start = chars.iter().rposition(|c| *c == '/').map(|i| i + 1).unwrap_or(0);
+
+    let mut positions = Vec::with_capacity(query.len());
+    let mut score = 0i64;
+    let mut qi = 0;
+    let mut previous: Option<usize> = None;
+
+    for (i, c) in lower.iter().enumerate() {
+        if qi == query.len() {
+            break;
+        }
+        if *c != query[qi] {
+            continue;
+        }
+
+        let mut bonus = 1;
+        if previous == Some(i.wrapping_sub(1)) {
+            bonus += 5;
+        }
+        let boundary = i == 0 || matches!(chars[i - 1], '/' | '_' | '-' | '.' | ' ')
+            || (chars[i].is_uppercase() && chars[i - 1].is_lowercase());
+        if boundary {
+            bonus += 4;
+        }
+        if i >= name_start {
+            bonus += 2;
+        }
+
+        score += bonus;
+        positions.push(i);
+        previous = Some(i);
+        qi += 1;
+    }
+
+    if qi < query.len() {
+        return None;
+    }
+
+    // Penalizar huecos largos entre la primera y la última coincidencia
+    let span = positions.last().unwrap() - positions.first().unwrap() + 1;
+    score -= (span - positions.len()) as i64 / 4;
+
+    Some((score, positions))
+}
+
+pub fn build_matcher(query: &str, options: &SearchOptions) -> Result<Regex, String> {
+    // Vacía coincidiría con todas las líneas del proyecto
+    if query.is_empty() {
+        return Err("La búsqueda está vacía".to_string());
+    }
+    let mut pattern = if options.regex {
+        query.to_string()
+    } else {
+        regex::escape(query)
+    };
+    if options.whole_word {
+        pattern = format!(r"\b(?:{})\b", pattern);
+    }
+
+    RegexBuilder::new(&pattern)
+        .case_insensitive(!options.case_sensitive)
+        .build()
+        .map_err(|e| format!("Expresión regular inválida: {}", e))
+}
+
+/// Busca `matcher` en todos los archivos de texto del proyecto.
+/// `on_file` se llama con los resultados de cada archivo en cuanto se encuentran.
+pub fn search_content<F>(
+    project_root: &Path,
+    search_id: &str,
+    matcher: &Regex,
+    options: &SearchOptions,
+    cancelled: &AtomicBool,
+    mut on_file: F,
+) -> Result<SearchSummary, String>
+where
+    F: FnMut(FileSearchResult),
+{
+    let mut summary = SearchSummary {
+        search_id: search_id.to_string(),
+        files_searched: 0,
+        files_matched: 0,
+        total_matches: 0,
+        truncated: false,
+        cancelled: false,
+    };
+
+    for (path, relative) in project_files(project_root)? {
+        if cancelled.load(Ordering::Relaxed) {
+            summary.cancelled = true;
+            break;
+        }
+
+        let too_big = fs::metadata(&path).map(|m| m.len() > MAX_SEARCH_FILE_SIZE).unwrap_or(true);
+        if too_big {
+            continue;
+        }
+
+        let bytes = match fs::read(&path) {
+            Ok(b) => b,
+            Err(_) => continue,
+        };
+        // Saltar binarios
+        if bytes.iter().take(8000).any(|b| *b == 0) {
+            continue;
+        }
+        let content = String::from_utf8_lossy(&bytes);
+        summary.files_searched += 1;
+
+        let lines: Vec<&str> = content.lines().collect();
+        let mut matches = Vec::new();
+
+        for (line_idx, line) in lines.iter().enumerate() {
+            for m in matcher.find_iter(line) {
+                let before_start = line_idx.saturating_sub(options.context_lines);
+                let after_end = (line_idx + 1 + options.context_lines).min(lines.len());
+
+                matches.push(TextMatch {
+                    line: line_idx + 1,
+                    column: line[..m.start()].chars().count() + 1,
+                    length: m.as_str().chars().count(),
+                    text: line.to_string(),
+                    before: lines[before_start..line_idx].iter().map(|l| l.to_string()).collect(),
+                    after: lines[line_idx + 1..after_end].iter().map(|l| l.to_string()).collect(),
+                });
+
+                if summary.total_matches + matches.len() >= options.max_results {
+                    summary.truncated = true;
+                    break;
+                }
+            }
+            if summary.truncated {
+                break;
+            }
+        }
+
+        if !matches.is_empty() {
+            summary.files_matched += 1;
+            summary.total_matches += matches.len();
+            on_file(FileSearchResult {
+                search_id: search_id.to_string(),
+                path: path.to_string_lossy().to_string(),
+                relative_path: relative,
+                matches,
+            });
+        }
+
+        if summary.truncated {
+            break;
+        }
+    }
+
+    Ok(summary)
+}
@@ -0,0 +1,171 @@
+use aes_gcm::aead::rand_core::RngCore;
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::Path;
+
+const SECRET_FILE: &str = "ai_secret.bin";
+const SALT_FILE: &str = "ai_secret.salt";
+// Versión del formato de `ai_secret.bin`: [versión][nonce de 12 bytes][texto cifrado]
+const FORMAT_VERSION: u8 = 1;
+
+/// Lo único que el frontend puede saber de la API key.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiKeyStatus {
+    pub configured: bool,
+    pub last4: Option<String>,
+    // "keyring", "encryptedFile" o "none"
+    pub storage: String,
+}
+
+/// Guarda la API key en el llavero del sistema (macOS / Windows) o, si no hay,
+/// cifrada con AES-256-GCM en `<app_data>/ai_secret.bin`. Devuelve dónde quedó.
+pub fn store_api_key(app_dir: &Path, api_key: &str) -> Result<&'static str, String> {
+    if keyring_store::set(api_key).is_ok() {
+        // No dejar una copia vieja en disco
+        let _ = fs::remove_file(app_dir.join(SECRET_FILE));
+        return Ok("keyring");
+    }
+
+    let cipher = file_cipher(app_dir)?;
+    let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+    let encrypted = cipher.encrypt(&nonce, api_key.as_bytes())
+        .map_err(|_| "Error cifrando la API key".to_string())?;
+
+    let mut bytes = vec![FORMAT_VERSION];
+    bytes.extend_from_slice(&nonce);
+    bytes.extend_from_slice(&encrypted);
+    fs::write(app_dir.join(SECRET_FILE), bytes)
+        .map_err(|e| format!("Error guardando la API key: {}", e))?;
+    Ok("encryptedFile")
+}
+
+pub fn load_api_key(app_dir: &Path) -> Option<String> {
+    load_with_storage(app_dir).map(|(key, _)| key)
+}
+
+pub fn delete_api_key(app_dir: &Path) -> Result<(), String> {
+    keyring_store::delete();
+    let path = app_dir.join(SECRET_FILE);
+    if path.exists() {
+        fs::remove_file(&path).map_err(|e| format!("Error borrando la API key: {}", e))?;
+    }
+    Ok(())
+}
+
+pub fn status(app_dir: &Path) -> ApiKeyStatus {
+    match load_with_storage(app_dir) {
+        Some((key, storage)) => ApiKeyStatus {
+            configured: true,
+            last4: Some(last4(&key)),
+            storage: storage.to_string(),
+        },
+        None => ApiKeyStatus {
+            configured: false,
+            last4: None,
+            storage: "none".to_string(),
+        },
+    }
+}
+
+fn last4(key: &str) -> String {
+    let chars: Vec<char> = key.chars().collect();
+    chars[chars.len().saturating_sub(4)..].iter().collect()
+}
+
+fn load_with_storage(app_dir: &Path) -> Option<(String, &'static str)> {
+    if let Some(key) = keyring_store::get() {
+        return Some((key, "keyring"));
+    }
+
+    let bytes = fs::read(app_dir.join(SECRET_FILE)).ok()?;
+    if bytes.len() < 13 || bytes[0] != FORMAT_VERSION {
+        return None;
+    }
+
+    let cipher = file_cipher(app_dir).ok()?;
+    let nonce: [u8; 12] = bytes[1..13].try_into().ok()?;
+    let decrypted = cipher.decrypt(&Nonce::from(nonce), &bytes[13..]).ok()?;
+    String::from_utf8(decrypted).ok().map(|key| (key, "encryptedFile"))
+}
+
+// Clave del archivo cifrado, derivada de una sal aleatoria guardada junto a él y de
+// datos de la máquina y el usuario: copiar la carpeta a otro equipo no alcanza para leerla.
+fn file_cipher(app_dir: &Path) -> Result<Aes256Gcm, String> {
+    let salt_path = app_dir.join(SALT_FILE);
+    let salt = match fs::read(&salt_path) {
+        Ok(salt) if salt.len() == 32 => salt,
+        _ => {
+            let mut salt = vec![0u8; 32];
+            OsRng.fill_bytes(&mut salt);
+            fs::create_dir_all(app_dir).map_err(|e| format!("Error creando directorio: {}", e))?;
+            fs::write(&salt_path, &salt).map_err(|e| format!("Error guardando la sal: {}", e))?;
+            salt
+        }
+    };
+
+    let mut hasher = Sha256::new();
+    hasher.update(b"aeditor-api-key-v1");
+    hasher.update(&salt);
+    hasher.update(machine_id().as_bytes());
+    for var in ["USER", "USERNAME"] {
+        hasher.update(std::env::var(var).unwrap_or_default().as_bytes());
+    }
+    let key: [u8; 32] = hasher.finalize().into();
+
+    Ok(Aes256Gcm::new(&Key::<Aes256Gcm>::from(key)))
+}
+
+fn machine_id() -> String {
+    ["/etc/machine-id", "/var/lib/dbus/machine-id"].iter()
+        .find_map(|path| fs::read_to_string(path).ok())
+        .map(|id| id.trim().to_string())
+        .or_else(|| std::env::var("COMPUTERNAME").ok())
+        .or_else(|| std::env::var("HOSTNAME").ok())
+        .unwrap_or_default()
+}
+
+// Llavero del sistema: Keychain en macOS y Credential Manager en Windows.
+// En Linux el llavero del kernel no persiste entre sesiones, así que se usa el archivo cifrado.
+#[cfg(any(windows, target_os = "macos"))]
+mod keyring_store {
+    const SERVICE: &str = "aeditor";
+    const ACCOUNT: &str = "ai_api_key";
+
+    fn entry() -> Option<keyring::Entry> {
+        keyring::Entry::new(SERVICE, ACCOUNT).ok()
+    }
+
+    pub fn set(api_key: &str) -> Result<(), String> {
+        entry()
+            .ok_or("Llavero no disponible")?
+            .set_password(api_key)
+            .map_err(|e| e.to_string())
+    }
+
+    pub fn get() -> Option<String> {
+        entry()?.get_password().ok()
+    }
+
+    pub fn delete() {
+        if let Some(entry) = entry() {
+            let _ = entry.delete_credential();
+        }
+    }
+}
+
+#[cfg(not(any(windows, target_os = "macos")))]
+mod keyring_store {
+    pub fn set(_api_key: &str) -> Result<(), String> {
+        Err("Llavero no disponible".to_string())
+    }
+
+    pub fn get() -> Option<String> {
+        None
+    }
+
+    pub fn delete() {}
+}
@@ -0,0 +1,445 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use crate::file_io;
+use crate::project_index::ProjectIndex;
+
+const CHUNK_LINES: usize = 40;
+// Líneas que comparte un fragmento con el siguiente, para no cortar una función sin contexto
+const CHUNK_OVERLAP: usize = 8;
+const MAX_CHUNK_CHARS: usize = 2400;
+// Archivos más grandes (generados, bundles...) no se indexan
+const MAX_FILE_SIZE: u64 = 256 * 1024;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SemanticHit {
+    pub file: String,
+    pub start_line: usize,
+    pub end_line: usize,
+    pub score: f32,
+    pub text: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SemanticIndexStatus {
+    pub project_root: Option<String>,
+    // "Proveedor:modelo" con el que se calcularon los vectores
+    pub embedder: Option<String>,
+    pub files: usize,
+    pub chunks: usize,
+    pub dimensions: usize,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SemanticSyncResult {
+    pub updated_files: usize,
+    pub removed_files: usize,
+    // Fragmentos que hubo que mandar al proveedor (los que no cambiaron reutilizan su vector)
+    pub embedded_chunks: usize,
+    pub total_chunks: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+struct FileStamp {
+    modified: u64,
+    size: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Chunk {
+    file: String,
+    start_line: usize,
+    end_line: usize,
+    hash: String,
+    text: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Manifest {
+    root: String,
+    embedder: String,
+    dimensions: usize,
+    files: BTreeMap<String, FileStamp>,
+    chunks: Vec<Chunk>,
+}
+
+/// Lo que falta para poner el índice al día: se calcula con el índice bloqueado, los
+/// embeddings se piden sin bloquearlo y el resultado se aplica con `apply`.
+pub struct SyncPlan {
+    root: String,
+    embedder: String,
+    stamps: BTreeMap<String, FileStamp>,
+    changed: HashSet<String>,
+    removed: Vec<String>,
+    chunks: Vec<Chunk>,
+    vectors: Vec<Option<Vec<f32>>>,
+    // Fragmentos cuyo vector se reutilizó del índice anterior
+    reused: usize,
+}
+
+impl SyncPlan {
+    pub fn is_empty(&self) -> bool {
+        self.changed.is_empty() && self.removed.is_empty()
+    }
+
+    /// Textos que hay que mandar al proveedor, con su posición en el plan.
+    pub fn pending(&self) -> Vec<(usize, String)> {
+        self.chunks.iter()
+            .zip(&self.vectors)
+            .enumerate()
+            .filter(|(_, (_, vector))| vector.is_none())
+            .map(|(i, (chunk, _))| (i, format!("File: {} (lines {}-{})\n{}", chunk.file, chunk.start_line, chunk.end_line, chunk.text)))
+            .collect()
+    }
+
+    pub fn set_vector(&mut self, position: usize, vector: Vec<f32>) {
+        self.vectors[position] = Some(normalize(vector));
+    }
+}
+
+/// Índice vectorial local del proyecto abierto, en `<app_data>/embeddings/<clave>/`:
+/// `index.json` con los fragmentos y `vectors.bin` con un vector f32 por fragmento.
+pub struct SemanticIndex {
+    embeddings_dir: PathBuf,
+    manifest: Manifest,
+    vectors: Vec<Vec<f32>>,
+}
+
+impl SemanticIndex {
+    pub fn new(app_dir: &Path) -> Result<Self, String> {
+        let embeddings_dir = app_dir.join("embeddings");
+        fs::create_dir_all(&embeddings_dir)
+            .map_err(|e| format!("Failed to create embeddings directory: {}", e))?;
+
+        Ok(SemanticIndex {
+            embeddings_dir,
+            manifest: Manifest::default(),
+            vectors: Vec::new(),
+        })
+    }
+
+    /// Carga desde disco el índice de `root` si no es el que está en memoria.
+    pub fn open(&mut self, root: &Path) {
+        let root_str = root.to_string_lossy().to_string();
+        if self.manifest.root == root_str {
+            return;
+        }
+
+        let dir = self.project_dir(&root_str);
+        let manifest: Option<Manifest> = fs::read_to_string(dir.join("index.json"))
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok());
+        let vectors = manifest.as_ref()
+            .and_then(|m| read_vectors(&dir.join("vectors.bin"), m.dimensions, m.chunks.len()));
+
+        match (manifest, vectors) {
+            (Some(manifest), Some(vectors)) => {
+                self.manifest = manifest;
+                self.vectors = vectors;
+            }
+            // Sin índice (o dañado): se reconstruye en el próximo sync
+            _ => {
+                self.manifest = Manifest { root: root_str, ..Default::default() };
+                self.vectors = Vec::new();
+            }
+        }
+    }
+
+    pub fn status(&self) -> SemanticIndexStatus {
+        SemanticIndexStatus {
+            project_root: Some(self.manifest.root.clone()).filter(|r| !r.is_empty()),
+            embedder: Some(self.manifest.embedder.clone()).filter(|e| !e.is_empty()),
+            files: self.manifest.files.len(),
+            chunks: self.manifest.chunks.len(),
+            dimensions: self.manifest.dimensions,
+        }
+    }
+
+    /// Compara el proyecto con el índice (fecha y tamaño, como `ProjectIndex`) y arma los
+    /// fragmentos de los archivos nuevos o modificados. Si cambió el modelo de embeddings
+    /// se reindexa todo.
+    pub fn plan(&self, index: &ProjectIndex, embedder: &str) -> SyncPlan {
+        let same_embedder = self.manifest.embedder == embedder;
+
+        let mut paths: Vec<String> = index.files().keys().cloned().collect();
+        let schema = index.layout().prisma_schema_path(index.root());
+        if schema.exists() {
+            paths.push(index.relative(&schema));
+        }
+
+        let mut stamps = BTreeMap::new();
+        let mut changed = HashSet::new();
+        for path in paths {
+            let Some(stamp) = stamp(&index.root().join(&path)) else { continue };
+            if stamp.size > MAX_FILE_SIZE {
+                continue;
+            }
+            if !same_embedder || self.manifest.files.get(&path) != Some(&stamp) {
+                changed.insert(path.clone());
+            }
+            stamps.insert(path, stamp);
+        }
+
+        let removed: Vec<String> = self.manifest.files.keys()
+            .filter(|path| !stamps.contains_key(*path))
+            .cloned()
+            .collect();
+
+        // Vectores ya calculados, por hash del fragmento: editar una función solo
+        // vuelve a calcular los fragmentos que la tocan
+        let known: HashMap<&str, &Vec<f32>> = if same_embedder {
+            self.manifest.chunks.iter()
+                .zip(&self.vectors)
+                .map(|(chunk, vector)| (chunk.hash.as_str(), vector))
+                .collect()
+        } else {
+            HashMap::new()
+        };
+
+        let mut sorted: Vec<&String> = changed.iter().collect();
+        sorted.sort();
+        let mut chunks = Vec::new();
+        for path in sorted {
+            if let Ok(file) = file_io::read_versioned(&index.root().join(path)) {
+                chunks.extend(chunk_file(path, &file.content));
+            }
+        }
+        let vectors: Vec<Option<Vec<f32>>> = chunks.iter()
+            .map(|chunk| known.get(chunk.hash.as_str()).map(|v| (*v).clone()))
+            .collect();
+        let reused = vectors.iter().filter(|v| v.is_some()).count();
+
+        SyncPlan {
+            root: self.manifest.root.clone(),
+            embedder: embedder.to_string(),
+            stamps,
+            changed,
+            removed,
+            chunks,
+            vectors,
+            reused,
+        }
+    }
+
+    /// Aplica un plan con todos sus vectores calculados y guarda el índice.
+    pub fn apply(&mut self, plan: SyncPlan) -> Result<SemanticSyncResult, String> {
+        // Se cambió de proyecto mientras se calculaban los embeddings
+        if plan.root != self.manifest.root {
+            return Err("El proyecto cambió durante la actualización del índice".to_string());
+        }
+        if plan.vectors.iter().any(|v| v.is_none()) {
+            return Err("Faltan embeddings por calcular".to_string());
+        }
+
+        let same_embedder = plan.embedder == self.manifest.embedder;
+        let stale: HashSet<&str> = plan.changed.iter()
+            .chain(&plan.removed)
+            .map(|p| p.as_str())
+            .collect();
+
+        // Se valida antes de tocar nada: si falla, el índice en memoria queda como estaba
+        let new_vectors: Vec<Vec<f32>> = plan.vectors.into_iter().flatten().collect();
+        let keeps_any = same_embedder && self.manifest.chunks.iter().any(|c| !stale.contains(c.file.as_str()));
+        let dimensions = if keeps_any {
+            self.manifest.dimensions
+        } else {
+            new_vectors.first().map(|v| v.len()).unwrap_or(0)
+        };
+        if new_vectors.iter().any(|v| v.len() != dimensions) {
+            return Err("El proveedor devolvió vectores de distinto tamaño".to_string());
+        }
+
+        if !same_embedder {
+            self.manifest.chunks.clear();
+            self.vectors.clear();
+        }
+        let mut kept_chunks = Vec::new();
+        let mut kept_vectors = Vec::new();
+        for (chunk, vector) in self.manifest.chunks.drain(..).zip(self.vectors.drain(..)) {
+            if !stale.contains(chunk.file.as_str()) {
+                kept_chunks.push(chunk);
+                kept_vectors.push(vector);
+            }
+        }
+
+        let result = SemanticSyncResult {
+            updated_files: plan.changed.len(),
+            removed_files: plan.removed.len(),
+            embedded_chunks: plan.chunks.len() - plan.reused,
+            total_chunks: 0,
+        };
+
+        kept_chunks.extend(plan.chunks);
+        kept_vectors.extend(new_vectors);
+
+        self.manifest.dimensions = dimensions;
+        self.manifest.embedder = plan.embedder;
+        self.manifest.files = plan.stamps;
+        self.manifest.chunks = kept_chunks;
+        self.vectors = kept_vectors;
+        self.save()?;
+
+        Ok(SemanticSyncResult { total_chunks: self.manifest.chunks.len(), ..result })
+    }
+
+    /// Los fragmentos más parecidos a `query` (ya convertido en vector), sin repetir
+    /// fragmentos solapados del mismo archivo.
+    pub fn search(&self, query: Vec<f32>, limit: usize) -> Vec<SemanticHit> {
+        let query = normalize(query);
+        if query.len() != self.manifest.dimensions {
+            return Vec::new();
+        }
+
+        let mut scored: Vec<(f32, &Chunk)> = self.manifest.chunks.iter()
+            .zip(&self.vectors)
+            .map(|(chunk, vector)| (dot(&query, vector), chunk))
+            .collect();
+        scored.sort_by(|a, b| b.0.total_cmp(&a.0));
+
+        let mut hits: Vec<SemanticHit> = Vec::new();
+        for (score, chunk) in scored {
+            if hits.len() >= limit {
+                break;
+            }
+            let overlaps = hits.iter().any(|hit| {
+                hit.file == chunk.file && hit.start_line <= chunk.end_line && chunk.start_line <= hit.end_line
+            });
+            if !overlaps {
+                hits.push(SemanticHit {
+                    file: chunk.file.clone(),
+                    start_line: chunk.start_line,
+                    end_line: chunk.end_line,
+                    score,
+                    text: chunk.text.clone(),
+                });
+            }
+        }
+        hits
+    }
+
+    /// Borra el índice del proyecto abierto (en memoria y en disco).
+    pub fn clear(&mut self) -> Result<(), String> {
+        let dir = self.project_dir(&self.manifest.root);
+        if dir.exists() {
+            fs::remove_dir_all(&dir)
+                .map_err(|e| format!("Failed to delete semantic index: {}", e))?;
+        }
+        self.manifest = Manifest { root: self.manifest.root.clone(), ..Default::default() };
+        self.vectors.clear();
+        Ok(())
+    }
+
+    fn project_dir(&self, root: &str) -> PathBuf {
+        let key = file_io::hash_bytes(root.as_bytes());
+        self.embeddings_dir.join(&key[..16])
+    }
+
+    fn save(&self) -> Result<(), String> {
+        let dir = self.project_dir(&self.manifest.root);
+        fs::create_dir_all(&dir)
+            .map_err(|e| format!("Failed to create embeddings directory: {}", e))?;
+
+        let mut bytes = Vec::with_capacity(self.vectors.len() * self.manifest.dimensions * 4);
+        for value in self.vectors.iter().flatten() {
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+        let json = serde_json::to_string(&self.manifest)
+            .map_err(|e| format!("Failed to serialize semantic index: {}", e))?;
+
+        file_io::write_atomic(&dir.join("vectors.bin"), &bytes).map_err(|e| e.to_string())?;
+        file_io::write_atomic(&dir.join("index.json"), json.as_bytes()).map_err(|e| e.to_string())?;
+        Ok(())
+    }
+}
+
+fn stamp(path: &Path) -> Option<FileStamp> {
+    let metadata = fs::metadata(path).ok()?;
+    let modified = metadata.modified()
+        .ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0);
+    Some(FileStamp { modified, size: metadata.len() })
+}
+
+/// Divide un archivo en ventanas de líneas solapadas. Cada ventana intenta terminar
+/// donde cierra un bloque de primer nivel (`}` / `};`) para no partir funciones.
+fn chunk_file(path: &str, content: &str) -> Vec<Chunk> {
+    let lines: Vec<&str> = content.lines().collect();
+    let mut chunks = Vec::new();
+    let mut start = 0;
+
+    while start < lines.len() {
+        let mut end = (start + CHUNK_LINES).min(lines.len());
+        if end < lines.len() {
+            let min_end = start + CHUNK_LINES / 2;
+            if let Some(close) = (min_end..end).rev().find(|&i| matches!(lines[i].trim_end(), "}" | "};")) {
+                end = close + 1;
+            }
+        }
+
+        let mut text = lines[start..end].join("\n");
+        if text.len() > MAX_CHUNK_CHARS {
+            let mut cut = MAX_CHUNK_CHARS;
+            while !text.is_char_boundary(cut) {
+                cut -= 1;
+            }
+            text.truncate(cut);
+            // El fragmento ya no llega hasta `end`: solo hasta la última línea que conserva
+            end = start + text.lines().count();
+        }
+
+        if !text.trim().is_empty() {
+            chunks.push(Chunk {
+                file: path.to_string(),
+                start_line: start + 1,
+                end_line: end,
+                hash: file_io::hash_bytes(format!("{}\0{}", path, text).as_bytes()),
+                text,
+            });
+        }
+
+        if end == lines.len() {
+            break;
+        }
+        start = end.saturating_sub(CHUNK_OVERLAP).max(start + 1);
+    }
+
+    chunks
+}
+
+fn read_vectors(path: &Path, dimensions: usize, count: usize) -> Option<Vec<Vec<f32>>> {
+    let bytes = fs::read(path).ok()?;
+    if bytes.len() != dimensions * count * 4 {
+        return None;
+    }
+    if dimensions == 0 {
+        return Some(vec![Vec::new(); count]);
+    }
+
+    let values: Vec<f32> = bytes.chunks_exact(4)
+        .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .collect();
+    Some(values.chunks(dimensions).map(|v| v.to_vec()).collect())
+}
+
+// Vectores normalizados: la similitud coseno queda como producto escalar
+fn normalize(mut vector: Vec<f32>) -> Vec<f32> {
+    let norm = vector.iter().map(|v| v * v).sum::<f32>().sqrt();
+    if norm > 0.0 {
+        vector.iter_mut().for_each(|v| *v /= norm);
+    }
+    vector
+}
+
+fn dot(a: &[f32], b: &[f32]) -> f32 {
+    a.iter().zip(b).map(|(x, y)| x * y).sum()
+}
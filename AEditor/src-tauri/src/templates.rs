@@ -0,0 +1,563 @@
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+use std::fs;
+use std::path::Path;
+
+use crate::events;
+use crate::project_config::ProjectLayout;
+use crate::refactor::relative_specifier;
+
+/// Carpeta (relativa a la raíz del bot) con las plantillas del equipo
+pub const TEMPLATES_DIR: &str = ".aeditor/templates";
+
+const SLASH_COMMAND: &str = r#"import type { ChatInputCommandInteraction } from "discord.js";
+import type { CommandSlash } from "{{srcRoot}}/core/types/commands";
+import type Amayo from "{{srcRoot}}/core/client";
+
+export const command: CommandSlash = {
+  name: "{{name}}",
+  description: "{{description}}",
+  type: "slash",
+{{#if cooldown}}  cooldown: {{cooldown}},
+{{/if}}{{#if options}}  options: [
+{{options}}
+  ],
+{{/if}}  run: async (interaction: ChatInputCommandInteraction, client: Amayo) => {
+{{#if optionGetters}}{{optionGetters}}
+
+{{/if}}    await interaction.reply("¡Comando {{name}} ejecutado!");
+  },
+};
+"#;
+
+const MESSAGE_COMMAND: &str = r#"import type { Message } from "discord.js";
+import type { CommandMessage } from "{{srcRoot}}/core/types/commands";
+import type Amayo from "{{srcRoot}}/core/client";
+
+export const command: CommandMessage = {
+  name: "{{name}}",
+  type: "message",
+  description: "{{description}}",
+{{#if category}}  category: "{{category|pascal}}",
+{{/if}}{{#if cooldown}}  cooldown: {{cooldown}},
+{{/if}}  usage: "{{name}}",
+  run: async (message: Message, args: string[], client: Amayo) => {
+    await message.reply("¡Comando {{name}} ejecutado!");
+  },
+};
+"#;
+
+const EVENT: &str = r#"import { Events } from "discord.js";
+import { bot } from "{{srcRoot}}/main";
+import logger from "{{srcRoot}}/core/lib/logger";
+
+bot.{{listener}}(Events.{{eventKey}}, async (...args) => {
+  logger.info("{{eventName}} recibido");
+});
+"#;
+
+const BUTTON: &str = r#"import type { ButtonInteraction } from "discord.js";
+import { MessageFlags } from "discord.js";
+import type { Button } from "{{srcRoot}}/core/types/components";
+import type Amayo from "{{srcRoot}}/core/client";
+
+export default {
+  customId: "{{customId}}",
+  run: async (interaction: ButtonInteraction, client: Amayo) => {
+    await interaction.reply({
+      content: "Botón {{customId}} pulsado",
+      flags: MessageFlags.Ephemeral,
+    });
+  },
+} satisfies Button;
+"#;
+
+const MODAL: &str = r#"import type { ModalSubmitInteraction } from "discord.js";
+import { MessageFlags } from "discord.js";
+import type { Modal } from "{{srcRoot}}/core/types/components";
+import type Amayo from "{{srcRoot}}/core/client";
+
+export default {
+  customId: "{{customId}}",
+  run: async (interaction: ModalSubmitInteraction, client: Amayo) => {
+    await interaction.reply({
+      content: "Formulario {{customId}} recibido",
+      flags: MessageFlags.Ephemeral,
+    });
+  },
+} satisfies Modal;
+"#;
+
+const SELECT_MENU: &str = r#"import type { SelectMenuInteraction } from "discord.js";
+import { MessageFlags } from "discord.js";
+import type { SelectMenu } from "{{srcRoot}}/core/types/components";
+import type Amayo from "{{srcRoot}}/core/client";
+
+export default {
+  customId: "{{customId}}",
+  run: async (interaction: SelectMenuInteraction, client: Amayo) => {
+    const selected = interaction.values[0];
+    await interaction.reply({
+      content: `Seleccionaste: ${selected}`,
+      flags: MessageFlags.Ephemeral,
+    });
+  },
+} satisfies SelectMenu;
+"#;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ScaffoldKind {
+    SlashCommand,
+    MessageCommand,
+    Event,
+    Button,
+    Modal,
+    SelectMenu,
+}
+
+impl ScaffoldKind {
+    const ALL: [ScaffoldKind; 6] = [
+        ScaffoldKind::SlashCommand,
+        ScaffoldKind::MessageCommand,
+        ScaffoldKind::Event,
+        ScaffoldKind::Button,
+        ScaffoldKind::Modal,
+        ScaffoldKind::SelectMenu,
+    ];
+
+    /// Id de la plantilla por defecto; también sirve de prefijo en el nombre de archivo
+    pub fn template_id(&self) -> &'static str {
+        match self {
+            ScaffoldKind::SlashCommand => "slash-command",
+            ScaffoldKind::MessageCommand => "message-command",
+            ScaffoldKind::Event => "event",
+            ScaffoldKind::Button => "button",
+            ScaffoldKind::Modal => "modal",
+            ScaffoldKind::SelectMenu => "select-menu",
+        }
+    }
+
+    fn default_path(&self) -> &'static str {
+        match self {
+            ScaffoldKind::SlashCommand => "{{commandsDir}}/splashcmd/{{category}}/{{name}}.ts",
+            ScaffoldKind::MessageCommand => "{{commandsDir}}/messages/{{category}}/{{name}}.ts",
+            ScaffoldKind::Event => "{{eventsDir}}/{{category}}/{{name}}.ts",
+            ScaffoldKind::Button => "{{componentsDir}}/buttons/{{category}}/{{name}}.ts",
+            ScaffoldKind::Modal => "{{componentsDir}}/modals/{{category}}/{{name}}.ts",
+            ScaffoldKind::SelectMenu => "{{componentsDir}}/selectMenus/{{category}}/{{name}}.ts",
+        }
+    }
+
+    fn builtin(&self) -> &'static str {
+        match self {
+            ScaffoldKind::SlashCommand => SLASH_COMMAND,
+            ScaffoldKind::MessageCommand => MESSAGE_COMMAND,
+            ScaffoldKind::Event => EVENT,
+            ScaffoldKind::Button => BUTTON,
+            ScaffoldKind::Modal => MODAL,
+            ScaffoldKind::SelectMenu => SELECT_MENU,
+        }
+    }
+
+    fn from_str(value: &str) -> Option<Self> {
+        let value = value.trim().to_lowercase().replace(['-', '_'], "");
+        ScaffoldKind::ALL.into_iter()
+            .find(|kind| kind.template_id().replace('-', "") == value)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TemplateInfo {
+    pub id: String,
+    pub kind: ScaffoldKind,
+    // "project" (de .aeditor/templates) o "builtin"
+    pub source: String,
+    pub file: Option<String>,
+    pub output_path: String,
+    pub variables: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SlashOption {
+    pub name: String,
+    pub description: String,
+    // STRING, INTEGER, BOOLEAN, USER, CHANNEL, ROLE, MENTIONABLE, NUMBER, ATTACHMENT
+    #[serde(rename = "type")]
+    pub option_type: String,
+    #[serde(default)]
+    pub required: bool,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ScaffoldRequest {
+    pub kind: Option<ScaffoldKind>,
+    // Id de plantilla; sin él se usa la plantilla por defecto del tipo
+    pub template: Option<String>,
+    pub name: String,
+    pub description: String,
+    // Subcarpeta dentro de la carpeta del tipo (`net`, `music`...)
+    pub category: String,
+    pub custom_id: Option<String>,
+    pub event_name: Option<String>,
+    pub once: bool,
+    pub cooldown: Option<u32>,
+    pub options: Vec<SlashOption>,
+    // Variables propias de las plantillas del equipo
+    pub variables: HashMap<String, String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScaffoldResult {
+    pub template: String,
+    pub path: String,
+    pub relative_path: String,
+    pub content: String,
+}
+
+struct Template {
+    id: String,
+    kind: ScaffoldKind,
+    file: Option<String>,
+    path: String,
+    body: String,
+}
+
+/// Plantillas disponibles: las integradas y las de `.aeditor/templates/*.ts`.
+/// Una plantilla del proyecto con el mismo id que una integrada la reemplaza.
+///
+/// Las plantillas del proyecto pueden empezar con una cabecera opcional:
+/// ```text
+/// /* @aeditor
+/// kind: button
+/// path: {{componentsDir}}/buttons/{{category}}/{{name|camel}}.ts
+/// */
+/// ```
+/// Sin `kind`, el tipo se deduce del prefijo del nombre (`button-confirm.ts` → button).
+fn load_templates(project_root: &Path) -> Result<Vec<Template>, String> {
+    let mut templates: Vec<Template> = ScaffoldKind::ALL.iter()
+        .map(|kind| Template {
+            id: kind.template_id().to_string(),
+            kind: *kind,
+            file: None,
+            path: kind.default_path().to_string(),
+            body: kind.builtin().to_string(),
+        })
+        .collect();
+
+    let dir = project_root.join(TEMPLATES_DIR);
+    if !dir.exists() {
+        return Ok(templates);
+    }
+
+    let mut entries: Vec<_> = fs::read_dir(&dir)
+        .map_err(|e| format!("Failed to read {}: {}", TEMPLATES_DIR, e))?
+        .flatten()
+        .map(|e| e.path())
+        .filter(|p| p.extension().and_then(|e| e.to_str()) == Some("ts"))
+        .collect();
+    entries.sort();
+
+    for path in entries {
+        let id = path.file_stem().and_then(|s| s.to_str()).unwrap_or("").to_string();
+        let content = fs::read_to_string(&path)
+            .map_err(|e| format!("Failed to read template {}: {}", id, e))?
+            .replace("\r\n", "\n");
+        let (header, body) = split_header(&content);
+
+        let kind = match header.get("kind") {
+            Some(kind) => ScaffoldKind::from_str(kind)
+                .ok_or_else(|| format!("Plantilla {}: tipo desconocido '{}'", id, kind))?,
+            None => ScaffoldKind::ALL.into_iter()
+                .find(|k| id.starts_with(k.template_id()))
+                .ok_or_else(|| format!("Plantilla {}: falta `kind` en la cabecera @aeditor", id))?,
+        };
+
+        let template = Template {
+            path: header.get("path").cloned().unwrap_or_else(|| kind.default_path().to_string()),
+            id: id.clone(),
+            kind,
+            file: Some(format!("{}/{}.ts", TEMPLATES_DIR, id)),
+            body: body.to_string(),
+        };
+
+        match templates.iter_mut().find(|t| t.id == id) {
+            Some(existing) => *existing = template,
+            None => templates.push(template),
+        }
+    }
+
+    Ok(templates)
+}
+
+fn split_header(content: &str) -> (HashMap<String, String>, &str) {
+    let mut header = HashMap::new();
+    let trimmed = content.trim_start();
+
+    let Some(rest) = trimmed.strip_prefix("/* @aeditor") else {
+        return (header, content);
+    };
+    let Some(end) = rest.find("*/") else {
+        return (header, content);
+    };
+
+    for line in rest[..end].lines() {
+        if let Some((key, value)) = line.split_once(':') {
+            header.insert(key.trim().to_string(), value.trim().to_string());
+        }
+    }
+
+    let body = &rest[end + 2..];
+    (header, body.strip_prefix('\n').unwrap_or(body))
+}
+
+pub fn list_templates(project_root: &Path) -> Result<Vec<TemplateInfo>, String> {
+    Ok(load_templates(project_root)?
+        .into_iter()
+        .map(|t| TemplateInfo {
+            variables: template_variables(&format!("{}\n{}", t.path, t.body)),
+            source: if t.file.is_some() { "project" } else { "builtin" }.to_string(),
+            id: t.id,
+            kind: t.kind,
+            file: t.file,
+            output_path: t.path,
+        })
+        .collect())
+}
+
+/// Genera el archivo a partir de la plantilla, sin escribirlo.
+pub fn render_scaffold(project_root: &Path, request: &ScaffoldRequest) -> Result<ScaffoldResult, String> {
+    let templates = load_templates(project_root)?;
+    let template = match (&request.template, request.kind) {
+        (Some(id), _) => templates.iter().find(|t| &t.id == id)
+            .ok_or_else(|| format!("No existe la plantilla '{}'", id))?,
+        (None, Some(kind)) => templates.iter().find(|t| t.id == kind.template_id()).unwrap(),
+        (None, None) => return Err("Indica el tipo (`kind`) o la plantilla a usar".to_string()),
+    };
+
+    let layout = ProjectLayout::load(project_root)?;
+    let mut vars = build_variables(template.kind, request, &layout)?;
+
+    let relative_path = render(&template.path, &vars)?
+        .split('/')
+        .filter(|part| !part.is_empty() && *part != ".")
+        .collect::<Vec<_>>()
+        .join("/");
+    if relative_path.split('/').any(|part| part == "..") {
+        return Err(format!("Ruta de salida inválida: {}", relative_path));
+    }
+
+    let src_root = relative_specifier(&relative_path, &layout.src_dir.replace('\\', "/"));
+    vars.insert("srcRoot".to_string(), src_root.trim_end_matches('/').to_string());
+
+    Ok(ScaffoldResult {
+        template: template.id.clone(),
+        path: project_root.join(&relative_path).to_string_lossy().to_string(),
+        content: render(&template.body, &vars)?,
+        relative_path,
+    })
+}
+
+fn build_variables(
+    kind: ScaffoldKind,
+    request: &ScaffoldRequest,
+    layout: &ProjectLayout,
+) -> Result<HashMap<String, String>, String> {
+    let name = request.name.trim();
+    if name.is_empty() {
+        return Err("El nombre es obligatorio".to_string());
+    }
+    if !name.chars().all(|c| c.is_alphanumeric() || c == '-' || c == '_') {
+        return Err(format!("Nombre inválido '{}': usa letras, números, - o _", name));
+    }
+
+    let mut vars: HashMap<String, String> = request.variables.clone();
+    vars.insert("name".to_string(), name.to_string());
+    vars.insert("description".to_string(), request.description.replace('"', "\\\""));
+    vars.insert("category".to_string(), request.category.trim().trim_matches('/').to_string());
+    vars.insert("srcDir".to_string(), layout.src_dir.clone());
+    vars.insert("commandsDir".to_string(), layout.commands_dir.clone());
+    vars.insert("eventsDir".to_string(), layout.events_dir.clone());
+    vars.insert("componentsDir".to_string(), layout.components_dir.clone());
+    if let Some(cooldown) = request.cooldown.filter(|c| *c > 0) {
+        vars.insert("cooldown".to_string(), cooldown.to_string());
+    }
+
+    match kind {
+        ScaffoldKind::SlashCommand => {
+            // Restricciones de Discord para comandos slash
+            if name.len() > 32 || name.chars().any(|c| c.is_uppercase()) {
+                return Err("Los comandos slash deben estar en minúsculas y tener hasta 32 caracteres".to_string());
+            }
+            if request.description.trim().is_empty() || request.description.chars().count() > 100 {
+                return Err("Los comandos slash necesitan una descripción de 1 a 100 caracteres".to_string());
+            }
+            let (options, getters) = render_options(&request.options)?;
+            vars.insert("options".to_string(), options);
+            vars.insert("optionGetters".to_string(), getters);
+        }
+        ScaffoldKind::Event => {
+            let raw = request.event_name.as_deref().unwrap_or(name);
+            let key = events::event_key(raw)
+                .ok_or_else(|| format!("'{}' no es un evento de discord.js", raw))?;
+            vars.insert("eventKey".to_string(), key.to_string());
+            vars.insert(
+                "eventName".to_string(),
+                events::resolve_event_name(&format!("Events.{}", key)).unwrap_or(key).to_string(),
+            );
+            vars.insert("listener".to_string(), if request.once { "once" } else { "on" }.to_string());
+            if request.once {
+                vars.insert("once".to_string(), "true".to_string());
+            }
+        }
+        ScaffoldKind::Button | ScaffoldKind::Modal | ScaffoldKind::SelectMenu => {
+            let custom_id = request.custom_id.clone()
+                .filter(|id| !id.trim().is_empty())
+                .unwrap_or_else(|| name.to_lowercase());
+            vars.insert("customId".to_string(), custom_id);
+        }
+        ScaffoldKind::MessageCommand => {}
+    }
+
+    Ok(vars)
+}
+
+// Bloque `options: [...]` en el formato de los comandos del bot y las líneas para leerlas
+fn render_options(options: &[SlashOption]) -> Result<(String, String), String> {
+    let mut blocks = Vec::new();
+    let mut getters = Vec::new();
+
+    for option in options {
+        let (type_id, getter) = match option.option_type.to_uppercase().as_str() {
+            "STRING" => (3, "getString"),
+            "INTEGER" => (4, "getInteger"),
+            "BOOLEAN" => (5, "getBoolean"),
+            "USER" => (6, "getUser"),
+            "CHANNEL" => (7, "getChannel"),
+            "ROLE" => (8, "getRole"),
+            "MENTIONABLE" => (9, "getMentionable"),
+            "NUMBER" => (10, "getNumber"),
+            "ATTACHMENT" => (11, "getAttachment"),
+            other => return Err(format!("Tipo de opción desconocido: {}", other)),
+        };
+
+        blocks.push(format!(
+            "    {{\n      name: \"{}\",\n      description: \"{}\",\n      type: {},\n      required: {},\n    }},",
+            option.name,
+            option.description.replace('"', "\\\""),
+            type_id,
+            option.required
+        ));
+        getters.push(format!(
+            "    const {} = interaction.options.{}(\"{}\"{});",
+            to_case(&option.name, "camel"),
+            getter,
+            option.name,
+            if option.required { ", true" } else { "" }
+        ));
+    }
+
+    Ok((blocks.join("\n"), getters.join("\n")))
+}
+
+/// Sustituye `{{var}}` / `{{var|filtro}}` y resuelve `{{#if var}}...{{/if}}` y
+/// `{{#unless var}}...{{/unless}}` (no anidados). Falla si falta alguna variable.
+pub fn render(template: &str, vars: &HashMap<String, String>) -> Result<String, String> {
+    let section_re = Regex::new(r"(?s)\{\{#(if|unless)\s+(\w+)\s*\}\}(.*?)\{\{/(?:if|unless)\}\}").unwrap();
+    let var_re = Regex::new(r"\{\{\s*(\w+)(?:\s*\|\s*(\w+))?\s*\}\}").unwrap();
+
+    let truthy = |name: &str| {
+        vars.get(name)
+            .map(|v| !v.is_empty() && v != "false" && v != "0")
+            .unwrap_or(false)
+    };
+
+    let sections_done = section_re.replace_all(template, |cap: &regex::Captures| {
+        let show = truthy(&cap[2]) == (&cap[1] == "if");
+        if show { cap[3].to_string() } else { String::new() }
+    });
+
+    let mut missing = BTreeSet::new();
+    let rendered = var_re.replace_all(&sections_done, |cap: &regex::Captures| {
+        match vars.get(&cap[1]) {
+            Some(value) => match cap.get(2) {
+                Some(filter) => to_case(value, filter.as_str()),
+                None => value.clone(),
+            },
+            None => {
+                missing.insert(cap[1].to_string());
+                String::new()
+            }
+        }
+    });
+
+    if !missing.is_empty() {
+        return Err(format!(
+            "Faltan variables de la plantilla: {}",
+            missing.into_iter().collect::<Vec<_>>().join(", ")
+        ));
+    }
+
+    Ok(rendered.into_owned())
+}
+
+// Variables y condiciones usadas por una plantilla, para que el frontend pueda pedirlas
+fn template_variables(template: &str) -> Vec<String> {
+    let re = Regex::new(r"\{\{\s*#?(?:if\s+|unless\s+)?(\w+)(?:\s*\|\s*\w+)?\s*\}\}").unwrap();
+    re.captures_iter(template)
+        .map(|cap| cap[1].to_string())
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect()
+}
+
+/// Convierte `value` a camel, pascal, kebab, snake, upper o lower.
+pub fn to_case(value: &str, case: &str) -> String {
+    // Partir en palabras por separadores y por cambios minúscula → mayúscula
+    let mut words: Vec<String> = Vec::new();
+    let mut current = String::new();
+    let mut previous_lower = false;
+    for c in value.chars() {
+        if !c.is_alphanumeric() {
+            if !current.is_empty() {
+                words.push(std::mem::take(&mut current));
+            }
+            previous_lower = false;
+            continue;
+        }
+        if c.is_uppercase() && previous_lower && !current.is_empty() {
+            words.push(std::mem::take(&mut current));
+        }
+        previous_lower = c.is_lowercase() || c.is_numeric();
+        current.push(c);
+    }
+    if !current.is_empty() {
+        words.push(current);
+    }
+
+    let capitalize = |w: &str| {
+        let lower = w.to_lowercase();
+        let mut chars = lower.chars();
+        chars.next()
+            .map(|first| first.to_uppercase().collect::<String>() + chars.as_str())
+            .unwrap_or_default()
+    };
+
+    match case {
+        "camel" => words.iter()
+            .enumerate()
+            .map(|(i, w)| if i == 0 { w.to_lowercase() } else { capitalize(w) })
+            .collect(),
+        "pascal" => words.iter().map(|w| capitalize(w)).collect(),
+        "kebab" => words.iter().map(|w| w.to_lowercase()).collect::<Vec<_>>().join("-"),
+        "snake" => words.iter().map(|w| w.to_lowercase()).collect::<Vec<_>>().join("_"),
+        "upper" => value.to_uppercase(),
+        "lower" => value.to_lowercase(),
+        _ => value.to_string(),
+    }
+}
@@ -0,0 +1,258 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const DEFAULT_RETENTION_DAYS: u64 = 30;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TrashEntry {
+    pub id: String,
+    pub name: String,
+    pub original_path: String,
+    pub is_dir: bool,
+    pub deleted_at: u64,
+    pub size: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TrashIndex {
+    retention_days: u64,
+    entries: Vec<TrashEntry>,
+}
+
+/// Papelera del editor: los archivos borrados se mueven a `<app_data>/trash/items/<id>/`
+/// y se purgan automáticamente pasados `retention_days` días (0 = nunca).
+pub struct TrashManager {
+    trash_dir: PathBuf,
+    index: TrashIndex,
+}
+
+impl TrashManager {
+    pub fn new(app_dir: &Path) -> Result<Self, String> {
+        let trash_dir = app_dir.join("trash");
+        fs::create_dir_all(trash_dir.join("items"))
+            .map_err(|e| format!("Failed to create trash directory: {}", e))?;
+
+        let index_file = trash_dir.join("trash.json");
+        let index = if index_file.exists() {
+            let content = fs::read_to_string(&index_file)
+                .map_err(|e| format!("Failed to read trash index: {}", e))?;
+            serde_json::from_str(&content)
+                .map_err(|e| format!("Failed to parse trash index: {}", e))?
+        } else {
+            TrashIndex {
+                retention_days: DEFAULT_RETENTION_DAYS,
+                entries: Vec::new(),
+            }
+        };
+
+        let mut manager = TrashManager { trash_dir, index };
+        manager.purge_expired()?;
+        Ok(manager)
+    }
+
+    /// Mueve `path` a la papelera y devuelve la entrada creada.
+    pub fn move_to_trash(&mut self, path: &Path) -> Result<TrashEntry, String> {
+        self.move_to_trash_as(path, path)
+    }
+
+    /// Igual que `move_to_trash`, pero registra `original` como ruta de restauración
+    /// (para elementos que ya se movieron a una ruta temporal antes de borrarlos).
+    pub fn move_to_trash_as(&mut self, path: &Path, original: &Path) -> Result<TrashEntry, String> {
+        self.purge_expired_in_passing();
+        let metadata = fs::symlink_metadata(path)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+
+        let id = format!("trash_{}", uuid::Uuid::new_v4());
+        let name = original.file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or("unknown")
+            .to_string();
+
+        let item_dir = self.trash_dir.join("items").join(&id);
+        fs::create_dir_all(&item_dir)
+            .map_err(|e| format!("Failed to create trash item: {}", e))?;
+
+        let size = dir_size(path);
+        move_path(path, &item_dir.join(&name))?;
+
+        let entry = TrashEntry {
+            id,
+            name,
+            original_path: original.to_string_lossy().to_string(),
+            is_dir: metadata.is_dir(),
+            deleted_at: now_millis(),
+            size,
+        };
+
+        self.index.entries.insert(0, entry.clone());
+        self.save()?;
+        Ok(entry)
+    }
+
+    /// Devuelve el elemento a su ruta original. Falla si ya existe algo en esa ruta.
+    /// `resolve` valida la ruta guardada en el índice (que podría estar manipulado)
+    /// y devuelve dónde restaurarlo.
+    pub fn restore<R>(&mut self, id: &str, resolve: R) -> Result<TrashEntry, String>
+    where
+        R: FnOnce(&str) -> Result<PathBuf, String>,
+    {
+        self.purge_expired_in_passing();
+        let entry = self.find(id)?.clone();
+        let original = resolve(&entry.original_path)?;
+
+        if fs::symlink_metadata(&original).is_ok() {
+            return Err(format!("Ya existe un archivo en la ruta original: {}", entry.original_path));
+        }
+        if let Some(parent) = original.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create directory: {}", e))?;
+        }
+
+        let item_dir = self.trash_dir.join("items").join(&entry.id);
+        move_path(&item_dir.join(&entry.name), &original)?;
+        let _ = fs::remove_dir_all(&item_dir);
+
+        self.index.entries.retain(|e| e.id != id);
+        self.save()?;
+        Ok(entry)
+    }
+
+    pub fn purge(&mut self, id: &str) -> Result<(), String> {
+        self.purge_expired_in_passing();
+        self.find(id)?;
+        self.remove_item(id)?;
+        self.index.entries.retain(|e| e.id != id);
+        self.save()
+    }
+
+    pub fn purge_all(&mut self) -> Result<(), String> {
+        let ids: Vec<String> = self.index.entries.iter().map(|e| e.id.clone()).collect();
+        for id in &ids {
+            self.remove_item(id)?;
+        }
+        self.index.entries.clear();
+        self.save()
+    }
+
+    /// Borra definitivamente las entradas más viejas que el tiempo de retención.
+    pub fn purge_expired(&mut self) -> Result<usize, String> {
+        if self.index.retention_days == 0 {
+            return Ok(0);
+        }
+
+        let max_age = self.index.retention_days * 24 * 60 * 60 * 1000;
+        let now = now_millis();
+        let expired: Vec<String> = self.index.entries.iter()
+            .filter(|e| now.saturating_sub(e.deleted_at) > max_age)
+            .map(|e| e.id.clone())
+            .collect();
+
+        for id in &expired {
+            self.remove_item(id)?;
+        }
+        if !expired.is_empty() {
+            self.index.entries.retain(|e| !expired.contains(&e.id));
+            self.save()?;
+        }
+
+        Ok(expired.len())
+    }
+
+    // En una sesión larga no basta con purgar al iniciar: se purga en cada operación
+    fn purge_expired_in_passing(&mut self) {
+        if let Err(e) = self.purge_expired() {
+            println!("⚠️ No se pudo purgar la papelera: {}", e);
+        }
+    }
+
+    pub fn get_entries(&self) -> &Vec<TrashEntry> {
+        &self.index.entries
+    }
+
+    pub fn retention_days(&self) -> u64 {
+        self.index.retention_days
+    }
+
+    pub fn set_retention_days(&mut self, days: u64) -> Result<usize, String> {
+        self.index.retention_days = days;
+        self.save()?;
+        self.purge_expired()
+    }
+
+    fn find(&self, id: &str) -> Result<&TrashEntry, String> {
+        self.index.entries.iter()
+            .find(|e| e.id == id)
+            .ok_or_else(|| format!("Trash entry not found: {}", id))
+    }
+
+    fn remove_item(&self, id: &str) -> Result<(), String> {
+        let item_dir = self.trash_dir.join("items").join(id);
+        if item_dir.exists() {
+            fs::remove_dir_all(&item_dir)
+                .map_err(|e| format!("Failed to purge trash item: {}", e))?;
+        }
+        Ok(())
+    }
+
+    fn save(&self) -> Result<(), String> {
+        let json = serde_json::to_string_pretty(&self.index)
+            .map_err(|e| format!("Failed to serialize trash index: {}", e))?;
+
+        fs::write(self.trash_dir.join("trash.json"), json)
+            .map_err(|e| format!("Failed to write trash index: {}", e))
+    }
+}
+
+fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_millis() as u64
+}
+
+// `rename` falla entre discos distintos (app data en C:, proyecto en D:); solo en ese caso copiar y borrar
+fn move_path(from: &Path, to: &Path) -> Result<(), String> {
+    match fs::rename(from, to) {
+        Ok(()) => return Ok(()),
+        Err(e) if e.kind() == io::ErrorKind::CrossesDevices => {}
+        Err(e) => return Err(format!("Failed to move {}: {}", from.display(), e)),
+    }
+
+    copy_recursive(from, to)?;
+    if from.is_dir() {
+        fs::remove_dir_all(from)
+    } else {
+        fs::remove_file(from)
+    }
+    .map_err(|e| format!("Failed to remove {}: {}", from.display(), e))
+}
+
+fn copy_recursive(from: &Path, to: &Path) -> Result<(), String> {
+    if from.is_dir() {
+        fs::create_dir_all(to).map_err(|e| format!("Failed to create directory: {}", e))?;
+        for entry in fs::read_dir(from).map_err(|e| e.to_string())? {
+            let entry = entry.map_err(|e| e.to_string())?;
+            copy_recursive(&entry.path(), &to.join(entry.file_name()))?;
+        }
+        Ok(())
+    } else {
+        fs::copy(from, to)
+            .map(|_| ())
+            .map_err(|e| format!("Failed to copy {}: {}", from.display(), e))
+    }
+}
+
+fn dir_size(path: &Path) -> u64 {
+    if path.is_dir() {
+        fs::read_dir(path)
+            .map(|entries| entries.flatten().map(|e| dir_size(&e.path())).sum())
+            .unwrap_or(0)
+    } else {
+        fs::metadata(path).map(|m| m.len()).unwrap_or(0)
+    }
+}
@@ -0,0 +1,259 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::ai_provider::TokenUsage;
+use crate::file_io;
+
+// Días que se conservan (algo más de un año, para comparar meses)
+const MAX_DAYS: usize = 400;
+// Días que se devuelven en el historial del resumen
+const HISTORY_DAYS: usize = 30;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UsageTotals {
+    pub requests: u64,
+    pub prompt_tokens: u64,
+    pub candidates_tokens: u64,
+    pub thought_tokens: u64,
+}
+
+impl UsageTotals {
+    /// Una petición; sin `usage` (servidores locales que no lo informan) solo cuenta la petición.
+    pub fn request(usage: Option<&TokenUsage>) -> Self {
+        let mut totals = UsageTotals::default();
+        totals.add_request(usage);
+        totals
+    }
+
+    /// Solo los tokens de una respuesta: la petición ya se contó al mandarla.
+    pub fn tokens(usage: Option<&TokenUsage>) -> Self {
+        let mut totals = UsageTotals::default();
+        totals.add_tokens(usage);
+        totals
+    }
+
+    pub fn add_request(&mut self, usage: Option<&TokenUsage>) {
+        self.requests += 1;
+        self.add_tokens(usage);
+    }
+
+    pub fn add_tokens(&mut self, usage: Option<&TokenUsage>) {
+        if let Some(usage) = usage {
+            self.prompt_tokens += usage.prompt_tokens;
+            self.candidates_tokens += usage.candidates_tokens;
+            self.thought_tokens += usage.thought_tokens;
+        }
+    }
+
+    fn merge(&mut self, other: &UsageTotals) {
+        self.requests += other.requests;
+        self.prompt_tokens += other.prompt_tokens;
+        self.candidates_tokens += other.candidates_tokens;
+        self.thought_tokens += other.thought_tokens;
+    }
+
+    pub fn total_tokens(&self) -> u64 {
+        self.prompt_tokens + self.candidates_tokens + self.thought_tokens
+    }
+}
+
+/// Límites de uso; `None` = sin límite. Al pasar cualquiera se desactivan las sugerencias inline.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct UsageBudgets {
+    pub daily_tokens: Option<u64>,
+    pub monthly_tokens: Option<u64>,
+    pub daily_requests: Option<u64>,
+    pub monthly_requests: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UsageBreakdown {
+    // "Proveedor:modelo"
+    pub model: String,
+    // "completion", "ask", "chat", "agent", "command" o "embedding"
+    pub feature: String,
+    pub totals: UsageTotals,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DailyUsage {
+    pub date: String,
+    pub totals: UsageTotals,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UsageSummary {
+    // Fechas en UTC: `YYYY-MM-DD` y `YYYY-MM`
+    pub today: String,
+    pub month: String,
+    pub daily: UsageTotals,
+    pub monthly: UsageTotals,
+    // Desglose del mes por modelo y función
+    pub by_model: Vec<UsageBreakdown>,
+    pub history: Vec<DailyUsage>,
+    pub budgets: UsageBudgets,
+    // Límites superados: "dailyTokens", "monthlyTokens", "dailyRequests", "monthlyRequests"
+    pub exceeded: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct UsageEntry {
+    model: String,
+    feature: String,
+    totals: UsageTotals,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+struct UsageFile {
+    budgets: UsageBudgets,
+    // Por día (`YYYY-MM-DD`), una entrada por modelo y función
+    days: BTreeMap<String, Vec<UsageEntry>>,
+}
+
+// Fecha UTC `YYYY-MM-DD` de un instante (algoritmo de días civiles de Howard Hinnant)
+fn utc_date(time: SystemTime) -> String {
+    let days = (time.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs() / 86_400) as i64;
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+/// Consumo de la IA por día, modelo y función, en `<app_data>/ai_usage.json`.
+pub struct UsageMeter {
+    file: PathBuf,
+    data: UsageFile,
+}
+
+impl UsageMeter {
+    pub fn new(app_dir: &Path) -> Self {
+        let file = app_dir.join("ai_usage.json");
+        let data = fs::read_to_string(&file)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default();
+        UsageMeter { file, data }
+    }
+
+    fn save(&self) -> Result<(), String> {
+        let json = serde_json::to_string_pretty(&self.data).map_err(|e| e.to_string())?;
+        file_io::write_atomic(&self.file, json.as_bytes())?;
+        Ok(())
+    }
+
+    pub fn record(&mut self, model: &str, feature: &str, totals: &UsageTotals) -> Result<(), String> {
+        if *totals == UsageTotals::default() {
+            return Ok(());
+        }
+        let entries = self.data.days.entry(utc_date(SystemTime::now())).or_default();
+        match entries.iter_mut().find(|e| e.model == model && e.feature == feature) {
+            Some(entry) => entry.totals.merge(totals),
+            None => entries.push(UsageEntry {
+                model: model.to_string(),
+                feature: feature.to_string(),
+                totals: *totals,
+            }),
+        }
+
+        while self.data.days.len() > MAX_DAYS {
+            self.data.days.pop_first();
+        }
+        self.save()
+    }
+
+    pub fn set_budgets(&mut self, budgets: UsageBudgets) -> Result<(), String> {
+        self.data.budgets = budgets;
+        self.save()
+    }
+
+    pub fn clear(&mut self) -> Result<(), String> {
+        self.data.days.clear();
+        self.save()
+    }
+
+    // Totales de los días que empiezan por `prefix` (un día o un mes)
+    fn totals(&self, prefix: &str) -> UsageTotals {
+        let mut totals = UsageTotals::default();
+        for (_, entries) in self.data.days.range(prefix.to_string()..).take_while(|(date, _)| date.starts_with(prefix)) {
+            for entry in entries {
+                totals.merge(&entry.totals);
+            }
+        }
+        totals
+    }
+
+    fn exceeded_for(&self, daily: &UsageTotals, monthly: &UsageTotals) -> Vec<String> {
+        let budgets = &self.data.budgets;
+        [
+            ("dailyTokens", budgets.daily_tokens, daily.total_tokens()),
+            ("monthlyTokens", budgets.monthly_tokens, monthly.total_tokens()),
+            ("dailyRequests", budgets.daily_requests, daily.requests),
+            ("monthlyRequests", budgets.monthly_requests, monthly.requests),
+        ]
+        .into_iter()
+        .filter(|(_, limit, used)| limit.map(|limit| *used >= limit).unwrap_or(false))
+        .map(|(name, _, _)| name.to_string())
+        .collect()
+    }
+
+    /// Límites superados hoy o este mes (vacío si no hay).
+    pub fn exceeded(&self) -> Vec<String> {
+        let today = utc_date(SystemTime::now());
+        self.exceeded_for(&self.totals(&today), &self.totals(&today[..7]))
+    }
+
+    pub fn summary(&self) -> UsageSummary {
+        let today = utc_date(SystemTime::now());
+        let month = today[..7].to_string();
+        let daily = self.totals(&today);
+        let monthly = self.totals(&month);
+
+        let mut by_model: Vec<UsageBreakdown> = Vec::new();
+        for (_, entries) in self.data.days.range(month.clone()..).take_while(|(date, _)| date.starts_with(&month)) {
+            for entry in entries {
+                match by_model.iter_mut().find(|b| b.model == entry.model && b.feature == entry.feature) {
+                    Some(breakdown) => breakdown.totals.merge(&entry.totals),
+                    None => by_model.push(UsageBreakdown {
+                        model: entry.model.clone(),
+                        feature: entry.feature.clone(),
+                        totals: entry.totals,
+                    }),
+                }
+            }
+        }
+        by_model.sort_by_key(|b| std::cmp::Reverse(b.totals.total_tokens()));
+
+        let history = self.data.days.keys()
+            .rev()
+            .take(HISTORY_DAYS)
+            .map(|date| DailyUsage { date: date.clone(), totals: self.totals(date) })
+            .collect();
+
+        UsageSummary {
+            exceeded: self.exceeded_for(&daily, &monthly),
+            today,
+            month,
+            daily,
+            monthly,
+            by_model,
+            history,
+            budgets: self.data.budgets.clone(),
+        }
+    }
+}
@@ -0,0 +1,560 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::file_io::{self, FileVersion};
+use crate::sandbox::FsError;
+
+/// Reemplazo de texto con posiciones estilo Monaco: líneas y columnas desde 1,
+/// columnas contadas en unidades UTF-16 (un emoji ocupa dos). El rango es [inicio, fin).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TextEdit {
+    pub start_line: usize,
+    pub start_column: usize,
+    pub end_line: usize,
+    pub end_column: usize,
+    pub new_text: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum EditOperation {
+    #[serde(rename_all = "camelCase")]
+    CreateFile {
+        path: String,
+        #[serde(default)]
+        content: String,
+        #[serde(default)]
+        overwrite: bool,
+    },
+    #[serde(rename_all = "camelCase")]
+    CreateFolder { path: String },
+    #[serde(rename_all = "camelCase")]
+    Rename {
+        from: String,
+        to: String,
+        #[serde(default)]
+        overwrite: bool,
+    },
+    #[serde(rename_all = "camelCase")]
+    Delete { path: String },
+    #[serde(rename_all = "camelCase")]
+    Edit {
+        path: String,
+        edits: Vec<TextEdit>,
+        expected_version: Option<FileVersion>,
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WorkspaceEditResult {
+    pub operations: usize,
+    pub changed_paths: Vec<String>,
+}
+
+/// Operación que falló (índice en el lote) y, si hubo, los errores al deshacer las anteriores.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EditFailure {
+    pub operation: usize,
+    pub error: FsError,
+    pub rollback_errors: Vec<String>,
+}
+
+impl EditFailure {
+    fn at(operation: usize, error: FsError) -> Self {
+        EditFailure {
+            operation,
+            error,
+            rollback_errors: Vec::new(),
+        }
+    }
+}
+
+// Operación ya validada, con rutas resueltas y el contenido final de las ediciones calculado
+enum Planned {
+    WriteFile { path: PathBuf, content: String },
+    CreateFolder { path: PathBuf },
+    Rename { from: PathBuf, to: PathBuf },
+    Delete { path: PathBuf },
+}
+
+// Estado simulado de una ruta durante la validación
+#[derive(Clone)]
+enum Node {
+    Absent,
+    // Carpeta; si viene del disco, de dónde leer su contenido
+    Dir(Option<PathBuf>),
+    File(FileSource),
+}
+
+#[derive(Clone)]
+enum FileSource {
+    Disk(PathBuf),
+    Memory(String),
+}
+
+/// Simula el lote sobre un overlay del sistema de archivos, sin tocar el disco,
+/// para que las operaciones posteriores vean el efecto de las anteriores.
+struct Overlay {
+    entries: HashMap<PathBuf, Node>,
+}
+
+impl Overlay {
+    fn lookup(&self, path: &Path) -> Node {
+        // La entrada más profunda gana: al escribir una ruta se borran las de debajo
+        for ancestor in path.ancestors() {
+            let Some(node) = self.entries.get(ancestor) else {
+                continue;
+            };
+            let is_self = ancestor == path;
+            return match node {
+                Node::Absent => Node::Absent,
+                Node::File(source) if is_self => Node::File(source.clone()),
+                Node::File(_) => Node::Absent,
+                Node::Dir(None) if is_self => Node::Dir(None),
+                Node::Dir(None) => Node::Absent,
+                Node::Dir(Some(source)) => {
+                    let rest = path.strip_prefix(ancestor).unwrap_or(Path::new(""));
+                    disk_node(&source.join(rest))
+                }
+            };
+        }
+        disk_node(path)
+    }
+
+    fn set(&mut self, path: &Path, node: Node) {
+        self.entries.retain(|p, _| !p.starts_with(path) || p == path);
+        self.entries.insert(path.to_path_buf(), node);
+    }
+
+    fn read(&self, path: &Path) -> Result<String, FsError> {
+        match self.lookup(path) {
+            Node::File(FileSource::Memory(content)) => Ok(content),
+            Node::File(FileSource::Disk(real)) => {
+                let file = file_io::read_versioned(&real)?;
+                if file.binary {
+                    return Err(FsError::NotAFile(path.display().to_string()));
+                }
+                Ok(file.content)
+            }
+            Node::Dir(_) => Err(FsError::NotAFile(path.display().to_string())),
+            Node::Absent => Err(FsError::NotFound(path.display().to_string())),
+        }
+    }
+}
+
+fn disk_node(path: &Path) -> Node {
+    match fs::symlink_metadata(path) {
+        Ok(m) if m.is_dir() => Node::Dir(Some(path.to_path_buf())),
+        Ok(_) => Node::File(FileSource::Disk(path.to_path_buf())),
+        Err(_) => Node::Absent,
+    }
+}
+
+/// Valida todo el lote y devuelve el plan a aplicar. No modifica nada en disco.
+fn plan<R>(operations: &[EditOperation], resolve: R) -> Result<Vec<Planned>, EditFailure>
+where
+    R: Fn(&str) -> Result<PathBuf, FsError>,
+{
+    let mut overlay = Overlay { entries: HashMap::new() };
+    let mut planned = Vec::with_capacity(operations.len());
+
+    for (index, operation) in operations.iter().enumerate() {
+        let step = plan_operation(operation, &resolve, &mut overlay)
+            .map_err(|e| EditFailure::at(index, e))?;
+        planned.push(step);
+    }
+
+    Ok(planned)
+}
+
+fn plan_operation<R>(operation: &EditOperation, resolve: &R, overlay: &mut Overlay) -> Result<Planned, FsError>
+where
+    R: Fn(&str) -> Result<PathBuf, FsError>,
+{
+    match operation {
+        EditOperation::CreateFile { path, content, overwrite } => {
+            let path = resolve(path)?;
+            check_parent(overlay, &path)?;
+            match overlay.lookup(&path) {
+                Node::Absent => {}
+                Node::File(_) if *overwrite => {}
+                Node::File(_) => return Err(FsError::AlreadyExists(path.display().to_string())),
+                Node::Dir(_) => return Err(FsError::NotAFile(path.display().to_string())),
+            }
+            overlay.set(&path, Node::File(FileSource::Memory(content.clone())));
+            Ok(Planned::WriteFile { path, content: content.clone() })
+        }
+        EditOperation::CreateFolder { path } => {
+            let path = resolve(path)?;
+            check_parent(overlay, &path)?;
+            match overlay.lookup(&path) {
+                Node::Absent => overlay.set(&path, Node::Dir(None)),
+                Node::Dir(_) => {}
+                Node::File(_) => return Err(FsError::AlreadyExists(path.display().to_string())),
+            }
+            Ok(Planned::CreateFolder { path })
+        }
+        EditOperation::Rename { from, to, overwrite } => {
+            let from = resolve(from)?;
+            let to = resolve(to)?;
+            let source = overlay.lookup(&from);
+            if let Node::Absent = source {
+                return Err(FsError::NotFound(from.display().to_string()));
+            }
+            if to.starts_with(&from) {
+                return Err(FsError::InvalidEdit(
+                    to.display().to_string(),
+                    "el destino está dentro del origen".to_string(),
+                ));
+            }
+            check_parent(overlay, &to)?;
+            match overlay.lookup(&to) {
+                Node::Absent => {}
+                Node::File(_) if *overwrite && matches!(source, Node::File(_)) => {}
+                _ => return Err(FsError::AlreadyExists(to.display().to_string())),
+            }
+            overlay.set(&from, Node::Absent);
+            overlay.set(&to, source);
+            Ok(Planned::Rename { from, to })
+        }
+        EditOperation::Delete { path } => {
+            let path = resolve(path)?;
+            if let Node::Absent = overlay.lookup(&path) {
+                return Err(FsError::NotFound(path.display().to_string()));
+            }
+            overlay.set(&path, Node::Absent);
+            Ok(Planned::Delete { path })
+        }
+        EditOperation::Edit { path, edits, expected_version } => {
+            let path = resolve(path)?;
+            if let (Some(expected), Node::File(FileSource::Disk(real))) = (expected_version, overlay.lookup(&path)) {
+                file_io::check_version(&real, expected)?;
+            }
+            let original = overlay.read(&path)?;
+            let content = apply_text_edits(&original, edits)
+                .map_err(|msg| FsError::InvalidEdit(path.display().to_string(), msg))?;
+            overlay.set(&path, Node::File(FileSource::Memory(content.clone())));
+            Ok(Planned::WriteFile { path, content })
+        }
+    }
+}
+
+// La carpeta padre tiene que existir o poder crearse (no puede ser un archivo)
+fn check_parent(overlay: &Overlay, path: &Path) -> Result<(), FsError> {
+    for ancestor in path.ancestors().skip(1) {
+        match overlay.lookup(ancestor) {
+            Node::Dir(_) => return Ok(()),
+            Node::File(_) => return Err(FsError::NotADirectory(ancestor.display().to_string())),
+            Node::Absent => {}
+        }
+    }
+    Ok(())
+}
+
+/// Aplica los reemplazos sobre `text`. Falla si un rango está fuera del texto o si dos se solapan.
+pub fn apply_text_edits(text: &str, edits: &[TextEdit]) -> Result<String, String> {
+    // Offset en bytes del inicio de cada línea
+    let mut line_starts = vec![0];
+    line_starts.extend(text.match_indices('\n').map(|(i, _)| i + 1));
+
+    let offset = |line: usize, column: usize| -> Result<usize, String> {
+        let start = *line_starts.get(line.wrapping_sub(1))
+            .ok_or_else(|| format!("línea {} fuera del archivo", line))?;
+        let end = line_starts.get(line)
+            .map(|next| next - 1)
+            .unwrap_or(text.len());
+        let content = text[start..end].trim_end_matches('\r');
+
+        if column == 0 {
+            return Err(format!("columna 0 inválida en la línea {}", line));
+        }
+        let target = column - 1;
+        let mut units = 0;
+        for (i, c) in content.char_indices() {
+            if units == target {
+                return Ok(start + i);
+            }
+            units += c.len_utf16();
+            if units > target {
+                return Err(format!("columna {} en medio de un carácter en la línea {}", column, line));
+            }
+        }
+        if units == target {
+            Ok(start + content.len())
+        } else {
+            Err(format!("columna {} fuera de la línea {}", column, line))
+        }
+    };
+
+    let mut ranges = Vec::with_capacity(edits.len());
+    for edit in edits {
+        let start = offset(edit.start_line, edit.start_column)?;
+        let end = offset(edit.end_line, edit.end_column)?;
+        if end < start {
+            return Err(format!("rango invertido en la línea {}", edit.start_line));
+        }
+        ranges.push((start, end, edit.new_text.as_str()));
+    }
+
+    ranges.sort_by_key(|(start, end, _)| (*start, *end));
+    for pair in ranges.windows(2) {
+        if pair[0].1 > pair[1].0 {
+            return Err("hay ediciones que se solapan".to_string());
+        }
+    }
+
+    let mut result = text.to_string();
+    for (start, end, new_text) in ranges.into_iter().rev() {
+        result.replace_range(start..end, new_text);
+    }
+    Ok(result)
+}
+
+// Cómo deshacer cada paso ya aplicado
+enum Undo {
+    RemoveFile(PathBuf),
+    RemoveDir(PathBuf),
+    Restore(PathBuf, Vec<u8>),
+    RenameBack { from: PathBuf, to: PathBuf },
+    Unstage { staged: PathBuf, original: PathBuf },
+}
+
+struct Transaction {
+    id: String,
+    journal: Vec<Undo>,
+    // Contador de elementos apartados: un lote puede apartar la misma ruta más de una vez
+    staged: usize,
+}
+
+impl Transaction {
+    fn ensure_dir(&mut self, dir: &Path) -> Result<(), FsError> {
+        let missing: Vec<&Path> = dir.ancestors()
+            .take_while(|a| !a.exists())
+            .collect();
+        for path in missing.into_iter().rev() {
+            fs::create_dir(path).map_err(|e| FsError::from_io(e, path))?;
+            self.journal.push(Undo::RemoveDir(path.to_path_buf()));
+        }
+        Ok(())
+    }
+
+    // Los borrados se renombran junto al original y solo se descartan al confirmar
+    fn stage(&mut self, path: &Path) -> Result<(), FsError> {
+        let name = path.file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or("item");
+        self.staged += 1;
+        let staged = path.with_file_name(format!(".{}.{}-{}.aeditor-tx", name, self.id, self.staged));
+        fs::rename(path, &staged).map_err(|e| FsError::from_io(e, path))?;
+        self.journal.push(Undo::Unstage {
+            staged,
+            original: path.to_path_buf(),
+        });
+        Ok(())
+    }
+
+    fn apply(&mut self, step: &Planned) -> Result<(), FsError> {
+        match step {
+            Planned::WriteFile { path, content } => {
+                if let Some(parent) = path.parent() {
+                    self.ensure_dir(parent)?;
+                }
+                match fs::read(path) {
+                    Ok(previous) => self.journal.push(Undo::Restore(path.clone(), previous)),
+                    Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                        self.journal.push(Undo::RemoveFile(path.clone()))
+                    }
+                    Err(e) => return Err(FsError::from_io(e, path)),
+                }
+                // Conservar codificación y saltos de línea si el archivo ya existía
+                let bytes = file_io::encode(path, content, file_io::detect_format(path))?;
+                file_io::write_atomic(path, &bytes)?;
+            }
+            Planned::CreateFolder { path } => self.ensure_dir(path)?,
+            Planned::Rename { from, to } => {
+                if let Some(parent) = to.parent() {
+                    self.ensure_dir(parent)?;
+                }
+                if fs::symlink_metadata(to).is_ok() {
+                    self.stage(to)?;
+                }
+                fs::rename(from, to).map_err(|e| FsError::from_io(e, from))?;
+                self.journal.push(Undo::RenameBack {
+                    from: from.clone(),
+                    to: to.clone(),
+                });
+            }
+            Planned::Delete { path } => self.stage(path)?,
+        }
+        Ok(())
+    }
+
+    fn rollback(self) -> Vec<String> {
+        let mut errors = Vec::new();
+        for undo in self.journal.into_iter().rev() {
+            let result = match &undo {
+                Undo::RemoveFile(path) => fs::remove_file(path).map_err(|e| (path.clone(), e.to_string())),
+                Undo::RemoveDir(path) => fs::remove_dir(path).map_err(|e| (path.clone(), e.to_string())),
+                Undo::Restore(path, bytes) => file_io::write_atomic(path, bytes)
+                    .map(|_| ())
+                    .map_err(|e| (path.clone(), e.to_string())),
+                Undo::RenameBack { from, to } => fs::rename(to, from).map_err(|e| (to.clone(), e.to_string())),
+                Undo::Unstage { staged, original } => fs::rename(staged, original)
+                    .map_err(|e| (original.clone(), e.to_string())),
+            };
+            if let Err((path, e)) = result {
+                errors.push(format!("{}: {}", path.display(), e));
+            }
+        }
+        errors
+    }
+
+    // Devuelve los elementos borrados/sobrescritos: (ruta temporal, ruta original)
+    fn commit(self) -> Vec<(PathBuf, PathBuf)> {
+        self.journal.into_iter()
+            .filter_map(|undo| match undo {
+                Undo::Unstage { staged, original } => Some((staged, original)),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+/// Solo valida el lote (rutas, existencia, rangos y versiones esperadas).
+pub fn validate<R>(operations: &[EditOperation], resolve: R) -> Result<(), EditFailure>
+where
+    R: Fn(&str) -> Result<PathBuf, FsError>,
+{
+    plan(operations, resolve).map(|_| ())
+}
+
+/// Valida y aplica el lote completo. Si un paso falla se deshacen los anteriores.
+/// `discard` recibe cada elemento borrado o sobrescrito, una vez confirmado el lote,
+/// como `(ruta temporal, ruta original)`.
+pub fn apply<R, D>(operations: &[EditOperation], resolve: R, mut discard: D) -> Result<WorkspaceEditResult, EditFailure>
+where
+    R: Fn(&str) -> Result<PathBuf, FsError>,
+    D: FnMut(&Path, &Path),
+{
+    let steps = plan(operations, resolve)?;
+
+    let mut transaction = Transaction {
+        id: uuid::Uuid::new_v4().simple().to_string()[..8].to_string(),
+        journal: Vec::new(),
+        staged: 0,
+    };
+    let mut changed_paths = Vec::new();
+
+    for (index, step) in steps.iter().enumerate() {
+        if let Err(error) = transaction.apply(step) {
+            return Err(EditFailure {
+                operation: index,
+                error,
+                rollback_errors: transaction.rollback(),
+            });
+        }
+
+        let touched: Vec<&PathBuf> = match step {
+            Planned::WriteFile { path, .. } | Planned::CreateFolder { path } | Planned::Delete { path } => vec![path],
+            Planned::Rename { from, to } => vec![from, to],
+        };
+        for path in touched {
+            let path = path.to_string_lossy().to_string();
+            if !changed_paths.contains(&path) {
+                changed_paths.push(path);
+            }
+        }
+    }
+
+    for (staged, original) in transaction.commit() {
+        discard(&staged, &original);
+    }
+
+    Ok(WorkspaceEditResult {
+        operations: operations.len(),
+        changed_paths,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_project() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("aeditor-tx-{}", uuid::Uuid::new_v4().simple()));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn steps_touching_twice(file: &Path) -> Vec<Planned> {
+        vec![
+            Planned::Delete { path: file.to_path_buf() },
+            Planned::WriteFile { path: file.to_path_buf(), content: "second".to_string() },
+            Planned::Delete { path: file.to_path_buf() },
+        ]
+    }
+
+    #[test]
+    fn rollback_restores_a_path_staged_twice() {
+        let dir = temp_project();
+        let file = dir.join("a.txt");
+        fs::write(&file, "original").unwrap();
+
+        let mut transaction = Transaction { id: "test".to_string(), journal: Vec::new(), staged: 0 };
+        for step in steps_touching_twice(&file) {
+            transaction.apply(&step).unwrap();
+        }
+        assert!(transaction.rollback().is_empty());
+        assert_eq!(fs::read_to_string(&file).unwrap(), "original");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn commit_keeps_every_staged_version() {
+        let dir = temp_project();
+        let file = dir.join("a.txt");
+        fs::write(&file, "original").unwrap();
+
+        // `a.txt` se aparta dos veces: al borrarlo y al sobrescribirlo con un rename
+        let operations = vec![
+            EditOperation::Delete { path: "a.txt".to_string() },
+            EditOperation::CreateFile { path: "a.txt".to_string(), content: "second".to_string(), overwrite: false },
+            EditOperation::Rename { from: "a.txt".to_string(), to: "b.txt".to_string(), overwrite: false },
+            EditOperation::CreateFile { path: "a.txt".to_string(), content: "third".to_string(), overwrite: false },
+            EditOperation::Rename { from: "b.txt".to_string(), to: "a.txt".to_string(), overwrite: true },
+        ];
+        let mut discarded = Vec::new();
+        apply(&operations, |p| Ok(dir.join(p)), |staged, _| {
+            discarded.push(fs::read_to_string(staged).unwrap());
+        })
+        .unwrap();
+
+        assert_eq!(discarded, vec!["original", "third"]);
+        assert_eq!(fs::read_to_string(&file).unwrap(), "second");
+        assert!(!dir.join("b.txt").exists());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn columns_count_utf16_units() {
+        // 🎉 ocupa dos unidades UTF-16: "x" empieza en la columna 11, no en la 10
+        let text = "log(\"🎉\", x);\n";
+        let edit = |column| TextEdit {
+            start_line: 1,
+            start_column: column,
+            end_line: 1,
+            end_column: column + 1,
+            new_text: "y".to_string(),
+        };
+
+        assert_eq!(apply_text_edits(text, &[edit(11)]).unwrap(), "log(\"🎉\", y);\n");
+        assert!(apply_text_edits(text, &[edit(7)]).is_err());
+    }
+}
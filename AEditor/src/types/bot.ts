@@ -37,6 +37,12 @@ export interface ProjectStats {
   customEvents: number;
   totalCommands: number;
   totalEvents: number;
+  buttons: number;
+  modals: number;
+  selectMenus: number;
+  contextMenus: number;
+  totalComponents: number;
+  eventsByName: Record<string, number>;
 }
 
 export interface FileInfo {
@@ -46,5 +52,418 @@ export interface FileInfo {
   type: "command" | "event";
   commandType?: "message" | "slash";
   eventType?: "standard" | "extra";
+  eventName?: string; // Evento de discord.js (messageCreate, ready...)
+  once?: boolean;
   folder?: string;
 }
+
+export interface ComponentInfo {
+  name: string;
+  path: string;
+  relativePath: string;
+  kind: "button" | "modal" | "selectMenu" | "contextMenu" | "unknown";
+  customId?: string;
+  folder?: string;
+}
+
+export interface CustomIdReference {
+  customId: string;
+  file: string;
+  line: number;
+}
+
+export interface ComponentScan {
+  handlers: ComponentInfo[];
+  unhandledIds: CustomIdReference[];
+  unusedHandlers: ComponentInfo[];
+}
+
+export interface EventBinding {
+  eventName: string;
+  raw: string;
+  once: boolean;
+  known: boolean;
+  file: string;
+  line: number;
+}
+
+export interface EventScan {
+  bindings: EventBinding[];
+  duplicates: Record<string, EventBinding[]>;
+  unknown: EventBinding[];
+  counts: Record<string, number>;
+}
+
+// Estructura del proyecto declarada en aeditor.json (rutas relativas a la raíz)
+export interface ProjectLayout {
+  srcDir: string;
+  commandsDir: string;
+  eventsDir: string;
+  componentsDir: string;
+  prismaSchema: string;
+  envFile: string;
+}
+
+// Elemento borrado que se conserva en la papelera del editor
+export interface TrashEntry {
+  id: string;
+  name: string;
+  originalPath: string;
+  isDir: boolean;
+  deletedAt: number;
+  size: number;
+}
+
+// Versión en disco devuelta por read_file_content / write_file_content
+export interface FileVersion {
+  hash: string;
+  modified: number;
+  size: number;
+}
+
+export type TextEncoding = 'utf8' | 'utf8Bom' | 'utf16Le' | 'utf16Be' | 'latin1';
+export type LineEnding = 'lf' | 'crlf';
+
+// Formato en disco; write_file_content lo conserva si no se pasa otro
+export interface TextFormat {
+  encoding: TextEncoding;
+  lineEnding: LineEnding;
+}
+
+// Límites para archivos grandes (bytes): editor normal / solo lectura / streaming
+export interface FileLimits {
+  readOnlySize: number;
+  streamSize: number;
+}
+
+export interface FileProbe {
+  size: number;
+  lineCount: number | null;
+  encoding: TextEncoding;
+  binary: boolean;
+  openMode: 'editor' | 'readOnly' | 'stream';
+}
+
+export interface LineChunk {
+  startLine: number;
+  lines: string[];
+  totalLines: number;
+}
+
+export interface ByteChunk {
+  offset: number;
+  nextOffset: number;
+  size: number;
+  content: string;
+  eof: boolean;
+}
+
+// `content` siempre usa \n; si `binary` es true, viene vacío
+export interface FileContent {
+  content: string;
+  version: FileVersion;
+  format: TextFormat;
+  binary: boolean;
+  mixedLineEndings: boolean;
+}
+
+// Edición de workspace transaccional (apply_workspace_edit). Líneas y columnas desde 1,
+// columnas en unidades UTF-16 como las de Monaco
+export interface TextEdit {
+  startLine: number;
+  startColumn: number;
+  endLine: number;
+  endColumn: number;
+  newText: string;
+}
+
+export type EditOperation =
+  | { type: 'createFile'; path: string; content?: string; overwrite?: boolean }
+  | { type: 'createFolder'; path: string }
+  | { type: 'rename'; from: string; to: string; overwrite?: boolean }
+  | { type: 'delete'; path: string }
+  | { type: 'edit'; path: string; edits: TextEdit[]; expectedVersion?: FileVersion };
+
+export interface WorkspaceEditResult {
+  operations: number;
+  changedPaths: string[];
+}
+
+// Vista previa de preview_rename_module; `operations` se aplica con apply_workspace_edit
+export interface ReferenceChange {
+  file: string;
+  line: number;
+  before: string;
+  after: string;
+}
+
+export interface RenamePreview {
+  oldPath: string;
+  newPath: string;
+  oldName: string | null;
+  newName: string | null;
+  changes: ReferenceChange[];
+  operations: EditOperation[];
+}
+
+export type ScaffoldKind = 'slashCommand' | 'messageCommand' | 'event' | 'button' | 'modal' | 'selectMenu';
+
+export interface TemplateInfo {
+  id: string;
+  kind: ScaffoldKind;
+  source: 'project' | 'builtin';
+  file: string | null;
+  outputPath: string;
+  variables: string[];
+}
+
+export interface ScaffoldRequest {
+  kind?: ScaffoldKind;
+  template?: string;
+  name: string;
+  description?: string;
+  category?: string;
+  customId?: string;
+  eventName?: string;
+  once?: boolean;
+  cooldown?: number;
+  options?: { name: string; description: string; type: string; required?: boolean }[];
+  variables?: Record<string, string>;
+}
+
+export interface ScaffoldResult {
+  template: string;
+  path: string;
+  relativePath: string;
+  content: string;
+}
+
+export interface CommandRename {
+  file: string;
+  oldName: string;
+  newName: string;
+}
+
+export interface CopyResult {
+  path: string;
+  copied: string[];
+  renamedCommands: CommandRename[];
+}
+
+export interface HistoryEntry {
+  id: string;
+  hash: string;
+  timestamp: number;
+  size: number;
+  source: 'save' | 'external' | 'restore';
+}
+
+export interface HistorySettings {
+  maxEntries: number;
+  maxAgeDays: number;
+  maxSizePerFile: number;
+  maxFileSize: number;
+}
+
+export interface DiffLine {
+  kind: 'equal' | 'added' | 'removed';
+  oldLine: number | null;
+  newLine: number | null;
+  text: string;
+}
+
+export interface HistoryDiff {
+  oldContent: string;
+  newContent: string;
+  lines: DiffLine[];
+  added: number;
+  removed: number;
+}
+
+export interface CompletionStats {
+  requests: number;
+  cacheHits: number;
+  cancelled: number;
+  rateLimited: number;
+  apiCalls: number;
+}
+
+export interface ContextSection {
+  kind: 'imports' | 'signatures' | 'prisma' | 'example';
+  source: string;
+  content: string;
+  tokens: number;
+}
+
+export interface ProjectContext {
+  sections: ContextSection[];
+  tokens: number;
+  budget: number;
+  truncated: boolean;
+}
+
+export interface SemanticHit {
+  file: string;
+  startLine: number;
+  endLine: number;
+  score: number;
+  text: string;
+}
+
+export interface SemanticIndexStatus {
+  projectRoot: string | null;
+  embedder: string | null;
+  files: number;
+  chunks: number;
+  dimensions: number;
+}
+
+export interface SemanticSyncResult {
+  updatedFiles: number;
+  removedFiles: number;
+  embeddedChunks: number;
+  totalChunks: number;
+}
+
+// Pasos y resultado de run_agent; las ediciones se aplican con apply_agent_edits
+export interface AgentStep {
+  step: number;
+  tool: string;
+  args: Record<string, unknown>;
+  target: string;
+  result: string;
+  error: boolean;
+}
+
+export interface ProposedEdit {
+  path: string;
+  description: string;
+  isNew: boolean;
+  expectedVersion: FileVersion | null;
+  diff: HistoryDiff;
+}
+
+export interface AgentRun {
+  id: string;
+  task: string;
+  summary: string;
+  steps: AgentStep[];
+  edits: ProposedEdit[];
+  status: 'finished' | 'maxSteps' | 'cancelled';
+  usage: UsageTotals;
+}
+
+export interface AgentApplyResult {
+  backupId: string;
+  result: WorkspaceEditResult;
+}
+
+// Resultado de generate_command; se escribe con create_generated_command
+export interface TypeCheckError {
+  id: string;
+  severity: 'error' | 'warning';
+  message: string;
+  file: string;
+  line: number;
+  column: number;
+  code: string | null;
+  source: string | null;
+}
+
+export interface TypeCheck {
+  // false si no se pudo ejecutar tsc
+  checked: boolean;
+  errors: TypeCheckError[];
+  message: string | null;
+}
+
+export interface GeneratedCommand {
+  name: string;
+  category: string;
+  path: string;
+  relativePath: string;
+  content: string;
+  exists: boolean;
+  context: ContextSection[];
+  typeCheck: TypeCheck;
+  attempts: number;
+  usage: UsageTotals;
+}
+
+// Conversaciones persistentes (send_chat_message y compañía)
+export interface ChatAttachment {
+  path: string;
+  startLine?: number | null;
+  endLine?: number | null;
+  // Vacío: el backend lee el archivo (o el rango)
+  content?: string;
+}
+
+export interface ChatMessage {
+  id: string;
+  role: 'user' | 'assistant';
+  text: string;
+  attachments: ChatAttachment[];
+  timestamp: number;
+  tokens: number;
+}
+
+export interface ChatSession {
+  id: string;
+  title: string;
+  createdAt: number;
+  updatedAt: number;
+  messages: ChatMessage[];
+  summary: string | null;
+  summarizedUntil: number;
+}
+
+export interface ChatSessionInfo {
+  id: string;
+  title: string;
+  createdAt: number;
+  updatedAt: number;
+  messageCount: number;
+  tokens: number;
+}
+
+// Consumo de la IA (get_ai_usage) y límites (set_ai_budgets)
+export interface UsageTotals {
+  requests: number;
+  promptTokens: number;
+  candidatesTokens: number;
+  thoughtTokens: number;
+}
+
+export interface UsageBudgets {
+  dailyTokens: number | null;
+  monthlyTokens: number | null;
+  dailyRequests: number | null;
+  monthlyRequests: number | null;
+}
+
+export interface UsageBreakdown {
+  model: string;
+  feature: 'completion' | 'ask' | 'chat' | 'agent' | 'command';
+  totals: UsageTotals;
+}
+
+export interface UsageSummary {
+  // Fechas en UTC
+  today: string;
+  month: string;
+  daily: UsageTotals;
+  monthly: UsageTotals;
+  byModel: UsageBreakdown[];
+  history: { date: string; totals: UsageTotals }[];
+  budgets: UsageBudgets;
+  // Con algún límite superado las sugerencias inline están desactivadas
+  exceeded: ('dailyTokens' | 'monthlyTokens' | 'dailyRequests' | 'monthlyRequests')[];
+}
+
+export interface ApiKeyStatus {
+  configured: boolean;
+  last4: string | null;
+  // 'keyring', 'encryptedFile' o 'none'
+  storage: string;
+}
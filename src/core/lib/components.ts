@@ -48,6 +48,8 @@ export function loadComponents(
 
     if (!file.endsWith(".ts") && !file.endsWith(".js")) continue;
     if (file.endsWith(".d.ts")) continue;
+    // Ocultos: temporales del editor (p. ej. la comprobación de tipos de AEditor)
+    if (file.startsWith(".")) continue;
 
     try {
       const imported = require(fullPath);
@@ -25,6 +25,8 @@ export function loadCommands(dir: string = path.resolve(__dirname, "../../comman
 
         if (!file.endsWith('.ts') && !file.endsWith('.js')) continue;
         if (file.endsWith('.d.ts')) continue;
+        // Ocultos: temporales del editor (p. ej. la comprobación de tipos de AEditor)
+        if (file.startsWith('.')) continue;
 
         const imported = require(fullPath);
         const command = imported.command ?? imported.default ?? imported;
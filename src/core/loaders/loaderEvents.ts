@@ -22,6 +22,8 @@ export function loadEvents(dir: string = path.resolve(__dirname, "../../events")
         }
 
         if (!file.endsWith(".ts") && !file.endsWith(".js")) continue;
+        // Ocultos: temporales del editor (p. ej. la comprobación de tipos de AEditor)
+        if (file.startsWith(".")) continue;
 
         const imported = require(fullPath);
         const event = imported.default ?? imported;